@@ -0,0 +1,831 @@
+//! Player-vs-house games backed by a house vault whose bankroll is owned by LP shares
+
+use crate::*;
+#[cfg(feature = "ephemeral")]
+use ephemeral_vrf_sdk::anchor::{vrf, vrf_callback};
+#[cfg(feature = "ephemeral")]
+use ephemeral_vrf_sdk::instructions::{create_request_randomness_ix, RequestRandomnessParams};
+#[cfg(feature = "ephemeral")]
+use ephemeral_vrf_sdk::types::SerializableAccountMeta;
+
+pub(crate) fn initialize_house_vault(
+    ctx: Context<InitializeHouseVault>,
+    house_authority: Pubkey,
+    params: HouseParams,
+) -> Result<()> {
+    validate_house_params(&params)?;
+
+    let vault = &mut ctx.accounts.house_vault;
+    vault.house_authority = house_authority;
+    vault.edge_bps = params.edge_bps;
+    vault.max_exposure_per_game = params.max_exposure_per_game;
+    vault.max_exposure_per_slot = params.max_exposure_per_slot;
+    vault.move_timeout = params.move_timeout;
+    vault.withdrawal_cooldown = params.withdrawal_cooldown;
+    vault.reserved = 0;
+    vault.exposure_slot = 0;
+    vault.slot_exposure = 0;
+    vault.total_games = 0;
+    vault.total_wagered = 0;
+    vault.total_shares = 0;
+
+    msg!("House vault initialized with edge {} bps", params.edge_bps);
+    Ok(())
+}
+
+pub(crate) fn configure_house(
+    ctx: Context<ConfigureHouse>,
+    house_authority: Pubkey,
+    params: HouseParams,
+) -> Result<()> {
+    validate_house_params(&params)?;
+
+    let vault = &mut ctx.accounts.house_vault;
+    vault.house_authority = house_authority;
+    vault.edge_bps = params.edge_bps;
+    vault.max_exposure_per_game = params.max_exposure_per_game;
+    vault.max_exposure_per_slot = params.max_exposure_per_slot;
+    vault.move_timeout = params.move_timeout;
+    vault.withdrawal_cooldown = params.withdrawal_cooldown;
+
+    msg!("House configured: edge {} bps, max exposure {} per game / {} per slot",
+        params.edge_bps,
+        params.max_exposure_per_game,
+        params.max_exposure_per_slot
+    );
+    Ok(())
+}
+
+pub(crate) fn open_lp_position(ctx: Context<OpenLpPosition>) -> Result<()> {
+    let position = &mut ctx.accounts.lp_position;
+    position.owner = ctx.accounts.user.key();
+    position.shares = 0;
+    position.pending_withdrawal_shares = 0;
+    position.withdrawal_requested_at = 0;
+    position.total_deposited = 0;
+    position.total_withdrawn = 0;
+
+    msg!("LP position opened for {}", position.owner);
+    Ok(())
+}
+
+pub(crate) fn deposit_liquidity(ctx: Context<DepositLiquidity>, amount: u64) -> Result<()> {
+    require!(amount > 0, GameError::InvalidStakeAmount);
+
+    let vault = &ctx.accounts.house_vault;
+    let equity = house_equity(vault)?;
+    let (shares, dead_shares) = if vault.total_shares == 0 {
+        require!(amount > LP_DEAD_SHARES, GameError::InvalidStakeAmount);
+        (amount - LP_DEAD_SHARES, equity + LP_DEAD_SHARES)
+    } else {
+        require!(equity > 0, GameError::InsufficientHouseBalance);
+        ((amount as u128 * vault.total_shares as u128 / equity as u128) as u64, 0)
+    };
+    require!(shares > 0, GameError::InvalidStakeAmount);
+
+    anchor_lang::solana_program::program::invoke(
+        &anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.house_vault.key(),
+            amount,
+        ),
+        &[
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.house_vault.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ],
+    )?;
+
+    ctx.accounts.house_vault.total_shares += shares + dead_shares;
+    let position = &mut ctx.accounts.lp_position;
+    position.shares += shares;
+    position.total_deposited += amount;
+
+    msg!("LP {} deposited {} lamports for {} shares", position.owner, amount, shares);
+    Ok(())
+}
+
+pub(crate) fn request_lp_withdrawal(ctx: Context<RequestLpWithdrawal>, shares: u64) -> Result<()> {
+    let position = &mut ctx.accounts.lp_position;
+    require!(shares > 0 && shares <= position.shares, GameError::InsufficientShares);
+
+    position.pending_withdrawal_shares = shares;
+    position.withdrawal_requested_at = Clock::get()?.unix_timestamp;
+
+    msg!("LP {} requested withdrawal of {} shares", position.owner, shares);
+    Ok(())
+}
+
+pub(crate) fn cancel_lp_withdrawal(ctx: Context<RequestLpWithdrawal>) -> Result<()> {
+    let position = &mut ctx.accounts.lp_position;
+    require!(position.pending_withdrawal_shares > 0, GameError::NoPendingWithdrawal);
+
+    position.pending_withdrawal_shares = 0;
+    position.withdrawal_requested_at = 0;
+
+    msg!("LP {} cancelled pending withdrawal", position.owner);
+    Ok(())
+}
+
+pub(crate) fn complete_lp_withdrawal(ctx: Context<CompleteLpWithdrawal>) -> Result<()> {
+    let position = &ctx.accounts.lp_position;
+    let vault = &ctx.accounts.house_vault;
+    let shares = position.pending_withdrawal_shares;
+    require!(shares > 0, GameError::NoPendingWithdrawal);
+    let ready_at = position.withdrawal_requested_at + vault.withdrawal_cooldown;
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= ready_at, GameError::WithdrawalCooldown);
+    require!(now < ready_at + LP_WITHDRAWAL_WINDOW, GameError::WithdrawalExpired);
+
+    let amount = (shares as u128 * house_equity(vault)? as u128 / vault.total_shares as u128) as u64;
+    require!(amount <= house_free_balance(vault)?, GameError::InsufficientHouseBalance);
+
+    move_lamports(
+        &ctx.accounts.house_vault.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        amount,
+    )?;
+
+    ctx.accounts.house_vault.total_shares -= shares;
+    let position = &mut ctx.accounts.lp_position;
+    position.shares -= shares;
+    position.pending_withdrawal_shares = 0;
+    position.withdrawal_requested_at = 0;
+    position.total_withdrawn += amount;
+
+    msg!("LP {} redeemed {} shares for {} lamports", position.owner, shares, amount);
+    Ok(())
+}
+
+pub(crate) fn close_lp_position(ctx: Context<CloseLpPosition>) -> Result<()> {
+    require!(ctx.accounts.lp_position.shares == 0, GameError::InsufficientShares);
+
+    msg!("LP position closed for {}", ctx.accounts.user.key());
+    Ok(())
+}
+
+pub(crate) fn create_house_game(
+    ctx: Context<CreateHouseGame>,
+    game_id: String,
+    stake_amount: u64,
+    move_commitment: [u8; 32],
+) -> Result<()> {
+    require!(game_id.len() <= 32, GameError::GameIdTooLong);
+    require!(stake_amount > 0, GameError::InvalidStakeAmount);
+    require!(
+        **ctx.accounts.user.lamports.borrow() >= stake_amount,
+        GameError::InsufficientSol
+    );
+
+    let vault = &ctx.accounts.house_vault;
+    let max_payout = house_win_payout(stake_amount, vault.edge_bps);
+    let exposure = max_payout.saturating_sub(stake_amount);
+    require!(exposure <= vault.max_exposure_per_game, GameError::HouseExposureExceeded);
+    require!(exposure <= house_free_balance(vault)?, GameError::InsufficientHouseBalance);
+
+    let clock = Clock::get()?;
+    let slot_exposure = if vault.exposure_slot == clock.slot {
+        vault.slot_exposure
+    } else {
+        0
+    };
+    require!(
+        slot_exposure + exposure <= vault.max_exposure_per_slot,
+        GameError::HouseExposureExceeded
+    );
+
+    anchor_lang::solana_program::program::invoke(
+        &anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.house_game.key(),
+            stake_amount,
+        ),
+        &[
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.house_game.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ],
+    )?;
+
+    let vault = &mut ctx.accounts.house_vault;
+    vault.reserved += exposure;
+    vault.exposure_slot = clock.slot;
+    vault.slot_exposure = slot_exposure + exposure;
+    vault.total_games += 1;
+    vault.total_wagered += stake_amount;
+
+    let house_game = &mut ctx.accounts.house_game;
+    house_game.game_id = game_id;
+    house_game.player = ctx.accounts.user.key();
+    house_game.stake_amount = stake_amount;
+    house_game.max_payout = max_payout;
+    house_game.exposure = exposure;
+    house_game.move_commitment = move_commitment;
+    house_game.player_move = None;
+    house_game.player_nonce = 0;
+    house_game.house_seed_hash = None;
+    house_game.house_move = None;
+    house_game.phase = HousePhase::AwaitingHouseSeed;
+    house_game.result = None;
+    house_game.deadline = clock.unix_timestamp + vault.move_timeout;
+    house_game.created_at = clock.unix_timestamp;
+
+    msg!("House game {} created by {} for {} lamports", house_game.game_id, house_game.player, stake_amount);
+    Ok(())
+}
+
+pub(crate) fn commit_house_seed(
+    ctx: Context<CommitHouseSeed>,
+    _game_id: String,
+    seed_hash: [u8; 32],
+) -> Result<()> {
+    let house_game = &mut ctx.accounts.house_game;
+    require!(house_game.phase == HousePhase::AwaitingHouseSeed, GameError::WrongPhase);
+
+    house_game.house_seed_hash = Some(seed_hash);
+    house_game.phase = HousePhase::AwaitingPlayerReveal;
+    house_game.deadline = Clock::get()?.unix_timestamp + ctx.accounts.house_vault.move_timeout;
+
+    msg!("House seed committed for house game {}", house_game.game_id);
+    Ok(())
+}
+
+#[cfg(feature = "ephemeral")]
+pub(crate) fn request_house_randomness(ctx: Context<RequestHouseRandomness>, _game_id: String) -> Result<()> {
+    let house_game = &ctx.accounts.house_game;
+    require!(house_game.phase == HousePhase::AwaitingHouseSeed, GameError::WrongPhase);
+
+    let ix = create_request_randomness_ix(RequestRandomnessParams {
+        payer: ctx.accounts.user.key(),
+        oracle_queue: ctx.accounts.oracle_queue.key(),
+        callback_program_id: crate::ID,
+        callback_discriminator: instruction::HouseVrfCallback::DISCRIMINATOR.to_vec(),
+        caller_seed: house_game.move_commitment,
+        accounts_metas: Some(vec![
+            SerializableAccountMeta {
+                pubkey: house_game.key(),
+                is_signer: false,
+                is_writable: true,
+            },
+            SerializableAccountMeta {
+                pubkey: ctx.accounts.house_vault.key(),
+                is_signer: false,
+                is_writable: false,
+            },
+        ]),
+        ..Default::default()
+    });
+    ctx.accounts.invoke_signed_vrf(&ctx.accounts.user.to_account_info(), &ix)?;
+
+    msg!("VRF randomness requested for house game {}", house_game.game_id);
+    Ok(())
+}
+
+#[cfg(feature = "ephemeral")]
+pub(crate) fn house_vrf_callback(ctx: Context<HouseVrfCallback>, randomness: [u8; 32]) -> Result<()> {
+    let house_game = &mut ctx.accounts.house_game;
+    require!(house_game.phase == HousePhase::AwaitingHouseSeed, GameError::WrongPhase);
+
+    house_game.house_move = Some(house_move_from_randomness(&randomness));
+    house_game.phase = HousePhase::AwaitingPlayerReveal;
+    house_game.deadline = Clock::get()?.unix_timestamp + ctx.accounts.house_vault.move_timeout;
+
+    msg!("VRF randomness received for house game {}", house_game.game_id);
+    Ok(())
+}
+
+pub(crate) fn reveal_house_move(
+    ctx: Context<RevealHouseMove>,
+    _game_id: String,
+    player_move: Move,
+    nonce: u64,
+) -> Result<()> {
+    let house_game = &mut ctx.accounts.house_game;
+    require!(house_game.phase == HousePhase::AwaitingPlayerReveal, GameError::WrongPhase);
+    require!(
+        house_game.move_commitment == hash_move(player_move, nonce),
+        GameError::InvalidCommitment
+    );
+
+    house_game.player_move = Some(player_move);
+    house_game.player_nonce = nonce;
+    if house_game.house_move.is_some() {
+        return settle_house_game(
+            &mut ctx.accounts.house_game,
+            &mut ctx.accounts.house_vault,
+            &ctx.accounts.user.to_account_info(),
+            &mut ctx.accounts.user_profile,
+        );
+    }
+    house_game.phase = HousePhase::AwaitingHouseReveal;
+    house_game.deadline = Clock::get()?.unix_timestamp + ctx.accounts.house_vault.move_timeout;
+    msg!("Player move revealed for house game {}", house_game.game_id);
+    Ok(())
+}
+
+pub(crate) fn reveal_house_seed(
+    ctx: Context<RevealHouseSeed>,
+    _game_id: String,
+    seed: [u8; 32],
+) -> Result<()> {
+    let house_game = &mut ctx.accounts.house_game;
+    require!(house_game.phase == HousePhase::AwaitingHouseReveal, GameError::WrongPhase);
+    let seed_hash = house_game.house_seed_hash.ok_or(GameError::WrongPhase)?;
+    require!(
+        anchor_lang::solana_program::hash::hash(&seed).to_bytes() == seed_hash,
+        GameError::InvalidCommitment
+    );
+
+    let mut data = seed.to_vec();
+    data.extend_from_slice(&house_game.player_nonce.to_le_bytes());
+    let randomness = anchor_lang::solana_program::hash::hash(&data).to_bytes();
+    house_game.house_move = Some(house_move_from_randomness(&randomness));
+
+    settle_house_game(
+        &mut ctx.accounts.house_game,
+        &mut ctx.accounts.house_vault,
+        &ctx.accounts.player.to_account_info(),
+        &mut ctx.accounts.player_profile,
+    )
+}
+
+pub(crate) fn claim_house_timeout(ctx: Context<ClaimHouseTimeout>, _game_id: String) -> Result<()> {
+    let house_game = &ctx.accounts.house_game;
+    require!(house_game.phase != HousePhase::Settled, GameError::GameAlreadySettled);
+    require!(
+        Clock::get()?.unix_timestamp >= house_game.deadline,
+        GameError::TimeoutNotReached
+    );
+
+    let result = match house_game.phase {
+        HousePhase::AwaitingHouseSeed => RoundResult::Draw,
+        HousePhase::AwaitingPlayerReveal => RoundResult::Player2Win,
+        _ => RoundResult::Player1Win,
+    };
+    msg!("House game {} timed out", house_game.game_id);
+
+    pay_out_house_game(
+        &mut ctx.accounts.house_game,
+        &mut ctx.accounts.house_vault,
+        &ctx.accounts.player.to_account_info(),
+        &mut ctx.accounts.player_profile,
+        result,
+    )
+}
+
+pub(crate) fn close_house_game(ctx: Context<CloseHouseGame>, _game_id: String) -> Result<()> {
+    require!(
+        ctx.accounts.house_game.phase == HousePhase::Settled,
+        GameError::GameNotFinished
+    );
+
+    msg!("House game {} closed", ctx.accounts.house_game.game_id);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeHouseVault<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 2 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8, // discriminator + house vault data + withdrawal cooldown + total shares
+        seeds = [b"house_vault"],
+        bump
+    )]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureHouse<'info> {
+    #[account(mut, seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenLpPosition<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + 8 + 8 + 8 + 8, // discriminator + owner + shares + pending shares + requested_at + totals
+        seeds = [b"lp_position", user.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositLiquidity<'info> {
+    #[account(mut, seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(
+        mut,
+        seeds = [b"lp_position", user.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestLpWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"lp_position", user.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteLpWithdrawal<'info> {
+    #[account(mut, seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(
+        mut,
+        seeds = [b"lp_position", user.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseLpPosition<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"lp_position", user.key().as_ref()],
+        bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CreateHouseGame<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + (4 + 32) + 32 + 8 + 8 + 8 + 32 + 2 + 8 + 33 + 2 + 1 + 2 + 8 + 8, // discriminator + house game data
+        seeds = [b"house_game", game_id.as_bytes()],
+        bump
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(mut, seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CommitHouseSeed<'info> {
+    #[account(
+        mut,
+        seeds = [b"house_game", game_id.as_bytes()],
+        bump
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(seeds = [b"house_vault"], bump, has_one = house_authority @ GameError::Unauthorized)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    pub house_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct RevealHouseMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"house_game", game_id.as_bytes()],
+        bump,
+        constraint = house_game.player == user.key() @ GameError::NotAPlayer
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(mut, seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[cfg(feature = "ephemeral")]
+#[vrf]
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct RequestHouseRandomness<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [b"house_game", game_id.as_bytes()],
+        bump,
+        constraint = house_game.player == user.key() @ GameError::NotAPlayer
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    /// CHECK: MagicBlock VRF oracle queue
+    #[account(mut, address = ephemeral_vrf_sdk::consts::DEFAULT_QUEUE)]
+    pub oracle_queue: AccountInfo<'info>,
+}
+
+#[cfg(feature = "ephemeral")]
+#[vrf_callback]
+#[derive(Accounts)]
+pub struct HouseVrfCallback<'info> {
+    #[account(mut)]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct RevealHouseSeed<'info> {
+    #[account(
+        mut,
+        seeds = [b"house_game", game_id.as_bytes()],
+        bump
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(mut, seeds = [b"house_vault"], bump, has_one = house_authority @ GameError::Unauthorized)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", house_game.player.as_ref()],
+        bump
+    )]
+    pub player_profile: Account<'info, UserProfile>,
+
+    /// CHECK: This account is checked against the house game's player field
+    #[account(mut, address = house_game.player)]
+    pub player: UncheckedAccount<'info>,
+
+    pub house_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct ClaimHouseTimeout<'info> {
+    #[account(
+        mut,
+        seeds = [b"house_game", game_id.as_bytes()],
+        bump
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(mut, seeds = [b"house_vault"], bump)]
+    pub house_vault: Account<'info, HouseVault>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", house_game.player.as_ref()],
+        bump
+    )]
+    pub player_profile: Account<'info, UserProfile>,
+
+    /// CHECK: This account is checked against the house game's player field
+    #[account(mut, address = house_game.player)]
+    pub player: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CloseHouseGame<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"house_game", game_id.as_bytes()],
+        bump,
+        constraint = house_game.player == user.key() @ GameError::NotAPlayer
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// Program-owned bankroll for player-vs-house games
+#[account]
+pub struct HouseVault {
+    pub house_authority: Pubkey, // Commits and reveals house seeds
+    pub edge_bps: u16, // House edge taken from a player's winning payout
+    pub max_exposure_per_game: u64,
+    pub max_exposure_per_slot: u64,
+    pub move_timeout: i64, // Seconds allowed for each step before a timeout can be claimed
+    pub reserved: u64, // House's potential loss on open games
+    pub exposure_slot: u64,
+    pub slot_exposure: u64, // Exposure taken on during `exposure_slot`
+    pub total_games: u64,
+    pub total_wagered: u64,
+    pub withdrawal_cooldown: i64, // Seconds between an LP withdrawal request and its payout
+    pub total_shares: u64,
+}
+
+/// Time after the cooldown in which a withdrawal must be completed before the
+/// request lapses, so a request can't be held open as a free option on house results
+pub const LP_WITHDRAWAL_WINDOW: i64 = 24 * 60 * 60;
+
+/// Shares minted to nobody on the first deposit. Keeps the share price from
+/// being inflated with a donation to round later depositors down to zero.
+pub const LP_DEAD_SHARES: u64 = 1000;
+
+/// A liquidity provider's share of the house vault
+#[account]
+pub struct LpPosition {
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub pending_withdrawal_shares: u64,
+    pub withdrawal_requested_at: i64,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+}
+
+/// Single-round game between a player and the house. The player plays as
+/// player 1 in `determine_winner`.
+#[account]
+pub struct HouseGame {
+    pub game_id: String,
+    pub player: Pubkey,
+    pub stake_amount: u64,
+    pub max_payout: u64, // Paid to the player on a win
+    pub exposure: u64, // Portion of `max_payout` reserved in the vault
+    pub move_commitment: [u8; 32],
+    pub player_move: Option<Move>,
+    pub player_nonce: u64,
+    pub house_seed_hash: Option<[u8; 32]>,
+    pub house_move: Option<Move>,
+    pub phase: HousePhase,
+    pub result: Option<RoundResult>,
+    pub deadline: i64,
+    pub created_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct HouseParams {
+    pub edge_bps: u16,
+    pub max_exposure_per_game: u64,
+    pub max_exposure_per_slot: u64,
+    pub move_timeout: i64,
+    pub withdrawal_cooldown: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum HousePhase {
+    AwaitingHouseSeed, // Seed commitment or VRF randomness
+    AwaitingPlayerReveal,
+    AwaitingHouseReveal,
+    Settled,
+}
+
+pub fn validate_house_params(params: &HouseParams) -> Result<()> {
+    require!(params.edge_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+    require!(params.move_timeout > 0, GameError::InvalidTimeout);
+    require!(params.withdrawal_cooldown >= 0, GameError::InvalidTimeout);
+    require!(
+        params.max_exposure_per_game <= params.max_exposure_per_slot,
+        GameError::HouseExposureExceeded
+    );
+    Ok(())
+}
+
+/// What a winning player receives: both stakes minus the house edge
+pub fn house_win_payout(stake_amount: u64, edge_bps: u16) -> u64 {
+    let gross = stake_amount * 2;
+    gross - gross * edge_bps as u64 / 10000
+}
+
+/// Vault lamports backing the LP shares (everything above rent)
+pub fn house_equity(vault: &Account<HouseVault>) -> Result<u64> {
+    let info = vault.to_account_info();
+    let rent = Rent::get()?.minimum_balance(info.data_len());
+    Ok(info.lamports().saturating_sub(rent))
+}
+
+/// Vault lamports not needed for rent or reserved for open games
+pub fn house_free_balance(vault: &Account<HouseVault>) -> Result<u64> {
+    Ok(house_equity(vault)?.saturating_sub(vault.reserved))
+}
+
+pub fn house_move_from_randomness(randomness: &[u8; 32]) -> Move {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&randomness[..8]);
+    match u64::from_le_bytes(value) % 3 {
+        0 => Move::Rock,
+        1 => Move::Paper,
+        _ => Move::Scissors,
+    }
+}
+
+/// Settle a house game once both moves are known
+pub fn settle_house_game<'info>(
+    house_game: &mut Account<'info, HouseGame>,
+    vault: &mut Account<'info, HouseVault>,
+    player: &AccountInfo<'info>,
+    player_profile: &mut UserProfile,
+) -> Result<()> {
+    let player_move = house_game.player_move.ok_or(GameError::MoveNotCommitted)?;
+    let house_move = house_game.house_move.ok_or(GameError::MoveNotCommitted)?;
+    let result = determine_winner(player_move, house_move);
+    pay_out_house_game(house_game, vault, player, player_profile, result)
+}
+
+/// Move the stake and any winnings between the game escrow, the vault and the
+/// player, and release the reserved exposure. A draw refunds the stake.
+pub fn pay_out_house_game<'info>(
+    house_game: &mut Account<'info, HouseGame>,
+    vault: &mut Account<'info, HouseVault>,
+    player: &AccountInfo<'info>,
+    player_profile: &mut UserProfile,
+    result: RoundResult,
+) -> Result<()> {
+    let stake_amount = house_game.stake_amount;
+    let game_info = house_game.to_account_info();
+    let vault_info = vault.to_account_info();
+
+    **game_info.try_borrow_mut_lamports()? -= stake_amount;
+    match result {
+        RoundResult::Player1Win => {
+            **vault_info.try_borrow_mut_lamports()? -= house_game.max_payout - stake_amount;
+            **player.try_borrow_mut_lamports()? += house_game.max_payout;
+            player_profile.wins += 1;
+            player_profile.total_points_earned += house_game.max_payout;
+        }
+        RoundResult::Player2Win => {
+            **vault_info.try_borrow_mut_lamports()? += stake_amount;
+            player_profile.losses += 1;
+        }
+        RoundResult::Draw => {
+            **player.try_borrow_mut_lamports()? += stake_amount;
+            player_profile.draws += 1;
+        }
+    }
+    player_profile.total_games += 1;
+
+    vault.reserved -= house_game.exposure;
+    house_game.result = Some(result);
+    house_game.phase = HousePhase::Settled;
+
+    msg!("House game {} settled", house_game.game_id);
+    Ok(())
+}
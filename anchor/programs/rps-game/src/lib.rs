@@ -1,8 +1,31 @@
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_spl::token_interface::{self, CloseAccount, Mint, TokenAccount, TokenInterface};
 
 declare_id!("GstXQkBpu26KABj6YZ3pYKJhQphoQ72YL1zL38NC6D9U");
 
+pub mod house;
+pub mod lobby;
+pub mod points;
+pub mod queue;
+pub mod royale;
+pub mod seasons;
+pub mod series;
+pub mod team;
+pub mod tokens;
+pub mod tournament;
+
+pub use house::*;
+pub use lobby::*;
+pub use points::*;
+pub use queue::*;
+pub use royale::*;
+pub use seasons::*;
+pub use series::*;
+pub use team::*;
+pub use tokens::*;
+pub use tournament::*;
+
 #[program]
 pub mod rps_game {
     use super::*;
@@ -19,6 +42,23 @@ pub mod rps_game {
         user_profile.referral_count = 0;
         user_profile.referral_earnings = 0;
         user_profile.created_at = Clock::get()?.unix_timestamp;
+        user_profile.draws = 0;
+        user_profile.last_claim_at = 0;
+        user_profile.claim_streak = 0;
+        user_profile.points_migrated = false;
+        user_profile.active_points_games = 0;
+        user_profile.transfer_window_start = 0;
+        user_profile.transferred_in_window = 0;
+        user_profile.net_sol_won = 0;
+        user_profile.rating = 0;
+        user_profile.rating_deviation = 0;
+        user_profile.ranked_wins = 0;
+        user_profile.ranked_net_sol_won = 0;
+        
+        // New players get enough points for their first Points games
+        if let Some(points_config) = &ctx.accounts.points_config {
+            user_profile.points_balance = points_config.params.starter_grant;
+        }
         
         msg!("User profile initialized for {} with referral code {:?}", ctx.accounts.user.key(), user_profile.referral_code);
         Ok(())
@@ -52,86 +92,71 @@ pub mod rps_game {
         Ok(())
     }
 
-    pub fn create_game(
-        ctx: Context<CreateGame>,
+    pub fn create_game<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateGame<'info>>,
         game_id: String,
         stake_amount: u64,
         currency_type: CurrencyType,
         rounds_to_win: u8,
+        rules: MatchRules,
     ) -> Result<()> {
-        // Validate inputs
-        require!(game_id.len() <= 32, GameError::GameIdTooLong);
-        require!(stake_amount > 0, GameError::InvalidStakeAmount);
-        require!(rounds_to_win > 0 && rounds_to_win <= 10, GameError::InvalidRoundsToWin);
-        
-        // For points games, check if user has enough points
-        if currency_type == CurrencyType::Points {
-            let user_profile = &ctx.accounts.user_profile;
-            require!(
-                user_profile.points_balance >= stake_amount,
-                GameError::InsufficientPoints
-            );
-        } else if currency_type == CurrencyType::Sol {
-            // For SOL games, transfer the stake amount to the game account
-            require!(
-                **ctx.accounts.user.lamports.borrow() >= stake_amount,
-                GameError::InsufficientSol
-            );
-            
-            // Transfer SOL from user to game account
-            anchor_lang::solana_program::program::invoke(
-                &anchor_lang::solana_program::system_instruction::transfer(
-                    &ctx.accounts.user.key(),
-                    &ctx.accounts.game.key(),
-                    stake_amount,
-                ),
-                &[
-                    ctx.accounts.user.to_account_info(),
-                    ctx.accounts.game.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-        }
-        
-        let game = &mut ctx.accounts.game;
-        game.game_id = game_id;
-        game.player1 = ctx.accounts.user.key();
-        game.player2 = None;
-        game.game_status = GameStatus::WaitingForPlayer;
-        game.stake_amount = stake_amount;
-        game.currency_type = currency_type;
-        game.rounds_to_win = rounds_to_win;
-        game.current_round = 1;
-        game.player1_rounds_won = 0;
-        game.player2_rounds_won = 0;
-        game.player1_move_commitment = None;
-        game.player2_move_commitment = None;
-        game.winner = None;
-        game.created_at = Clock::get()?.unix_timestamp;
-        
-        // Deduct points for points games
-        if currency_type == CurrencyType::Points {
-            let user_profile = &mut ctx.accounts.user_profile;
-            user_profile.points_balance -= stake_amount;
-        }
-        
-        msg!("Game {} created by {}", game.game_id, ctx.accounts.user.key());
-        Ok(())
+        open_game(ctx, game_id, stake_amount, currency_type, rounds_to_win, None, rules)
+    }
+
+    /// Create a game with the stake given in USD (6 decimals). The stake is converted
+    /// to lamports or token units at the oracle price and locked into the game.
+    pub fn create_usd_game<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateGame<'info>>,
+        game_id: String,
+        usd_stake: u64,
+        currency_type: CurrencyType,
+        rounds_to_win: u8,
+        rules: MatchRules,
+    ) -> Result<()> {
+        require!(currency_type != CurrencyType::Points, GameError::UnsupportedCurrency);
+
+        let (asset, decimals) = if currency_type == CurrencyType::Sol {
+            (Pubkey::default(), SOL_DECIMALS)
+        } else {
+            let mint = ctx.accounts.mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            (mint.key(), mint.decimals)
+        };
+        let price_feed = ctx.accounts.price_feed.as_ref().ok_or(GameError::PriceFeedMismatch)?;
+        let price_account = ctx.accounts.price_account.as_ref().ok_or(GameError::PriceFeedMismatch)?;
+        require!(price_feed.asset == asset, GameError::PriceFeedMismatch);
+
+        let stake_amount = usd_to_amount(&price_account.to_account_info(), price_feed, usd_stake, decimals)?;
+        msg!("USD stake {} converted to {} at the oracle price", usd_stake, stake_amount);
+
+        open_game(ctx, game_id, stake_amount, currency_type, rounds_to_win, Some(usd_stake), rules)
     }
 
-    pub fn join_game(ctx: Context<JoinGame>, _game_id: String) -> Result<()> {
+    pub fn join_game<'info>(ctx: Context<'_, '_, '_, 'info, JoinGame<'info>>, _game_id: String) -> Result<()> {
         // Validate game can be joined
         require!(ctx.accounts.game.game_status == GameStatus::WaitingForPlayer, GameError::GameNotJoinable);
         require!(ctx.accounts.game.player1 != ctx.accounts.user.key(), GameError::CannotJoinOwnGame);
         require!(ctx.accounts.game.player2.is_none(), GameError::GameAlreadyFull);
         
+        // Rating-gated games only admit joiners inside the creator's band
+        let game = &ctx.accounts.game;
+        if game.min_opponent_rating.is_some() || game.max_opponent_rating.is_some() {
+            let rating_config = ctx.accounts.rating_config.as_ref().ok_or(GameError::MissingRatingConfig)?;
+            let rating = effective_rating(&ctx.accounts.user_profile, &rating_config.params);
+            require!(
+                game.min_opponent_rating.is_none_or(|min| rating >= min)
+                    && game.max_opponent_rating.is_none_or(|max| rating <= max),
+                GameError::RatingOutOfBand
+            );
+        }
+        
         // For points games, check if user has enough points and deduct them
-        if ctx.accounts.game.currency_type == CurrencyType::Points {
+        if ctx.accounts.game.currency_type == CurrencyType::Points && ctx.accounts.game.mint.is_none() {
             require!(
                 ctx.accounts.user_profile.points_balance >= ctx.accounts.game.stake_amount,
                 GameError::InsufficientPoints
             );
             ctx.accounts.user_profile.points_balance -= ctx.accounts.game.stake_amount;
+            lock_points(&mut ctx.accounts.user_profile);
         } else if ctx.accounts.game.currency_type == CurrencyType::Sol {
             // For SOL games, transfer the stake amount to the game account
             require!(
@@ -152,9 +177,32 @@ pub mod rps_game {
                     ctx.accounts.system_program.to_account_info(),
                 ],
             )?;
+        } else if ctx.accounts.game.mint.is_some() {
+            // SPL token games and tokenized points games escrow the stake
+            let mint = ctx.accounts.mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            require!(ctx.accounts.game.mint == Some(mint.key()), GameError::MintMismatch);
+            if ctx.accounts.game.currency_type == CurrencyType::SplToken {
+                // The mint's authorities may have changed since the game was created
+                let allowed_mint = ctx.accounts.allowed_mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+                require!(allowed_mint.mint == mint.key(), GameError::MintNotAllowed);
+                validate_mint_extensions(mint, allowed_mint.transfer_hook_program)?;
+            }
+            
+            let received = deposit_tokens(
+                ctx.accounts.user_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+                ctx.accounts.escrow.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+                mint,
+                &ctx.accounts.user,
+                ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+                ctx.remaining_accounts,
+                ctx.accounts.game.stake_amount,
+            )?;
+            msg!("Escrowed {} tokens after transfer fees", received);
         }
         
         let game = &mut ctx.accounts.game;
+        unlist_game(game.key(), game.lobby, ctx.accounts.lobby.as_mut())?;
+        game.lobby = None;
         game.player2 = Some(ctx.accounts.user.key());
         game.game_status = GameStatus::InProgress;
         
@@ -175,6 +223,9 @@ pub mod rps_game {
             GameError::NotAPlayer
         );
         
+        // A doubling offer must be answered before play continues
+        require!(game.double_offered_by.is_none(), GameError::DoublePending);
+        
         // Commit the move
         if player == game.player1 {
             require!(game.player1_move_commitment.is_none(), GameError::MoveAlreadyCommitted);
@@ -295,17 +346,25 @@ pub mod rps_game {
             game.current_round += 1;
             game.player1_move_commitment = None;
             game.player2_move_commitment = None;
+            // Pending offers were made for the previous position, so they lapse
+            game.draw_offered_by = None;
+            game.cancel_proposed_by = None;
             msg!("Round {} completed, starting round {}", game.current_round - 1, game.current_round);
         }
         
         Ok(())
     }
 
+    /// Admin override of a game's result, e.g. to resolve a dispute
     pub fn set_winner(ctx: Context<SetWinner>, _game_id: String, winner: Pubkey) -> Result<()> {
         let game = &mut ctx.accounts.game;
         
         // Validate game is in progress
         require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        // Series and tournament games are only decided by play or forfeit
+        require!(game.parent.is_none(), GameError::ChildGame);
+        // An escrowed doubling top-up is only returned by declining the offer
+        require!(game.double_offered_by.is_none(), GameError::DoublePending);
         
         // Validate winner is one of the players
         require!(
@@ -321,18 +380,51 @@ pub mod rps_game {
         Ok(())
     }
 
-    pub fn finalize_game(ctx: Context<FinalizeGameWithReferral>, _game_id: String) -> Result<()> {
+    pub fn finalize_game<'info>(
+        ctx: Context<'_, '_, '_, 'info, FinalizeGameWithReferral<'info>>,
+        _game_id: String,
+    ) -> Result<()> {
         // Validate game is finished
         require!(ctx.accounts.game.game_status == GameStatus::Finished, GameError::GameNotFinished);
         require!(ctx.accounts.game.winner.is_some(), GameError::NoWinner);
+        // Series and tournament games are paid out by their parent account
+        require!(ctx.accounts.game.parent.is_none(), GameError::ChildGame);
         
         let winner_key = ctx.accounts.game.winner.unwrap();
         let total_pot = ctx.accounts.game.stake_amount * 2;
         
+        // Slice of the platform fee routed into the progressive jackpot (SOL games only).
+        // Once the jackpot exists it can't be left out, so every SOL game contributes.
+        let jackpot_info = ctx.accounts.jackpot.to_account_info();
+        let mut jackpot = load_if_initialized::<Jackpot>(&jackpot_info)?;
+        let jackpot_contribution = match &jackpot {
+            Some(jackpot) if ctx.accounts.game.currency_type == CurrencyType::Sol => {
+                let total_fees = total_pot * get_platform_fee_rate(ctx.accounts.game.stake_amount) / 10000;
+                total_fees * jackpot.params.fee_share_bps as u64 / 10000
+            }
+            _ => 0,
+        };
+        
+        // Ledger balance before payout, so points credited to a migrated winner can be minted instead
+        let winner_points_before = if winner_key == ctx.accounts.game.player1 {
+            ctx.accounts.player1_profile.points_balance
+        } else {
+            ctx.accounts.player2_profile.points_balance
+        };
+        
+        // Winner's net lamport gain in SOL games, for the net SOL leaderboards
+        let mut winner_sol_gain = 0i64;
+        
         // Update stats for both players
         let player1_profile = &mut ctx.accounts.player1_profile;
         let player2_profile = &mut ctx.accounts.player2_profile;
         
+        // Both stakes leave the game, so neither player has points locked in it anymore
+        if ctx.accounts.game.currency_type == CurrencyType::Points && ctx.accounts.game.mint.is_none() {
+            unlock_points(player1_profile)?;
+            unlock_points(player2_profile)?;
+        }
+        
         // Determine which profile is the winner
         msg!("DEBUG: winner_key = {}", winner_key);
         msg!("DEBUG: game.player1 = {}", ctx.accounts.game.player1);
@@ -346,7 +438,7 @@ pub mod rps_game {
             player2_profile.total_games += 1;
             
             // Distribute rewards based on currency type
-            if ctx.accounts.game.currency_type == CurrencyType::Points {
+            if ctx.accounts.game.currency_type == CurrencyType::Points && ctx.accounts.game.mint.is_none() {
                 // Winner gets full pot for points games
                 player1_profile.points_balance += total_pot;
                 player1_profile.total_points_earned += total_pot;
@@ -355,6 +447,8 @@ pub mod rps_game {
                 let fee_rate = get_platform_fee_rate(ctx.accounts.game.stake_amount);
                 let total_fees = total_pot * fee_rate / 10000; // fee_rate is in basis points
                 let winner_amount = total_pot - total_fees;
+                let total_fees = total_fees - jackpot_contribution; // Jackpot slice is moved below
+                winner_sol_gain = winner_amount as i64 - ctx.accounts.game.stake_amount as i64;
                 
                 // Check if winner has a referrer AND this is their first game - take 1% from platform fee
                 if let Some(referrer_key) = player1_profile.referred_by {
@@ -363,12 +457,18 @@ pub mod rps_game {
                         let platform_fee_amount = total_fees - referral_commission; // Reduce platform fee by referral amount
                         
                         // Transfer commission to referrer
-                        **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= referral_commission;
-                        **ctx.accounts.referrer.to_account_info().try_borrow_mut_lamports()? += referral_commission;
+                        move_lamports(
+                            &ctx.accounts.game.to_account_info(),
+                            &ctx.accounts.referrer.to_account_info(),
+                            referral_commission,
+                        )?;
                         
                         // Transfer remaining platform fee to platform wallet
-                        **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= platform_fee_amount;
-                        **ctx.accounts.platform_wallet.to_account_info().try_borrow_mut_lamports()? += platform_fee_amount;
+                        move_lamports(
+                            &ctx.accounts.game.to_account_info(),
+                            &ctx.accounts.platform_wallet.to_account_info(),
+                            platform_fee_amount,
+                        )?;
                         
                         // Update referrer's earnings
                         ctx.accounts.referrer_profile.referral_earnings += referral_commission;
@@ -380,18 +480,27 @@ pub mod rps_game {
                         );
                     } else {
                         // Referrer key doesn't match - no referral commission
-                        **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= total_fees;
-                        **ctx.accounts.platform_wallet.to_account_info().try_borrow_mut_lamports()? += total_fees;
+                        move_lamports(
+                            &ctx.accounts.game.to_account_info(),
+                            &ctx.accounts.platform_wallet.to_account_info(),
+                            total_fees,
+                        )?;
                     }
                 } else {
                     // No referrer - all fees go to platform
-                    **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= total_fees;
-                    **ctx.accounts.platform_wallet.to_account_info().try_borrow_mut_lamports()? += total_fees;
+                    move_lamports(
+                        &ctx.accounts.game.to_account_info(),
+                        &ctx.accounts.platform_wallet.to_account_info(),
+                        total_fees,
+                    )?;
                 }
                 
                 // Transfer from game escrow to player1 (the actual winner)
-                **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= winner_amount;
-                **ctx.accounts.player1.to_account_info().try_borrow_mut_lamports()? += winner_amount;
+                move_lamports(
+                    &ctx.accounts.game.to_account_info(),
+                    &ctx.accounts.player1.to_account_info(),
+                    winner_amount,
+                )?;
                 
                 // SOL game winners also get 100 bonus points
                 player1_profile.points_balance += 100;
@@ -420,7 +529,7 @@ pub mod rps_game {
             player1_profile.total_games += 1;
             
             // Distribute rewards based on currency type
-            if ctx.accounts.game.currency_type == CurrencyType::Points {
+            if ctx.accounts.game.currency_type == CurrencyType::Points && ctx.accounts.game.mint.is_none() {
                 // Winner gets full pot for points games
                 player2_profile.points_balance += total_pot;
                 player2_profile.total_points_earned += total_pot;
@@ -429,6 +538,8 @@ pub mod rps_game {
                 let fee_rate = get_platform_fee_rate(ctx.accounts.game.stake_amount);
                 let total_fees = total_pot * fee_rate / 10000; // fee_rate is in basis points
                 let winner_amount = total_pot - total_fees;
+                let total_fees = total_fees - jackpot_contribution; // Jackpot slice is moved below
+                winner_sol_gain = winner_amount as i64 - ctx.accounts.game.stake_amount as i64;
                 
                 // Check if winner has a referrer - take 1% from platform fee
                 if let Some(referrer_key) = player2_profile.referred_by {
@@ -437,12 +548,18 @@ pub mod rps_game {
                         let platform_fee_amount = total_fees - referral_commission; // Reduce platform fee by referral amount
                         
                         // Transfer commission to referrer
-                        **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= referral_commission;
-                        **ctx.accounts.referrer.to_account_info().try_borrow_mut_lamports()? += referral_commission;
+                        move_lamports(
+                            &ctx.accounts.game.to_account_info(),
+                            &ctx.accounts.referrer.to_account_info(),
+                            referral_commission,
+                        )?;
                         
                         // Transfer remaining platform fee to platform wallet
-                        **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= platform_fee_amount;
-                        **ctx.accounts.platform_wallet.to_account_info().try_borrow_mut_lamports()? += platform_fee_amount;
+                        move_lamports(
+                            &ctx.accounts.game.to_account_info(),
+                            &ctx.accounts.platform_wallet.to_account_info(),
+                            platform_fee_amount,
+                        )?;
                         
                         // Update referrer's earnings
                         ctx.accounts.referrer_profile.referral_earnings += referral_commission;
@@ -454,18 +571,27 @@ pub mod rps_game {
                         );
                     } else {
                         // Referrer key doesn't match - no referral commission
-                        **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= total_fees;
-                        **ctx.accounts.platform_wallet.to_account_info().try_borrow_mut_lamports()? += total_fees;
+                        move_lamports(
+                            &ctx.accounts.game.to_account_info(),
+                            &ctx.accounts.platform_wallet.to_account_info(),
+                            total_fees,
+                        )?;
                     }
                 } else {
                     // No referrer - all fees go to platform
-                    **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= total_fees;
-                    **ctx.accounts.platform_wallet.to_account_info().try_borrow_mut_lamports()? += total_fees;
+                    move_lamports(
+                        &ctx.accounts.game.to_account_info(),
+                        &ctx.accounts.platform_wallet.to_account_info(),
+                        total_fees,
+                    )?;
                 }
                 
                 // Transfer from game escrow to player2 (the actual winner)
-                **ctx.accounts.game.to_account_info().try_borrow_mut_lamports()? -= winner_amount;
-                **ctx.accounts.player2.to_account_info().try_borrow_mut_lamports()? += winner_amount;
+                move_lamports(
+                    &ctx.accounts.game.to_account_info(),
+                    &ctx.accounts.player2.to_account_info(),
+                    winner_amount,
+                )?;
                 
                 // SOL game winners also get 100 bonus points
                 player2_profile.points_balance += 100;
@@ -488,6 +614,235 @@ pub mod rps_game {
             }
         }
         
+        if let Some(jackpot) = jackpot.as_mut() {
+            let game = &mut ctx.accounts.game;
+            
+            if jackpot_contribution > 0 {
+                move_lamports(&game.to_account_info(), &jackpot_info, jackpot_contribution)?;
+                jackpot.total_contributed += jackpot_contribution;
+                jackpot.contributions += 1;
+                
+                emit!(JackpotContributed {
+                    game: game.key(),
+                    amount: jackpot_contribution,
+                    jackpot_balance: jackpot_balance(&jackpot_info)?,
+                });
+            }
+            
+            // A flawless win (every round won, no draws) enters the jackpot draw.
+            // Games ended early by a forfeit, declined double or admin ruling never
+            // reach the winning round count, so they stay out.
+            // The draw uses the hash of a slot that has not been produced yet.
+            let (winner_rounds_won, loser_rounds_won) = if winner_key == game.player1 {
+                (game.player1_rounds_won, game.player2_rounds_won)
+            } else {
+                (game.player2_rounds_won, game.player1_rounds_won)
+            };
+            let flawless = game.currency_type == CurrencyType::Sol
+                && winner_rounds_won == game.rounds_to_win
+                && loser_rounds_won == 0
+                && game.current_round == game.rounds_to_win
+                && game.rounds_to_win >= jackpot.params.min_rounds_to_win
+                && game.stake_amount >= jackpot.params.min_stake;
+            if flawless {
+                let draw_slot = Clock::get()?.slot + JACKPOT_DRAW_DELAY;
+                game.jackpot_draw_slot = Some(draw_slot);
+                msg!("Game {} entered the jackpot draw for slot {}", game.game_id, draw_slot);
+            }
+            jackpot.try_serialize(&mut &mut jackpot_info.try_borrow_mut_data()?[..])?;
+        }
+        
+        if ctx.accounts.game.mint.is_some() {
+            let game = &ctx.accounts.game;
+            let mint = ctx.accounts.mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let escrow = ctx.accounts.escrow.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let winner_token_account = ctx.accounts.winner_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let token_program = ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            require!(game.mint == Some(mint.key()), GameError::MintMismatch);
+            require!(winner_token_account.owner == winner_key, GameError::InvalidTokenAccount);
+            
+            // Token games use the fee tiers configured for their mint, while points
+            // games stay fee free. The pot is whatever the escrow actually holds,
+            // since transfer fees may have shaved the deposits below twice the stake.
+            let fee_bps = if game.currency_type == CurrencyType::Points {
+                0
+            } else {
+                let allowed_mint = ctx.accounts.allowed_mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+                require!(allowed_mint.mint == mint.key(), GameError::MintMismatch);
+                token_fee_bps(allowed_mint, game.stake_amount)
+            };
+            let token_pot = escrow.amount;
+            let total_fees = token_fee(token_pot, fee_bps);
+            let winner_amount = token_pot - total_fees;
+            let game_bump = ctx.bumps.game;
+            let extra_accounts = ctx.remaining_accounts;
+            
+            withdraw_tokens(game, game_bump, escrow, mint, &winner_token_account.to_account_info(), token_program, extra_accounts, winner_amount)?;
+            if total_fees > 0 {
+                let platform_token_account = ctx.accounts.platform_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+                require!(
+                    platform_token_account.owner == ctx.accounts.platform_wallet.key(),
+                    GameError::InvalidTokenAccount
+                );
+                withdraw_tokens(game, game_bump, escrow, mint, &platform_token_account.to_account_info(), token_program, extra_accounts, total_fees)?;
+            }
+            close_escrow(game, game_bump, escrow, mint, &ctx.accounts.player1.to_account_info(), token_program)?;
+            
+            if game.currency_type == CurrencyType::Points {
+                if winner_key == game.player1 {
+                    ctx.accounts.player1_profile.total_points_earned += winner_amount;
+                } else {
+                    ctx.accounts.player2_profile.total_points_earned += winner_amount;
+                }
+            }
+            
+            msg!(
+                "Transferred {} tokens of mint {} to winner {}, platform fee {} ({} bps)",
+                winner_amount,
+                mint.key(),
+                winner_key,
+                total_fees,
+                fee_bps
+            );
+        }
+        
+        let game = &ctx.accounts.game;
+        let (player1_sol_net, player2_sol_net) = match game.currency_type {
+            CurrencyType::Sol if winner_key == game.player1 => (winner_sol_gain, -(game.stake_amount as i64)),
+            CurrencyType::Sol => (-(game.stake_amount as i64), winner_sol_gain),
+            _ => (0, 0),
+        };
+        ctx.accounts.player1_profile.net_sol_won += player1_sol_net;
+        ctx.accounts.player2_profile.net_sol_won += player2_sol_net;
+        
+        // Only ranked games count toward ratings and leaderboards, and a ranked game
+        // can't be finalized without its rating update
+        if game.ranked {
+            require!(ctx.accounts.rating_config.is_some(), GameError::MissingRatingConfig);
+            if winner_key == game.player1 {
+                ctx.accounts.player1_profile.ranked_wins += 1;
+            } else {
+                ctx.accounts.player2_profile.ranked_wins += 1;
+            }
+            ctx.accounts.player1_profile.ranked_net_sol_won += player1_sol_net;
+            ctx.accounts.player2_profile.ranked_net_sol_won += player2_sol_net;
+        }
+        
+        // Every finalized game counts toward the active season's stats, which roll over once
+        // the season ends. Once seasons have started, both players' stats for the active
+        // season are required and are created when missing.
+        let season_info = ctx.accounts.season.to_account_info();
+        let mut active_season_id = None;
+        let mut season_stats = Vec::new();
+        if let Some(mut season) = load_if_initialized::<Season>(&season_info)? {
+            let now = Clock::get()?.unix_timestamp;
+            if now >= season.ends_at {
+                advance_season(&mut season, now);
+                season.try_serialize(&mut &mut season_info.try_borrow_mut_data()?[..])?;
+                msg!("Season rolled over to {}", season.season_id);
+            }
+            let players = [
+                (ctx.accounts.player1_season_stats.as_ref(), game.player1, player1_sol_net),
+                (ctx.accounts.player2_season_stats.as_ref(), game.player2.unwrap(), player2_sol_net),
+            ];
+            for (stats_info, player, sol_net) in players {
+                let stats_info = stats_info.ok_or(GameError::MissingSeasonStats)?.to_account_info();
+                let mut stats = load_or_create_season_stats(
+                    &stats_info,
+                    season.season_id,
+                    &player,
+                    ctx.accounts.payer.as_ref(),
+                    &ctx.accounts.system_program,
+                )?;
+                stats.total_games += 1;
+                stats.net_sol_won += sol_net;
+                if player == winner_key {
+                    stats.wins += 1;
+                } else {
+                    stats.losses += 1;
+                }
+                stats.try_serialize(&mut &mut stats_info.try_borrow_mut_data()?[..])?;
+                season_stats.push(stats);
+            }
+            active_season_id = Some(season.season_id);
+        }
+        
+        // Ranked games move rating from the loser to the winner
+        if let Some(rating_config) = ctx.accounts.rating_config.as_ref().filter(|_| game.ranked) {
+            let (winner_profile, loser_profile) = if winner_key == game.player1 {
+                (&mut ctx.accounts.player1_profile, &mut ctx.accounts.player2_profile)
+            } else {
+                (&mut ctx.accounts.player2_profile, &mut ctx.accounts.player1_profile)
+            };
+            let change = apply_rating_result(winner_profile, loser_profile, &rating_config.params);
+            msg!(
+                "Ratings updated by {}: winner {}, loser {}",
+                change,
+                winner_profile.rating,
+                loser_profile.rating
+            );
+        }
+        
+        // Leaderboards are updated in place with both players' new totals. Neither the
+        // all-time board nor the active season's board can be left out once it exists.
+        let leaderboard_info = ctx.accounts.leaderboard.to_account_info();
+        if let Some(mut leaderboard) = load_if_initialized::<Leaderboard>(&leaderboard_info)?.filter(|_| game.ranked) {
+            let players = [
+                (game.player1, &ctx.accounts.player1_profile),
+                (game.player2.unwrap(), &ctx.accounts.player2_profile),
+            ];
+            for (player, profile) in players {
+                leaderboard_insert(&mut leaderboard.by_wins, player, profile.ranked_wins as i64);
+                leaderboard_insert(&mut leaderboard.by_net_sol, player, profile.ranked_net_sol_won);
+                if profile.rating > 0 {
+                    leaderboard_insert(&mut leaderboard.by_rating, player, profile.rating as i64);
+                }
+            }
+            leaderboard.try_serialize(&mut &mut leaderboard_info.try_borrow_mut_data()?[..])?;
+        }
+        if let Some(season_id) = active_season_id.filter(|_| game.ranked) {
+            let season_leaderboard_info = ctx.accounts.season_leaderboard.to_account_info();
+            let (expected, _) =
+                Pubkey::find_program_address(&[b"leaderboard", season_id.to_le_bytes().as_ref()], &crate::ID);
+            require!(season_leaderboard_info.key() == expected, GameError::LeaderboardMismatch);
+            if let Some(mut leaderboard) = load_if_initialized::<Leaderboard>(&season_leaderboard_info)? {
+                for stats in &season_stats {
+                    leaderboard_insert(&mut leaderboard.by_wins, stats.player, stats.wins as i64);
+                    leaderboard_insert(&mut leaderboard.by_net_sol, stats.player, stats.net_sol_won);
+                
+                    // Ratings are not seasonal, so the season ranking shows the current rating
+                    let profile = if stats.player == game.player1 {
+                        &ctx.accounts.player1_profile
+                    } else {
+                        &ctx.accounts.player2_profile
+                    };
+                    if profile.rating > 0 {
+                        leaderboard_insert(&mut leaderboard.by_rating, stats.player, profile.rating as i64);
+                    }
+                }
+                leaderboard.try_serialize(&mut &mut season_leaderboard_info.try_borrow_mut_data()?[..])?;
+            }
+        }
+        
+        // Points credited to a migrated winner (such as the SOL win bonus) are minted as tokens
+        let winner_profile = if winner_key == ctx.accounts.game.player1 {
+            &mut ctx.accounts.player1_profile
+        } else {
+            &mut ctx.accounts.player2_profile
+        };
+        if winner_profile.points_migrated && winner_profile.points_balance > winner_points_before {
+            let amount = winner_profile.points_balance - winner_points_before;
+            winner_profile.points_balance = winner_points_before;
+            
+            let points_mint = ctx.accounts.points_mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let winner_token_account = ctx.accounts.winner_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let token_program = ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            require!(winner_token_account.owner == winner_key, GameError::InvalidTokenAccount);
+            let bump = ctx.bumps.points_mint.ok_or(GameError::MissingTokenAccounts)?;
+            mint_points(points_mint, bump, winner_token_account, token_program, amount)?;
+            msg!("Minted {} points to migrated winner {}", amount, winner_key);
+        }
+        
         msg!(
             "Game {} finalized! Winner: {}, Pot: {}",
             ctx.accounts.game.game_id,
@@ -495,12 +850,14 @@ pub mod rps_game {
             total_pot
         );
         
+        // The pot has been paid out, so the game can't be finalized again
+        ctx.accounts.game.game_status = GameStatus::Settled;
         Ok(())
     }
 
     /// Cancel game and refund SOL to player (when game hasn't started)
     /// Can only be called by player1 (creator) when player2 hasn't joined yet
-    pub fn cancel_game(ctx: Context<CancelGame>, _game_id: String) -> Result<()> {
+    pub fn cancel_game<'info>(ctx: Context<'_, '_, '_, 'info, CancelGame<'info>>, _game_id: String) -> Result<()> {
         let game = &ctx.accounts.game;
         
         // Validate game hasn't started yet
@@ -514,11 +871,37 @@ pub mod rps_game {
       ctx.accounts.user.key() == game.player1,
       GameError::NotAPlayer
     );
+    unlist_game(game.key(), game.lobby, ctx.accounts.lobby.as_mut())?;
+    // Refund spectators now, as a game re-created under this id would reuse the pool's address
+    if game.side_bets_open {
+        let pool = ctx.accounts.side_bet_pool.as_mut().ok_or(GameError::SideBetsUnsettled)?;
+        pool.outcome = Some(SideBetOutcome::Refund);
+        msg!("Side bet pool for game {} settled: {:?}", pool.game, SideBetOutcome::Refund);
+    }
     
-    // Transfer SOL back from game account to player
     let stake_amount = game.stake_amount;
+    if game.mint.is_some() {
+        // Refund the escrowed tokens and return the escrow's rent
+        let mint = ctx.accounts.mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let escrow = ctx.accounts.escrow.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let user_token_account = ctx.accounts.user_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let token_program = ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        require!(game.mint == Some(mint.key()), GameError::MintMismatch);
+        
+        // Refund what the escrow holds, which is the stake less any transfer fee
+        let refund = escrow.amount;
+        withdraw_tokens(game, ctx.bumps.game, escrow, mint, &user_token_account.to_account_info(), token_program, ctx.remaining_accounts, refund)?;
+        close_escrow(game, ctx.bumps.game, escrow, mint, &ctx.accounts.user.to_account_info(), token_program)?;
+    } else if game.currency_type == CurrencyType::Points {
+        // Points stakes go back to the creator's ledger
+        let user_profile = ctx.accounts.user_profile.as_mut().ok_or(GameError::MissingUserProfile)?;
+        user_profile.points_balance += stake_amount;
+        unlock_points(user_profile)?;
+    } else {
+    // Transfer SOL back from game account to player
     **ctx.accounts.user.lamports.borrow_mut() += stake_amount;
     **ctx.accounts.game.to_account_info().lamports.borrow_mut() -= stake_amount;
+    }
         
         msg!(
             "Game {} cancelled. Refunding {} lamports to creator {}.",
@@ -531,251 +914,2396 @@ pub mod rps_game {
         Ok(())
     }
 
-    /// Close game account and return rent to creator (player1)
+    /// Close game account and return rent to whoever paid it: the creator (player1),
+    /// or the cranker for games matched from a queue.
     /// Can be called by service wallet after finalization or by creator
     pub fn close_game(ctx: Context<CloseGame>, _game_id: String) -> Result<()> {
-        // Validate game is finished
+        // Validate game is settled (won and paid out, drawn, cancelled by agreement or forfeited on timeout)
         require!(
-            ctx.accounts.game.game_status == GameStatus::Finished,
+            matches!(
+                ctx.accounts.game.game_status,
+                GameStatus::Settled
+                    | GameStatus::Drawn
+                    | GameStatus::Cancelled
+                    | GameStatus::Abandoned
+            ),
             GameError::GameNotFinished
         );
+        // Child games are closed by their parent when the result is recorded
+        require!(ctx.accounts.game.parent.is_none(), GameError::ChildGame);
+        // Spectators' bets are settled against this account
+        require!(!ctx.accounts.game.side_bets_open, GameError::SideBetsUnsettled);
+        // A pending jackpot draw must be drawn first, unless it has already lapsed
+        if let Some(draw_slot) = ctx.accounts.game.jackpot_draw_slot {
+            require!(
+                Clock::get()?.slot > draw_slot + SLOT_HASHES_MAX_ENTRIES,
+                GameError::JackpotDrawPending
+            );
+        }
         
-        // Return rent to player1 (creator), but allow service wallet to call this
-        // The `close = player1` attribute handles returning rent to player1
+        // Return rent to its payer, but allow service wallet to call this
+        // The `close = rent_receiver` attribute handles returning the rent
         msg!(
-            "Game {} closed. Rent exemption returned to creator {}.",
+            "Game {} closed. Rent exemption returned to {}.",
             ctx.accounts.game.game_id,
-            ctx.accounts.game.player1
+            ctx.accounts.rent_receiver.key()
         );
         
         Ok(())
     }
-}
 
-// Account validation structures
-#[derive(Accounts)]
-pub struct InitializeUserProfile<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 8 + 4 + 4 + 4 + 8 + 8 + 33 + 4 + 8 + 8, // discriminator + points_balance + wins + losses + total_games + total_points_earned + referral_code + referred_by + referral_count + referral_earnings + created_at
-        seeds = [b"user_profile", user.key().as_ref()],
-        bump
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Create the global config account. The signer becomes the admin.
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        platform_wallet: Pubkey,
+        draw_fee_bps: u16,
+        side_bet_fee_bps: u16,
+        max_stake_multiplier: u8,
+    ) -> Result<()> {
+        require!(draw_fee_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+        require!(side_bet_fee_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+        require!(max_stake_multiplier >= 1, GameError::InvalidStakeMultiplier);
 
-#[derive(Accounts)]
-pub struct SetReferrer<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_profile", user.key().as_ref()],
-        bump
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    
-    #[account(
-        mut,
-        seeds = [b"user_profile", referrer.key().as_ref()],
-        bump
-    )]
-    pub referrer_profile: Account<'info, UserProfile>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    /// CHECK: This account is validated by the referrer_profile seeds
-    pub referrer: UncheckedAccount<'info>,
-}
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.platform_wallet = platform_wallet;
+        config.draw_fee_bps = draw_fee_bps;
+        config.side_bet_fee_bps = side_bet_fee_bps;
+        config.max_stake_multiplier = max_stake_multiplier;
 
-#[derive(Accounts)]
-#[instruction(game_id: String)]
-pub struct CreateGame<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 33 + 1 + 8 + 1 + 1 + 1 + 1 + 1 + 33 + 33 + 33 + 8, // discriminator + game data
-        seeds = [b"game", game_id.as_bytes()],
-        bump
-    )]
-    pub game: Account<'info, Game>,
-    
-    #[account(
-        mut,
-        seeds = [b"user_profile", user.key().as_ref()],
-        bump
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        msg!("Config initialized by admin {}", config.admin);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(game_id: String)]
-pub struct JoinGame<'info> {
-    #[account(
-        mut,
-        seeds = [b"game", game_id.as_bytes()],
-        bump
-    )]
-    pub game: Account<'info, Game>,
-    
-    #[account(
-        mut,
-        seeds = [b"user_profile", user.key().as_ref()],
-        bump
-    )]
-    pub user_profile: Account<'info, UserProfile>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Update the platform wallet and the fees charged on draws, mutual cancels and side bets
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        platform_wallet: Pubkey,
+        draw_fee_bps: u16,
+        side_bet_fee_bps: u16,
+        max_stake_multiplier: u8,
+    ) -> Result<()> {
+        require!(draw_fee_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+        require!(side_bet_fee_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+        require!(max_stake_multiplier >= 1, GameError::InvalidStakeMultiplier);
 
-#[derive(Accounts)]
-#[instruction(game_id: String)]
-pub struct CommitMove<'info> {
-    #[account(
-        mut,
-        seeds = [b"game", game_id.as_bytes()],
-        bump
-    )]
-    pub game: Account<'info, Game>,
-    
-    pub user: Signer<'info>,
-}
+        let config = &mut ctx.accounts.config;
+        config.platform_wallet = platform_wallet;
+        config.draw_fee_bps = draw_fee_bps;
+        config.side_bet_fee_bps = side_bet_fee_bps;
+        config.max_stake_multiplier = max_stake_multiplier;
 
-#[derive(Accounts)]
-#[instruction(game_id: String)]
-pub struct RevealMove<'info> {
-    #[account(
-        mut,
-        seeds = [b"game", game_id.as_bytes()],
-        bump
-    )]
-    pub game: Account<'info, Game>,
-    
-    pub user: Signer<'info>,
-}
+        msg!(
+            "Config updated: platform wallet {}, draw fee {} bps, side bet fee {} bps, max stake multiplier {}",
+            platform_wallet,
+            draw_fee_bps,
+            side_bet_fee_bps,
+            max_stake_multiplier
+        );
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(game_id: String)]
-pub struct ProcessRound<'info> {
-    #[account(
-        mut,
-        seeds = [b"game", game_id.as_bytes()],
-        bump
-    )]
-    pub game: Account<'info, Game>,
-    
-    pub user: Signer<'info>,
-}
+    /// Grow a profile created by an older program version to the current layout.
+    /// Permissionless; the payer covers the extra rent.
+    pub fn upgrade_user_profile(ctx: Context<UpgradeUserProfile>) -> Result<()> {
+        grow_account(
+            &ctx.accounts.user_profile,
+            UserProfile::DISCRIMINATOR,
+            USER_PROFILE_SPACE,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+
+        msg!("Profile of {} upgraded", ctx.accounts.owner.key());
+        Ok(())
+    }
+
+    /// Grow a game created by an older program version to the current layout.
+    /// Permissionless; the payer covers the extra rent.
+    pub fn upgrade_game(ctx: Context<UpgradeGame>, _game_id: String) -> Result<()> {
+        let info = ctx.accounts.game.to_account_info();
+        grow_account(
+            &info,
+            Game::DISCRIMINATOR,
+            GAME_SPACE,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+
+        let mut game = Game::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        // Games from before the doubling cube have no multiplier yet
+        if game.stake_multiplier == 0 {
+            game.stake_multiplier = 1;
+        }
+        game.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        msg!("Game {} upgraded", game.game_id);
+        Ok(())
+    }
+
+    /// Offer the opponent a draw while the game is in progress
+    pub fn offer_draw(ctx: Context<OfferDraw>, _game_id: String) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let player = ctx.accounts.user.key();
+
+        require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        require!(game.draw_offered_by.is_none(), GameError::DrawAlreadyOffered);
+
+        game.draw_offered_by = Some(player);
+
+        msg!("Player {} offered a draw in game {}", player, game.game_id);
+        Ok(())
+    }
+
+    /// Accept the opponent's draw offer. Both stakes are returned minus the draw fee.
+    pub fn accept_draw<'info>(ctx: Context<'_, '_, '_, 'info, AcceptDraw<'info>>, _game_id: String) -> Result<()> {
+        let player = ctx.accounts.user.key();
+        let game = &ctx.accounts.game;
+
+        require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        let offered_by = game.draw_offered_by.ok_or(GameError::NoDrawOffer)?;
+        require!(offered_by != player, GameError::CannotAcceptOwnOffer);
+        require!(game.double_offered_by.is_none(), GameError::DoublePending);
+
+        let fee_per_player = if ctx.accounts.game.mint.is_some() {
+            refund_both_token_stakes(
+                &ctx.accounts.game,
+                ctx.bumps.game,
+                ctx.accounts.mint.as_ref(),
+                ctx.accounts.escrow.as_ref(),
+                ctx.accounts.player1_token_account.as_ref(),
+                ctx.accounts.player2_token_account.as_ref(),
+                ctx.accounts.platform_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &ctx.accounts.player1,
+                &ctx.accounts.platform_wallet,
+                ctx.remaining_accounts,
+                ctx.accounts.config.draw_fee_bps,
+            )?
+        } else {
+            refund_both_stakes(
+                &ctx.accounts.game,
+                &ctx.accounts.player1,
+                &ctx.accounts.player2,
+                &ctx.accounts.platform_wallet,
+                &mut ctx.accounts.player1_profile,
+                &mut ctx.accounts.player2_profile,
+                ctx.accounts.config.draw_fee_bps,
+            )?
+        };
+
+        ctx.accounts.player1_profile.draws += 1;
+        ctx.accounts.player1_profile.total_games += 1;
+        ctx.accounts.player2_profile.draws += 1;
+        ctx.accounts.player2_profile.total_games += 1;
+
+        let game = &mut ctx.accounts.game;
+        game.game_status = GameStatus::Drawn;
+        game.draw_offered_by = None;
+        game.cancel_proposed_by = None;
+
+        msg!("Game {} drawn by agreement, fee per player: {}", game.game_id, fee_per_player);
+        Ok(())
+    }
+
+    /// Propose cancelling a game that is in progress
+    pub fn propose_cancel(ctx: Context<ProposeCancel>, _game_id: String) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let player = ctx.accounts.user.key();
+
+        require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        require!(game.cancel_proposed_by.is_none(), GameError::CancelAlreadyProposed);
+
+        game.cancel_proposed_by = Some(player);
+
+        msg!("Player {} proposed cancelling game {}", player, game.game_id);
+        Ok(())
+    }
+
+    /// Accept the opponent's cancel proposal. Both stakes are returned minus the draw fee
+    /// and neither profile's game stats change.
+    pub fn accept_cancel<'info>(ctx: Context<'_, '_, '_, 'info, AcceptCancel<'info>>, _game_id: String) -> Result<()> {
+        let player = ctx.accounts.user.key();
+        let game = &ctx.accounts.game;
+
+        require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        let proposed_by = game.cancel_proposed_by.ok_or(GameError::NoCancelProposal)?;
+        require!(proposed_by != player, GameError::CannotAcceptOwnOffer);
+        require!(game.double_offered_by.is_none(), GameError::DoublePending);
+
+        let fee_per_player = if ctx.accounts.game.mint.is_some() {
+            refund_both_token_stakes(
+                &ctx.accounts.game,
+                ctx.bumps.game,
+                ctx.accounts.mint.as_ref(),
+                ctx.accounts.escrow.as_ref(),
+                ctx.accounts.player1_token_account.as_ref(),
+                ctx.accounts.player2_token_account.as_ref(),
+                ctx.accounts.platform_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                &ctx.accounts.player1,
+                &ctx.accounts.platform_wallet,
+                ctx.remaining_accounts,
+                ctx.accounts.config.draw_fee_bps,
+            )?
+        } else {
+            refund_both_stakes(
+                &ctx.accounts.game,
+                &ctx.accounts.player1,
+                &ctx.accounts.player2,
+                &ctx.accounts.platform_wallet,
+                &mut ctx.accounts.player1_profile,
+                &mut ctx.accounts.player2_profile,
+                ctx.accounts.config.draw_fee_bps,
+            )?
+        };
+
+        let game = &mut ctx.accounts.game;
+        game.game_status = GameStatus::Cancelled;
+        game.draw_offered_by = None;
+        game.cancel_proposed_by = None;
+
+        msg!("Game {} cancelled by agreement, fee per player: {}", game.game_id, fee_per_player);
+        Ok(())
+    }
+
+    /// Ask the opponent for a rematch with the same stake, currency and rounds_to_win.
+    /// Nothing is escrowed yet; both stakes are collected when the rematch is accepted.
+    /// SOL and points games only.
+    pub fn request_rematch(ctx: Context<RequestRematch>, _game_id: String) -> Result<()> {
+        let player = ctx.accounts.user.key();
+        let game = &ctx.accounts.game;
+
+        require!(
+            matches!(game.game_status, GameStatus::Finished | GameStatus::Settled | GameStatus::Drawn),
+            GameError::GameNotFinished
+        );
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        require!(game.rematch_requested_by.is_none(), GameError::RematchPending);
+        require!(game.next_game.is_none(), GameError::RematchAlreadyPlayed);
+        require!(game.mint.is_none(), GameError::UnsupportedCurrency);
+        require!(game.parent.is_none(), GameError::ChildGame);
+
+        let game = &mut ctx.accounts.game;
+        game.rematch_requested_by = Some(player);
+
+        msg!("Player {} requested a rematch of game {}", player, game.game_id);
+        Ok(())
+    }
+
+    /// Withdraw a pending rematch request
+    pub fn cancel_rematch(ctx: Context<CancelRematch>, _game_id: String) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(
+            game.rematch_requested_by == Some(ctx.accounts.user.key()),
+            GameError::NoRematchRequest
+        );
+        game.rematch_requested_by = None;
+
+        msg!("Rematch request for game {} withdrawn", game.game_id);
+        Ok(())
+    }
+
+    /// Accept a pending rematch. Creates `new_game_id` with the same players and
+    /// settings and collects both stakes into it, so the requester co-signs.
+    pub fn accept_rematch(
+        ctx: Context<AcceptRematch>,
+        _game_id: String,
+        new_game_id: String,
+    ) -> Result<()> {
+        require!(new_game_id.len() <= 32, GameError::GameIdTooLong);
+
+        let player = ctx.accounts.user.key();
+        let game = &ctx.accounts.game;
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        let requested_by = game.rematch_requested_by.ok_or(GameError::NoRematchRequest)?;
+        require!(requested_by != player, GameError::CannotAcceptOwnOffer);
+        require!(ctx.accounts.requester.key() == requested_by, GameError::NotAPlayer);
+
+        // Rematches start over at the stake the game was created with
+        let stake_amount = game.stake_amount / game.stake_multiplier as u64;
+        let currency_type = game.currency_type;
+        let new_game_info = ctx.accounts.new_game.to_account_info();
+        for (payer, profile) in [
+            (&ctx.accounts.user, &mut ctx.accounts.user_profile),
+            (&ctx.accounts.requester, &mut ctx.accounts.requester_profile),
+        ] {
+            collect_stake(
+                &new_game_info,
+                payer,
+                profile,
+                &ctx.accounts.system_program,
+                currency_type,
+                stake_amount,
+            )?;
+        }
+
+        let previous_game = ctx.accounts.game.key();
+        let new_game_key = ctx.accounts.new_game.key();
+        let game = &ctx.accounts.game;
+
+        let new_game = &mut ctx.accounts.new_game;
+        init_game(
+            new_game,
+            new_game_id,
+            game.player1,
+            game.player2,
+            stake_amount,
+            currency_type,
+            game.rounds_to_win,
+        )?;
+        new_game.previous_game = Some(previous_game);
+        new_game.ranked = game.ranked;
+        new_game.min_opponent_rating = game.min_opponent_rating;
+        new_game.max_opponent_rating = game.max_opponent_rating;
+
+        let game = &mut ctx.accounts.game;
+        game.rematch_requested_by = None;
+        game.next_game = Some(new_game_key);
+
+        msg!(
+            "Rematch of game {} accepted by {}, new game {}",
+            game.game_id,
+            player,
+            ctx.accounts.new_game.game_id
+        );
+        Ok(())
+    }
+
+    /// Create a best-of-N series. The series account escrows both stakes and
+    /// spawns one child game at a time, each played to `rounds_to_win` rounds.
+    /// A child game still unfinished `game_timeout` seconds after it started
+    /// can be forfeited, so an absent opponent cannot hold the stakes forever.
+    /// Series are staked in SOL or points; SPL tokens are refused.
+    pub fn create_series(
+        ctx: Context<CreateSeries>,
+        series_id: String,
+        stake_amount: u64,
+        currency_type: CurrencyType,
+        games_to_win: u8,
+        rounds_to_win: u8,
+        game_timeout: i64,
+    ) -> Result<()> {
+        series::create_series(
+            ctx,
+            series_id,
+            stake_amount,
+            currency_type,
+            games_to_win,
+            rounds_to_win,
+            game_timeout,
+        )
+    }
+
+    pub fn join_series(ctx: Context<JoinSeries>, _series_id: String) -> Result<()> {
+        series::join_series(ctx, _series_id)
+    }
+
+    /// Cancel a series nobody has joined yet and refund the creator
+    pub fn cancel_series(ctx: Context<CancelSeries>, _series_id: String) -> Result<()> {
+        series::cancel_series(ctx, _series_id)
+    }
+
+    /// Spawn the next child game of a series. The child game holds no stake;
+    /// the series escrow is paid out once by finalize_series.
+    pub fn start_series_game(
+        ctx: Context<StartSeriesGame>,
+        _series_id: String,
+        game_id: String,
+    ) -> Result<()> {
+        series::start_series_game(ctx, _series_id, game_id)
+    }
+
+    /// Tally the result of the current child game and close it. Anyone can call
+    /// this once the game is won, drawn or cancelled by agreement. Once
+    /// `game_timeout` has passed an unfinished game is forfeited by the same
+    /// rules as a tournament match. After `MAX_SERIES_UNDECIDED_GAMES` games
+    /// without a winner the series itself is drawn.
+    pub fn record_series_game(
+        ctx: Context<RecordSeriesGame>,
+        _series_id: String,
+        _game_id: String,
+    ) -> Result<()> {
+        series::record_series_game(ctx, _series_id, _game_id)
+    }
+
+    /// Pay out a decided series: the winner takes both stakes minus the platform fee.
+    /// A drawn series returns both stakes in full.
+    pub fn finalize_series(ctx: Context<FinalizeSeries>, _series_id: String) -> Result<()> {
+        series::finalize_series(ctx, _series_id)
+    }
+
+    /// Create a single-elimination tournament (admin only).
+    /// `rounds_to_win_by_stage[0]` applies to the final, `[1]` to the semifinals
+    /// and so on. `prize_split_bps` pays 1st, 2nd and 3rd (shared by both
+    /// semifinal losers) and must add up to 10000. SOL or points only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_tournament(
+        ctx: Context<CreateTournament>,
+        tournament_id: String,
+        currency_type: CurrencyType,
+        entry_fee: u64,
+        max_players: u8,
+        registration_ends_at: i64,
+        match_timeout: i64,
+        rounds_to_win_by_stage: [u8; 4],
+        prize_split_bps: [u16; 3],
+    ) -> Result<()> {
+        tournament::create_tournament(
+            ctx,
+            tournament_id,
+            currency_type,
+            entry_fee,
+            max_players,
+            registration_ends_at,
+            match_timeout,
+            rounds_to_win_by_stage,
+            prize_split_bps,
+        )
+    }
+
+    /// Register for a tournament by paying the entry fee
+    pub fn register_tournament(ctx: Context<RegisterTournament>, _tournament_id: String) -> Result<()> {
+        tournament::register_tournament(ctx, _tournament_id)
+    }
+
+    /// Close registration and seed the bracket. Anyone can call this once the
+    /// tournament is full or the registration window has ended. Players without
+    /// a first-round opponent get a bye. With fewer than 2 players the
+    /// tournament is cancelled and entries become refundable.
+    pub fn start_tournament(ctx: Context<StartTournament>, _tournament_id: String) -> Result<()> {
+        tournament::start_tournament(ctx, _tournament_id)
+    }
+
+    /// Spawn the child game for bracket node `node` once both of its seats are filled
+    pub fn start_tournament_match(
+        ctx: Context<StartTournamentMatch>,
+        _tournament_id: String,
+        node: u8,
+        game_id: String,
+    ) -> Result<()> {
+        tournament::start_tournament_match(ctx, _tournament_id, node, game_id)
+    }
+
+    /// Advance the winner of a settled tournament match and close its game.
+    /// A drawn or cancelled match is cleared so it can be replayed. Once `match_timeout` has passed
+    /// an unfinished match is forfeited: a player who committed in the current
+    /// round beats one who did not, then rounds won decide, then the upper seat.
+    pub fn record_tournament_match(
+        ctx: Context<RecordTournamentMatch>,
+        _tournament_id: String,
+        node: u8,
+        _game_id: String,
+    ) -> Result<()> {
+        tournament::record_tournament_match(ctx, _tournament_id, node, _game_id)
+    }
+
+    /// Claim a prize from a completed tournament, or an entry refund from a cancelled one
+    pub fn claim_tournament_payout(ctx: Context<ClaimTournamentPayout>, _tournament_id: String) -> Result<()> {
+        tournament::claim_tournament_payout(ctx, _tournament_id)
+    }
+
+    /// Close a tournament once every prize or refund has been claimed
+    pub fn close_tournament(ctx: Context<CloseTournament>, _tournament_id: String) -> Result<()> {
+        tournament::close_tournament(ctx, _tournament_id)
+    }
+
+    /// Create a battle-royale lobby for 3-16 players. The creator joins with
+    /// the first stake. SOL or points only.
+    pub fn create_battle_royale(
+        ctx: Context<CreateBattleRoyale>,
+        game_id: String,
+        stake_amount: u64,
+        currency_type: CurrencyType,
+        max_players: u8,
+        move_timeout: i64,
+    ) -> Result<()> {
+        royale::create_battle_royale(
+            ctx,
+            game_id,
+            stake_amount,
+            currency_type,
+            max_players,
+            move_timeout,
+        )
+    }
+
+    pub fn join_battle_royale(ctx: Context<JoinBattleRoyale>, _game_id: String) -> Result<()> {
+        royale::join_battle_royale(ctx, _game_id)
+    }
+
+    /// Leave a battle royale that has not started and take the stake back
+    pub fn leave_battle_royale(ctx: Context<LeaveBattleRoyale>, _game_id: String) -> Result<()> {
+        royale::leave_battle_royale(ctx, _game_id)
+    }
+
+    /// Cancel a battle royale nobody else has joined and refund the creator
+    pub fn cancel_battle_royale(ctx: Context<CancelBattleRoyale>, _game_id: String) -> Result<()> {
+        royale::cancel_battle_royale(ctx, _game_id)
+    }
+
+    /// Start the battle royale (creator only, at least 3 players)
+    pub fn start_battle_royale(ctx: Context<StartBattleRoyale>, _game_id: String) -> Result<()> {
+        royale::start_battle_royale(ctx, _game_id)
+    }
+
+    /// Commit a move for the current round. The reveal phase starts as soon as
+    /// every surviving player has committed.
+    pub fn commit_battle_royale_move(
+        ctx: Context<CommitBattleRoyaleMove>,
+        _game_id: String,
+        move_commitment: [u8; 32],
+    ) -> Result<()> {
+        royale::commit_battle_royale_move(ctx, _game_id, move_commitment)
+    }
+
+    /// Reveal a committed move. The round resolves as soon as every surviving
+    /// player has revealed.
+    pub fn reveal_battle_royale_move(
+        ctx: Context<RevealBattleRoyaleMove>,
+        _game_id: String,
+        player_move: Move,
+        nonce: u64,
+    ) -> Result<()> {
+        royale::reveal_battle_royale_move(ctx, _game_id, player_move, nonce)
+    }
+
+    /// After the phase deadline, eliminate surviving players who did not commit
+    /// or reveal and carry on. If nobody acted the game ends in a draw and the
+    /// survivors split the pot.
+    pub fn resolve_battle_royale_timeout(
+        ctx: Context<ResolveBattleRoyaleTimeout>,
+        _game_id: String,
+    ) -> Result<()> {
+        royale::resolve_battle_royale_timeout(ctx, _game_id)
+    }
+
+    /// Pay the last player standing the whole pot minus the platform fee, or split
+    /// it between the survivors of a drawn game. Every player's profile and wallet
+    /// are passed as remaining accounts in `players` order, so the result is
+    /// recorded for the eliminated players too.
+    pub fn finalize_battle_royale(ctx: Context<FinalizeBattleRoyale>, _game_id: String) -> Result<()> {
+        royale::finalize_battle_royale(ctx, _game_id)
+    }
+
+    /// Create a 2v2 team game. The creator takes the first seat of team 0.
+    /// Each commit or reveal phase lasts at most `move_timeout` seconds.
+    /// SOL or points only.
+    pub fn create_team_game(
+        ctx: Context<CreateTeamGame>,
+        game_id: String,
+        stake_amount: u64,
+        currency_type: CurrencyType,
+        rounds_to_win: u8,
+        decision: TeamDecision,
+        move_timeout: i64,
+    ) -> Result<()> {
+        team::create_team_game(
+            ctx,
+            game_id,
+            stake_amount,
+            currency_type,
+            rounds_to_win,
+            decision,
+            move_timeout,
+        )
+    }
+
+    /// Take a free seat on `team` (0 or 1). The game starts once all seats are taken.
+    pub fn join_team_game(ctx: Context<JoinTeamGame>, _game_id: String, team: u8) -> Result<()> {
+        team::join_team_game(ctx, _game_id, team)
+    }
+
+    /// Leave a team game that has not started and take the stake back
+    pub fn leave_team_game(ctx: Context<LeaveTeamGame>, _game_id: String) -> Result<()> {
+        team::leave_team_game(ctx, _game_id)
+    }
+
+    /// Cancel a team game nobody else has joined and refund the creator
+    pub fn cancel_team_game(ctx: Context<CancelTeamGame>, _game_id: String) -> Result<()> {
+        team::cancel_team_game(ctx, _game_id)
+    }
+
+    /// Vote for a team captain (captain mode). A captain is elected once every
+    /// member of the team has voted for the same teammate.
+    pub fn vote_captain(ctx: Context<VoteCaptain>, _game_id: String, candidate: Pubkey) -> Result<()> {
+        team::vote_captain(ctx, _game_id, candidate)
+    }
+
+    /// Commit a move vote for the current round. In captain mode only the
+    /// captain votes; in majority mode every member does. The reveal phase
+    /// starts once every voter of both teams has committed.
+    pub fn commit_team_move(
+        ctx: Context<CommitTeamMove>,
+        _game_id: String,
+        move_commitment: [u8; 32],
+    ) -> Result<()> {
+        team::commit_team_move(ctx, _game_id, move_commitment)
+    }
+
+    /// Reveal a committed move vote. Once every voter of both teams has
+    /// revealed, the team moves are decided and the round is scored. A split
+    /// majority vote is broken with a coin flip derived from the members'
+    /// committed nonces.
+    pub fn reveal_team_move(
+        ctx: Context<RevealTeamMove>,
+        _game_id: String,
+        player_move: Move,
+        nonce: u64,
+    ) -> Result<()> {
+        team::reveal_team_move(ctx, _game_id, player_move, nonce)
+    }
+
+    /// After the phase deadline, a team whose voters all committed (or revealed)
+    /// wins the game by forfeit. If neither team did, the game ends in a draw and
+    /// every stake is returned.
+    pub fn resolve_team_game_timeout(ctx: Context<ResolveTeamGameTimeout>, _game_id: String) -> Result<()> {
+        team::resolve_team_game_timeout(ctx, _game_id)
+    }
+
+    /// Split the pot evenly among the winning team and update every member's
+    /// profile. A drawn game returns every member's stake instead.
+    pub fn finalize_team_game(ctx: Context<FinalizeTeamGame>, _game_id: String) -> Result<()> {
+        team::finalize_team_game(ctx, _game_id)
+    }
+
+    /// Create the house vault that backs player-vs-house games (admin only)
+    pub fn initialize_house_vault(
+        ctx: Context<InitializeHouseVault>,
+        house_authority: Pubkey,
+        params: HouseParams,
+    ) -> Result<()> {
+        house::initialize_house_vault(ctx, house_authority, params)
+    }
+
+    /// Update the house edge, exposure limits, timeout and authority (admin only)
+    pub fn configure_house(
+        ctx: Context<ConfigureHouse>,
+        house_authority: Pubkey,
+        params: HouseParams,
+    ) -> Result<()> {
+        house::configure_house(ctx, house_authority, params)
+    }
+
+    /// Open an LP position for the house bankroll
+    pub fn open_lp_position(ctx: Context<OpenLpPosition>) -> Result<()> {
+        house::open_lp_position(ctx)
+    }
+
+    /// Deposit SOL into the house vault in exchange for shares priced at the
+    /// vault's current equity. The first deposit mints one share per lamport of
+    /// equity after the deposit; `LP_DEAD_SHARES` of them, and any equity the
+    /// vault already held, stay unowned.
+    pub fn deposit_liquidity(ctx: Context<DepositLiquidity>, amount: u64) -> Result<()> {
+        house::deposit_liquidity(ctx, amount)
+    }
+
+    /// Start the withdrawal cooldown for `shares`. The shares keep sharing
+    /// house profit and loss until the withdrawal is completed.
+    pub fn request_lp_withdrawal(ctx: Context<RequestLpWithdrawal>, shares: u64) -> Result<()> {
+        house::request_lp_withdrawal(ctx, shares)
+    }
+
+    pub fn cancel_lp_withdrawal(ctx: Context<RequestLpWithdrawal>) -> Result<()> {
+        house::cancel_lp_withdrawal(ctx)
+    }
+
+    /// Redeem the pending shares once the cooldown has passed, at the share
+    /// price at completion time. Only the vault's free balance can be paid out.
+    /// A request not completed within `LP_WITHDRAWAL_WINDOW` after the cooldown
+    /// lapses and has to be made again.
+    pub fn complete_lp_withdrawal(ctx: Context<CompleteLpWithdrawal>) -> Result<()> {
+        house::complete_lp_withdrawal(ctx)
+    }
+
+    /// Close an empty LP position and reclaim its rent
+    pub fn close_lp_position(ctx: Context<CloseLpPosition>) -> Result<()> {
+        house::close_lp_position(ctx)
+    }
+
+    /// Start a single-round SOL game against the house with a committed move.
+    /// The house's potential loss is reserved in the vault and counted against
+    /// the per-game and per-slot exposure limits.
+    pub fn create_house_game(
+        ctx: Context<CreateHouseGame>,
+        game_id: String,
+        stake_amount: u64,
+        move_commitment: [u8; 32],
+    ) -> Result<()> {
+        house::create_house_game(ctx, game_id, stake_amount, move_commitment)
+    }
+
+    /// House authority commits to its seed before the player reveals
+    pub fn commit_house_seed(
+        ctx: Context<CommitHouseSeed>,
+        _game_id: String,
+        seed_hash: [u8; 32],
+    ) -> Result<()> {
+        house::commit_house_seed(ctx, _game_id, seed_hash)
+    }
+
+    /// Ask the MagicBlock VRF for the house move instead of waiting for the house
+    /// authority's seed. The oracle answers through `house_vrf_callback`.
+    #[cfg(feature = "ephemeral")]
+    pub fn request_house_randomness(ctx: Context<RequestHouseRandomness>, _game_id: String) -> Result<()> {
+        house::request_house_randomness(ctx, _game_id)
+    }
+
+    /// VRF callback delivering the house move. Only the VRF program can sign as
+    /// this program's scoped VRF identity.
+    #[cfg(feature = "ephemeral")]
+    pub fn house_vrf_callback(ctx: Context<HouseVrfCallback>, randomness: [u8; 32]) -> Result<()> {
+        house::house_vrf_callback(ctx, randomness)
+    }
+
+    /// Player reveals the committed move. With a VRF house move the game settles
+    /// right away; otherwise it waits for the house to reveal its seed.
+    pub fn reveal_house_move(
+        ctx: Context<RevealHouseMove>,
+        _game_id: String,
+        player_move: Move,
+        nonce: u64,
+    ) -> Result<()> {
+        house::reveal_house_move(ctx, _game_id, player_move, nonce)
+    }
+
+    /// House authority reveals its seed; the house move is derived from the
+    /// seed and the player's nonce, then the game settles.
+    pub fn reveal_house_seed(
+        ctx: Context<RevealHouseSeed>,
+        _game_id: String,
+        seed: [u8; 32],
+    ) -> Result<()> {
+        house::reveal_house_seed(ctx, _game_id, seed)
+    }
+
+    /// Resolve a house game whose deadline has passed. If the house never
+    /// committed its seed the stake is refunded; if the player never revealed
+    /// the house keeps the stake; if the house never revealed its seed the
+    /// player is paid as the winner.
+    pub fn claim_house_timeout(ctx: Context<ClaimHouseTimeout>, _game_id: String) -> Result<()> {
+        house::claim_house_timeout(ctx, _game_id)
+    }
+
+    /// Close a settled house game and return rent to the player
+    pub fn close_house_game(ctx: Context<CloseHouseGame>, _game_id: String) -> Result<()> {
+        house::close_house_game(ctx, _game_id)
+    }
+
+    /// Create the progressive jackpot vault (admin only)
+    pub fn initialize_jackpot(ctx: Context<InitializeJackpot>, params: JackpotParams) -> Result<()> {
+        validate_jackpot_params(&params)?;
+
+        let jackpot = &mut ctx.accounts.jackpot;
+        jackpot.params = params;
+        jackpot.total_contributed = 0;
+        jackpot.total_paid = 0;
+        jackpot.contributions = 0;
+        jackpot.payouts = 0;
+        jackpot.last_winner = None;
+        jackpot.last_payout = 0;
+        jackpot.last_paid_at = 0;
+
+        msg!("Jackpot initialized with fee share {} bps", params.fee_share_bps);
+        Ok(())
+    }
+
+    /// Open a spectator side bet pool on a game that has not started playing yet
+    pub fn open_side_bet_pool(ctx: Context<OpenSideBetPool>, _game_id: String) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        require!(game.parent.is_none(), GameError::ChildGame);
+        require!(side_bets_accepted(game), GameError::BettingClosed);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.game = game.key();
+        pool.creator = ctx.accounts.creator.key();
+        pool.fee_bps = ctx.accounts.config.side_bet_fee_bps;
+        pool.total_on_player1 = 0;
+        pool.total_on_player2 = 0;
+        pool.open_bets = 0;
+        pool.outcome = None;
+        pool.player1 = game.player1;
+        pool.player2 = game.player2;
+        pool.created_at = Clock::get()?.unix_timestamp;
+        game.side_bets_open = true;
+
+        msg!("Side bet pool opened for game {}", game.game_id);
+        Ok(())
+    }
+
+    /// Bet SOL on one of the players. Players cannot bet on their own game.
+    pub fn place_side_bet(
+        ctx: Context<PlaceSideBet>,
+        _game_id: String,
+        side: BetSide,
+        amount: u64,
+    ) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let bettor = ctx.accounts.bettor.key();
+        require!(side_bets_accepted(game), GameError::BettingClosed);
+        require!(
+            bettor != game.player1 && Some(bettor) != game.player2,
+            GameError::PlayerCannotBet
+        );
+        require!(amount > 0, GameError::InvalidStakeAmount);
+
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                &bettor,
+                &ctx.accounts.pool.key(),
+                amount,
+            ),
+            &[
+                ctx.accounts.bettor.to_account_info(),
+                ctx.accounts.pool.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let pool = &mut ctx.accounts.pool;
+        match side {
+            BetSide::Player1 => pool.total_on_player1 += amount,
+            BetSide::Player2 => pool.total_on_player2 += amount,
+        }
+        pool.open_bets += 1;
+
+        let bet = &mut ctx.accounts.bet;
+        bet.pool = pool.key();
+        bet.bettor = bettor;
+        bet.side = side;
+        bet.amount = amount;
+
+        msg!("Side bet of {} lamports placed by {} on game {}", amount, bettor, game.game_id);
+        Ok(())
+    }
+
+    /// Lock in the pool outcome once the game has settled. Bets are refunded when the
+    /// game was drawn, cancelled or abandoned, or when it outlives the pool's expiry.
+    /// Cancelling a waiting game refunds its pool directly.
+    /// Permissionless so spectators are not at the mercy of the players.
+    pub fn settle_side_bets(ctx: Context<SettleSideBets>, _game_id: String) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        require!(pool.outcome.is_none(), GameError::GameAlreadySettled);
+
+        let game_info = ctx.accounts.game.to_account_info();
+        let now = Clock::get()?.unix_timestamp;
+        let expired = now >= pool.created_at + SIDE_BET_EXPIRY;
+
+        // A game closed without settling leaves nothing to bet on
+        let outcome = if game_info.owner != &crate::ID || game_info.data_is_empty() {
+            SideBetOutcome::Refund
+        } else {
+            let mut game = Game::try_deserialize(&mut &game_info.try_borrow_data()?[..])?;
+            let outcome = match (game.game_status.clone(), game.winner) {
+                (GameStatus::Finished | GameStatus::Settled, Some(winner)) if winner == game.player1 => {
+                    SideBetOutcome::Player1
+                }
+                (GameStatus::Finished | GameStatus::Settled, Some(_)) => SideBetOutcome::Player2,
+                (GameStatus::Drawn | GameStatus::Cancelled | GameStatus::Abandoned, _) => SideBetOutcome::Refund,
+                _ => {
+                    require!(expired, GameError::GameNotFinished);
+                    SideBetOutcome::Refund
+                }
+            };
+            pool.player2 = game.player2;
+            game.side_bets_open = false;
+            game.try_serialize(&mut &mut game_info.try_borrow_mut_data()?[..])?;
+            outcome
+        };
+
+        // With nobody on the winning side there is no one to pay, so everyone is refunded
+        let winning_total = match outcome {
+            SideBetOutcome::Player1 => pool.total_on_player1,
+            SideBetOutcome::Player2 => pool.total_on_player2,
+            SideBetOutcome::Refund => 0,
+        };
+        let outcome = if winning_total == 0 { SideBetOutcome::Refund } else { outcome };
+
+        if outcome != SideBetOutcome::Refund {
+            let fee = (pool.total_on_player1 + pool.total_on_player2) * pool.fee_bps as u64 / 10000;
+            move_lamports(&pool.to_account_info(), &ctx.accounts.platform_wallet.to_account_info(), fee)?;
+            msg!("Side bet platform cut: {} lamports", fee);
+        }
+        pool.outcome = Some(outcome);
+
+        msg!("Side bet pool for game {} settled: {:?}", pool.game, outcome);
+        Ok(())
+    }
+
+    /// Collect a settled side bet: winnings pari-mutuel, or the stake on a refund
+    pub fn claim_side_bet(ctx: Context<ClaimSideBet>, _game_id: String) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let bet = &ctx.accounts.bet;
+        let outcome = pool.outcome.ok_or(GameError::GameNotFinished)?;
+
+        let total = pool.total_on_player1 + pool.total_on_player2;
+        let amount = match (outcome, bet.side) {
+            (SideBetOutcome::Refund, _) => bet.amount,
+            // A bettor who went on to play the game forfeits the bet
+            _ if bet.bettor == pool.player1 || Some(bet.bettor) == pool.player2 => 0,
+            (SideBetOutcome::Player1, BetSide::Player1) | (SideBetOutcome::Player2, BetSide::Player2) => {
+                let winning_total = if bet.side == BetSide::Player1 {
+                    pool.total_on_player1
+                } else {
+                    pool.total_on_player2
+                };
+                let net = total - total * pool.fee_bps as u64 / 10000;
+                (bet.amount as u128 * net as u128 / winning_total as u128) as u64
+            }
+            _ => 0,
+        };
+
+        if amount > 0 {
+            move_lamports(&pool.to_account_info(), &ctx.accounts.bettor.to_account_info(), amount)?;
+        }
+        pool.open_bets -= 1;
+
+        msg!("Side bet claimed by {}: {} lamports", bet.bettor, amount);
+        Ok(())
+    }
+
+    /// Close a fully claimed pool. Rounding dust and forfeited bets go to the platform
+    /// wallet and rent returns to whoever opened the pool.
+    pub fn close_side_bet_pool(ctx: Context<CloseSideBetPool>, _game_id: String) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        require!(pool.outcome.is_some(), GameError::GameNotFinished);
+        require!(pool.open_bets == 0, GameError::PayoutsPending);
+
+        let info = pool.to_account_info();
+        let rent = Rent::get()?.minimum_balance(info.data_len());
+        let dust = info.lamports().saturating_sub(rent);
+        if dust > 0 {
+            move_lamports(&info, &ctx.accounts.platform_wallet.to_account_info(), dust)?;
+        }
+
+        msg!("Side bet pool for game {} closed", pool.game);
+        Ok(())
+    }
+
+    /// Draw a finalized game's jackpot entry once its draw slot has been produced.
+    /// Permissionless. An entry not drawn while its slot is still in SlotHashes
+    /// (about 512 slots) lapses.
+    pub fn draw_jackpot(ctx: Context<DrawJackpot>, _game_id: String) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let draw_slot = game.jackpot_draw_slot.ok_or(GameError::NoJackpotEntry)?;
+        let winner_key = game.winner.ok_or(GameError::NoWinner)?;
+        let draw = jackpot_draw(&ctx.accounts.slot_hashes, draw_slot, &game.key())?;
+        game.jackpot_draw_slot = None;
+
+        let Some(draw) = draw else {
+            msg!("Jackpot entry of game {} lapsed", game.game_id);
+            return Ok(());
+        };
+        let jackpot = &mut ctx.accounts.jackpot;
+        let jackpot_info = jackpot.to_account_info();
+        let amount = jackpot_balance(&jackpot_info)?;
+        if draw % jackpot.params.odds != 0 || amount == 0 {
+            msg!("Game {} did not win the jackpot", game.game_id);
+            return Ok(());
+        }
+
+        move_lamports(&jackpot_info, &ctx.accounts.winner.to_account_info(), amount)?;
+        let now = Clock::get()?.unix_timestamp;
+        jackpot.total_paid += amount;
+        jackpot.payouts += 1;
+        jackpot.last_winner = Some(winner_key);
+        jackpot.last_payout = amount;
+        jackpot.last_paid_at = now;
+
+        emit!(JackpotWon {
+            game: game.key(),
+            winner: winner_key,
+            amount,
+            timestamp: now,
+        });
+        msg!("Jackpot of {} lamports won by {}", amount, winner_key);
+        Ok(())
+    }
+
+    /// Update the jackpot fee share and trigger rules (admin only)
+    pub fn configure_jackpot(ctx: Context<ConfigureJackpot>, params: JackpotParams) -> Result<()> {
+        validate_jackpot_params(&params)?;
+        ctx.accounts.jackpot.params = params;
+
+        msg!(
+            "Jackpot updated: fee share {} bps, min rounds {}, min stake {}, odds 1 in {}",
+            params.fee_share_bps,
+            params.min_rounds_to_win,
+            params.min_stake,
+            params.odds
+        );
+        Ok(())
+    }
+
+    /// Allow a mint for SPL token games with its stake-based fee tiers (admin only).
+    /// Token-2022 mints with a transfer hook need that hook program approved here.
+    pub fn add_allowed_mint(
+        ctx: Context<AddAllowedMint>,
+        fee_tiers: [FeeTier; 3],
+        transfer_hook_program: Option<Pubkey>,
+    ) -> Result<()> {
+        tokens::add_allowed_mint(ctx, fee_tiers, transfer_hook_program)
+    }
+
+    /// Enable or disable an allowed mint and update its fee tiers (admin only).
+    /// Disabling stops new games; games already escrowed still settle.
+    pub fn update_allowed_mint(
+        ctx: Context<UpdateAllowedMint>,
+        enabled: bool,
+        fee_tiers: [FeeTier; 3],
+        transfer_hook_program: Option<Pubkey>,
+    ) -> Result<()> {
+        tokens::update_allowed_mint(ctx, enabled, fee_tiers, transfer_hook_program)
+    }
+
+    /// Register the Pyth feed id used to convert USD stakes for `asset`
+    /// (a mint, or the default key for SOL) along with its staleness and
+    /// confidence limits (admin only)
+    pub fn register_price_feed(
+        ctx: Context<RegisterPriceFeed>,
+        asset: Pubkey,
+        feed_id: [u8; 32],
+        max_staleness: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        require!(max_staleness > 0, GameError::InvalidTimeout);
+        require!(max_confidence_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+
+        let price_feed = &mut ctx.accounts.price_feed;
+        price_feed.asset = asset;
+        price_feed.feed_id = feed_id;
+        price_feed.max_staleness = max_staleness;
+        price_feed.max_confidence_bps = max_confidence_bps;
+
+        msg!("Price feed registered for {}", asset);
+        Ok(())
+    }
+
+    /// Point an asset at a new Pyth feed id or change its limits (admin only)
+    pub fn update_price_feed(
+        ctx: Context<UpdatePriceFeed>,
+        feed_id: [u8; 32],
+        max_staleness: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        require!(max_staleness > 0, GameError::InvalidTimeout);
+        require!(max_confidence_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+
+        let price_feed = &mut ctx.accounts.price_feed;
+        price_feed.feed_id = feed_id;
+        price_feed.max_staleness = max_staleness;
+        price_feed.max_confidence_bps = max_confidence_bps;
+
+        msg!("Price feed for {} updated", price_feed.asset);
+        Ok(())
+    }
+
+    /// Create the points faucet settings (admin only)
+    pub fn initialize_points_config(ctx: Context<InitializePointsConfig>, params: PointsParams) -> Result<()> {
+        points::initialize_points_config(ctx, params)
+    }
+
+    /// Update the points faucet settings (admin only)
+    pub fn update_points_config(ctx: Context<UpdatePointsConfig>, params: PointsParams) -> Result<()> {
+        points::update_points_config(ctx, params)
+    }
+
+    /// Claim the daily points grant. Claiming again within twice the cooldown
+    /// keeps the streak going and adds the streak bonus.
+    pub fn claim_daily_points(ctx: Context<ClaimDailyPoints>) -> Result<()> {
+        points::claim_daily_points(ctx)
+    }
+
+    /// Send ledger points to another profile. A share of the amount set by the
+    /// points config is burned, and each sender has a daily transfer limit.
+    pub fn transfer_points(ctx: Context<TransferPoints>, amount: u64) -> Result<()> {
+        points::transfer_points(ctx, amount)
+    }
+
+    /// Create the program-controlled points mint (admin only). The mint is its own
+    /// mint authority, so only this program can issue points tokens.
+    pub fn initialize_points_mint(ctx: Context<InitializePointsMint>) -> Result<()> {
+        points::initialize_points_mint(ctx)
+    }
+
+    /// Move a profile's ledger points into points tokens. Permissionless, so existing
+    /// profiles can be migrated in bulk; the tokens always go to the profile owner.
+    /// Profiles still in the pre-upgrade layout are grown first, with the payer
+    /// covering the extra rent. A migrated profile plays points only in one-on-one
+    /// games; series, tournaments, battle royales, team games and the matchmaking
+    /// queue stake ledger points and refuse it.
+    pub fn migrate_points_to_tokens(ctx: Context<MigratePoints>) -> Result<()> {
+        points::migrate_points_to_tokens(ctx)
+    }
+
+    /// Create a promo code worth `amount` points (admin only). Only the hash of
+    /// the code is stored, so the code stays secret until the campaign starts.
+    pub fn create_promo(
+        ctx: Context<CreatePromo>,
+        code_hash: [u8; 32],
+        amount: u64,
+        max_redemptions: u32,
+        expires_at: i64,
+    ) -> Result<()> {
+        points::create_promo(ctx, code_hash, amount, max_redemptions, expires_at)
+    }
+
+    /// Redeem a promo code for points. The redemption marker account makes a
+    /// second redemption by the same user fail.
+    pub fn redeem_promo(ctx: Context<RedeemPromo>, _code: String) -> Result<()> {
+        points::redeem_promo(ctx, _code)
+    }
+
+    /// Create a reward distribution committed to by a merkle root (admin only).
+    /// SOL distributions are funded with `total_amount` up front; points
+    /// distributions are credited as they are claimed.
+    pub fn create_distributor(
+        ctx: Context<CreateDistributor>,
+        distributor_id: String,
+        merkle_root: [u8; 32],
+        currency_type: CurrencyType,
+        total_amount: u64,
+        num_recipients: u32,
+        expires_at: i64,
+    ) -> Result<()> {
+        points::create_distributor(
+            ctx,
+            distributor_id,
+            merkle_root,
+            currency_type,
+            total_amount,
+            num_recipients,
+            expires_at,
+        )
+    }
+
+    /// Claim a reward by proving (index, player, amount) is a leaf of the
+    /// distributor's merkle tree. Each index can be claimed once.
+    pub fn claim_reward(
+        ctx: Context<ClaimReward>,
+        _distributor_id: String,
+        index: u32,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        points::claim_reward(ctx, _distributor_id, index, amount, proof)
+    }
+
+    /// Close an expired distributor, returning unclaimed SOL and rent to the admin
+    pub fn clawback_distributor(ctx: Context<ClawbackDistributor>, _distributor_id: String) -> Result<()> {
+        points::clawback_distributor(ctx, _distributor_id)
+    }
+
+    /// Start the first season (admin only)
+    pub fn initialize_season(ctx: Context<InitializeSeason>, duration: i64) -> Result<()> {
+        seasons::initialize_season(ctx, duration)
+    }
+
+    /// End the active season now and start the next one with `duration` (admin only).
+    /// Seasons also roll over on their own when a game is finalized after they end.
+    pub fn start_new_season(ctx: Context<StartNewSeason>, duration: i64) -> Result<()> {
+        seasons::start_new_season(ctx, duration)
+    }
+
+    /// Create the caller's stats account for the active season
+    pub fn open_season_stats(ctx: Context<OpenSeasonStats>) -> Result<()> {
+        seasons::open_season_stats(ctx)
+    }
+
+    /// Create the leaderboard for `season_id`, or the all-time leaderboard for 0 (admin only)
+    pub fn initialize_leaderboard(ctx: Context<InitializeLeaderboard>, season_id: u32) -> Result<()> {
+        seasons::initialize_leaderboard(ctx, season_id)
+    }
+
+    /// Create the rating settings (admin only)
+    pub fn initialize_rating_config(ctx: Context<InitializeRatingConfig>, params: RatingParams) -> Result<()> {
+        validate_rating_params(&params)?;
+        ctx.accounts.rating_config.params = params;
+
+        msg!("Ratings initialized: default {}, k-factor {}", params.default_rating, params.k_factor);
+        Ok(())
+    }
+
+    /// Update the rating settings (admin only). Existing ratings are kept.
+    pub fn update_rating_config(ctx: Context<UpdateRatingConfig>, params: RatingParams) -> Result<()> {
+        validate_rating_params(&params)?;
+        ctx.accounts.rating_config.params = params;
+
+        msg!("Ratings updated: default {}, k-factor {}", params.default_rating, params.k_factor);
+        Ok(())
+    }
+
+    /// Create the matchmaking queue for a (currency, stake) tier (admin only).
+    /// Queues hold SOL or points stakes only. Ranked queues only pair players
+    /// rated at most `max_rating_gap` apart.
+    pub fn create_match_queue(
+        ctx: Context<CreateMatchQueue>,
+        currency_type: CurrencyType,
+        stake_amount: u64,
+        rounds_to_win: u8,
+        ranked: bool,
+        max_rating_gap: u32,
+    ) -> Result<()> {
+        queue::create_match_queue(
+            ctx,
+            currency_type,
+            stake_amount,
+            rounds_to_win,
+            ranked,
+            max_rating_gap,
+        )
+    }
+
+    /// Join a matchmaking queue. The stake is deposited into the queue until the
+    /// player is matched or leaves. Ranked queues record the player's rating as
+    /// of joining.
+    pub fn enqueue(ctx: Context<Enqueue>) -> Result<()> {
+        queue::enqueue(ctx)
+    }
+
+    /// Leave a matchmaking queue and get the stake back
+    pub fn dequeue(ctx: Context<Enqueue>) -> Result<()> {
+        queue::dequeue(ctx)
+    }
+
+    /// Pair the two longest-waiting compatible players of a queue into a new game.
+    /// Anyone can crank this; the caller fronts the game account's rent and gets it
+    /// back on close.
+    pub fn match_players(ctx: Context<MatchPlayers>, game_id: String) -> Result<()> {
+        queue::match_players(ctx, game_id)
+    }
+
+    /// Create a lobby page. `currency_type` restricts the page to one currency and
+    /// `None` accepts games of any currency. Pages are numbered from 0; anyone can
+    /// open the next page once the last one fills up.
+    pub fn create_lobby_page(
+        ctx: Context<CreateLobbyPage>,
+        currency_type: Option<CurrencyType>,
+        page: u32,
+    ) -> Result<()> {
+        lobby::create_lobby_page(ctx, currency_type, page)
+    }
+
+    /// Drop a game that has waited longer than `LOBBY_ENTRY_TTL` from its lobby
+    /// page. Permissionless; the game itself can still be joined or cancelled.
+    pub fn expire_lobby_entry(ctx: Context<ExpireLobbyEntry>, _game_id: String) -> Result<()> {
+        lobby::expire_lobby_entry(ctx, _game_id)
+    }
+
+    /// Offer to double the stake between rounds. The offerer's top-up is escrowed
+    /// in the game until the opponent accepts or declines. SOL and points games only.
+    pub fn offer_double(ctx: Context<OfferDouble>, _game_id: String) -> Result<()> {
+        let player = ctx.accounts.user.key();
+        let game = &ctx.accounts.game;
+
+        require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        require!(game.parent.is_none(), GameError::ChildGame);
+        require!(game.double_offered_by.is_none(), GameError::DoublePending);
+        require!(game.mint.is_none(), GameError::UnsupportedCurrency);
+        require!(
+            game.player1_move_commitment.is_none() && game.player2_move_commitment.is_none(),
+            GameError::RoundInProgress
+        );
+        require!(
+            game.cube_owner.is_none_or(|owner| owner == player),
+            GameError::NotCubeOwner
+        );
+        require!(
+            game.stake_multiplier as u16 * 2 <= ctx.accounts.config.max_stake_multiplier as u16,
+            GameError::StakeMultiplierCap
+        );
+
+        escrow_double_top_up(
+            &ctx.accounts.game,
+            &ctx.accounts.user,
+            &mut ctx.accounts.user_profile,
+            &ctx.accounts.system_program,
+        )?;
+
+        let game = &mut ctx.accounts.game;
+        game.double_offered_by = Some(player);
+
+        msg!("Player {} offered to double the stake in game {}", player, game.game_id);
+        Ok(())
+    }
+
+    /// Accept a doubling offer by matching the top-up; the stake doubles and the
+    /// accepter takes the cube.
+    pub fn accept_double(ctx: Context<OfferDouble>, _game_id: String) -> Result<()> {
+        let player = ctx.accounts.user.key();
+        let game = &ctx.accounts.game;
+
+        require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        let offered_by = game.double_offered_by.ok_or(GameError::NoDoubleOffer)?;
+        require!(offered_by != player, GameError::CannotAcceptOwnOffer);
+
+        escrow_double_top_up(
+            &ctx.accounts.game,
+            &ctx.accounts.user,
+            &mut ctx.accounts.user_profile,
+            &ctx.accounts.system_program,
+        )?;
+
+        let game = &mut ctx.accounts.game;
+        game.stake_amount *= 2;
+        game.stake_multiplier *= 2;
+        game.double_offered_by = None;
+        game.cube_owner = Some(player);
+
+        msg!(
+            "Game {} doubled to {}x, stake now {}",
+            game.game_id,
+            game.stake_multiplier,
+            game.stake_amount
+        );
+        Ok(())
+    }
+
+    /// Decline a doubling offer, forfeiting the match at the current stake.
+    /// The offerer's top-up is returned and the game is left for `finalize_game`.
+    pub fn decline_double(ctx: Context<DeclineDouble>, _game_id: String) -> Result<()> {
+        let player = ctx.accounts.user.key();
+        let game = &ctx.accounts.game;
+
+        require!(game.game_status == GameStatus::InProgress, GameError::GameNotInProgress);
+        require!(
+            player == game.player1 || player == game.player2.unwrap_or(game.player1),
+            GameError::NotAPlayer
+        );
+        let offered_by = game.double_offered_by.ok_or(GameError::NoDoubleOffer)?;
+        require!(offered_by != player, GameError::CannotAcceptOwnOffer);
+        require!(ctx.accounts.offerer.key() == offered_by, GameError::NotAPlayer);
+
+        let top_up = game.stake_amount;
+        if game.currency_type == CurrencyType::Points {
+            ctx.accounts.offerer_profile.points_balance += top_up;
+        } else if game.currency_type == CurrencyType::Sol {
+            move_lamports(
+                &ctx.accounts.game.to_account_info(),
+                &ctx.accounts.offerer.to_account_info(),
+                top_up,
+            )?;
+        }
+
+        let game = &mut ctx.accounts.game;
+        game.double_offered_by = None;
+        game.draw_offered_by = None;
+        game.cancel_proposed_by = None;
+        game.winner = Some(offered_by);
+        game.game_status = GameStatus::Finished;
+
+        msg!("Player {} declined the double; game {} forfeited to {}", player, game.game_id, offered_by);
+        Ok(())
+    }
+}
+
+// Account validation structures
+#[derive(Accounts)]
+pub struct InitializeUserProfile<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = USER_PROFILE_SPACE,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// Faucet settings; omit before the faucet is configured
+    #[account(seeds = [b"points_config"], bump)]
+    pub points_config: Option<Account<'info, PointsConfig>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetReferrer<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", referrer.key().as_ref()],
+        bump
+    )]
+    pub referrer_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// CHECK: This account is validated by the referrer_profile seeds
+    pub referrer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CreateGame<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = GAME_SPACE,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // The accounts below are only needed for SPL token games
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    /// Checked in the handler against `mint`
+    pub allowed_mint: Option<Account<'info, AllowedMint>>,
+    
+    #[account(
+        init,
+        payer = user,
+        token::mint = mint,
+        token::authority = game,
+        token::token_program = token_program,
+        seeds = [b"escrow", game.key().as_ref()],
+        bump
+    )]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut, token::authority = user)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    // The accounts below are only needed for USD-denominated stakes
+    pub price_feed: Option<Account<'info, PriceFeed>>,
+    
+    /// CHECK: Pyth PriceUpdateV2 account; owner and feed id are checked against `price_feed` when parsed
+    pub price_account: Option<UncheckedAccount<'info>>,
+    
+    /// Lobby page to list the game in; omit to leave the game unlisted
+    #[account(mut)]
+    pub lobby: Option<Account<'info, Lobby>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct JoinGame<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // The accounts below are only needed for SPL token games
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    /// Checked in the handler against `mint`
+    pub allowed_mint: Option<Account<'info, AllowedMint>>,
+    
+    #[account(mut, seeds = [b"escrow", game.key().as_ref()], bump)]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut, token::authority = user)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// Rating settings, required to join a rating-gated game
+    #[account(seeds = [b"rating_config"], bump)]
+    pub rating_config: Option<Account<'info, RatingConfig>>,
+    
+    /// Lobby page listing the game, required while the game is listed
+    #[account(mut)]
+    pub lobby: Option<Account<'info, Lobby>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CommitMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct RevealMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct ProcessRound<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct SetWinner<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct FinalizeGameWithReferral<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", game.player1.as_ref()],
+        bump
+    )]
+    pub player1_profile: Account<'info, UserProfile>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_profile", game.player2.unwrap().as_ref()],
+        bump
+    )]
+    pub player2_profile: Account<'info, UserProfile>,
+    
+    /// CHECK: This account is checked against the game's player1 field
+    #[account(mut, address = game.player1)]
+    pub player1: UncheckedAccount<'info>,
+    
+    /// CHECK: This account is checked against the game's player2 field
+    #[account(mut, address = game.player2.unwrap())]
+    pub player2: UncheckedAccount<'info>,
+    
+    /// CHECK: Platform wallet for collecting fees
+    #[account(mut)]
+    pub platform_wallet: UncheckedAccount<'info>,
+    
+    /// CHECK: Referrer account for commission payouts (can be same as platform_wallet if no referrer)
+    #[account(mut)]
+    pub referrer: UncheckedAccount<'info>,
+    
+    /// Referrer profile for tracking earnings (can be any profile if no referrer)
+    #[account(mut)]
+    pub referrer_profile: Account<'info, UserProfile>,
+    
+    /// CHECK: Progressive jackpot PDA; loaded by hand so it is used whenever it has been initialized
+    #[account(mut, seeds = [b"jackpot"], bump)]
+    pub jackpot: UncheckedAccount<'info>,
+    
+    // The accounts below are only needed for SPL token games.
+    // The mint is writable so withheld Token-2022 transfer fees can be harvested to it.
+    #[account(mut)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    /// Checked in the handler against `mint`
+    pub allowed_mint: Option<Account<'info, AllowedMint>>,
+    
+    #[account(mut, seeds = [b"escrow", game.key().as_ref()], bump)]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub winner_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub platform_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// Points mint, required when the winner's profile has migrated to tokens
+    #[account(mut, seeds = [b"points_mint"], bump)]
+    pub points_mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    /// CHECK: Season PDA; loaded by hand so ranked games always count once seasons have started
+    #[account(mut, seeds = [b"season"], bump)]
+    pub season: UncheckedAccount<'info>,
+    
+    /// CHECK: Player1's stats PDA for the active season, required once seasons have
+    /// started; checked in the handler and created if missing
+    #[account(mut)]
+    pub player1_season_stats: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Player2's stats PDA for the active season, as above
+    #[account(mut)]
+    pub player2_season_stats: Option<UncheckedAccount<'info>>,
+    
+    /// Pays for season stats accounts that don't exist yet
+    #[account(mut)]
+    pub payer: Option<Signer<'info>>,
+    
+    /// CHECK: All-time leaderboard PDA; loaded by hand so it is updated whenever it has been initialized
+    #[account(mut, seeds = [b"leaderboard", 0u32.to_le_bytes().as_ref()], bump)]
+    pub leaderboard: UncheckedAccount<'info>,
+    
+    /// CHECK: Leaderboard PDA of the active season; checked in the handler against the
+    /// season id and updated whenever it has been initialized
+    #[account(mut)]
+    pub season_leaderboard: UncheckedAccount<'info>,
+    
+    /// Rating settings; omit to finalize without updating ratings
+    #[account(seeds = [b"rating_config"], bump)]
+    pub rating_config: Option<Account<'info, RatingConfig>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CancelGame<'info> {
+    #[account(
+        mut,
+        close = user, // Close account and return rent to creator (who signs the transaction)
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    #[account(mut, signer)]
+    pub user: SystemAccount<'info>,
+    
+    /// Creator's profile, required to refund points games
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Option<Account<'info, UserProfile>>,
+    
+    // The accounts below are only needed for SPL token games.
+    // The mint is writable so withheld Token-2022 transfer fees can be harvested to it.
+    #[account(mut)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    
+    #[account(mut, seeds = [b"escrow", game.key().as_ref()], bump)]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    #[account(mut, token::authority = user)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// Lobby page listing the game, required while the game is listed
+    #[account(mut)]
+    pub lobby: Option<Account<'info, Lobby>>,
+    
+    /// Spectator pool on the game, required while one is open
+    #[account(mut, seeds = [b"side_bet_pool", game.key().as_ref()], bump)]
+    pub side_bet_pool: Option<Account<'info, SideBetPool>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CloseGame<'info> {
+    #[account(
+        mut,
+        close = rent_receiver, // Close account and return rent to whoever paid it
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+    
+    /// CHECK: Rent is returned to the game's rent payer (player1 unless matched from a queue), not to signer
+    #[account(mut, address = game.rent_payer.unwrap_or(game.player1))]
+    pub rent_receiver: UncheckedAccount<'info>,
+    
+    /// Service wallet or creator can sign this transaction
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct RequestRematch<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CancelRematch<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String, new_game_id: String)]
+pub struct AcceptRematch<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(
+        init,
+        payer = user,
+        space = GAME_SPACE,
+        seeds = [b"game", new_game_id.as_bytes()],
+        bump
+    )]
+    pub new_game: Account<'info, Game>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", requester.key().as_ref()],
+        bump
+    )]
+    pub requester_profile: Account<'info, UserProfile>,
+
+    /// Player who requested the rematch; signs to put up their stake
+    #[account(mut)]
+    pub requester: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct OpenSideBetPool<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + 32 + 32 + 2 + 8 + 8 + 4 + 2 + 32 + 33 + 8, // discriminator + game + creator + fee + side totals + open bets + outcome + players + created_at
+        seeds = [b"side_bet_pool", game.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, SideBetPool>,
+
+    #[account(mut, seeds = [b"game", game_id.as_bytes()], bump)]
+    pub game: Account<'info, Game>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct PlaceSideBet<'info> {
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + 32 + 32 + 1 + 8, // discriminator + pool + bettor + side + amount
+        seeds = [b"side_bet", pool.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, SideBet>,
+
+    #[account(mut, seeds = [b"side_bet_pool", game.key().as_ref()], bump)]
+    pub pool: Account<'info, SideBetPool>,
+
+    #[account(seeds = [b"game", game_id.as_bytes()], bump)]
+    pub game: Account<'info, Game>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct SettleSideBets<'info> {
+    #[account(mut, seeds = [b"side_bet_pool", game.key().as_ref()], bump)]
+    pub pool: Account<'info, SideBetPool>,
+
+    /// CHECK: Game PDA; may already be closed if the game was cancelled
+    #[account(mut, seeds = [b"game", game_id.as_bytes()], bump)]
+    pub game: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Checked against the config's platform wallet
+    #[account(mut, address = config.platform_wallet)]
+    pub platform_wallet: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct ClaimSideBet<'info> {
+    #[account(
+        mut,
+        close = bettor,
+        has_one = pool,
+        has_one = bettor,
+        seeds = [b"side_bet", pool.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
+    pub bet: Account<'info, SideBet>,
+
+    #[account(mut, seeds = [b"side_bet_pool", game.key().as_ref()], bump)]
+    pub pool: Account<'info, SideBetPool>,
+
+    /// CHECK: Only used to derive the pool address
+    #[account(seeds = [b"game", game_id.as_bytes()], bump)]
+    pub game: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CloseSideBetPool<'info> {
+    #[account(
+        mut,
+        close = creator,
+        has_one = creator,
+        seeds = [b"side_bet_pool", game.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, SideBetPool>,
+
+    /// CHECK: Only used to derive the pool address
+    #[account(seeds = [b"game", game_id.as_bytes()], bump)]
+    pub game: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Checked against the config's platform wallet
+    #[account(mut, address = config.platform_wallet)]
+    pub platform_wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Receives the pool's rent; checked against the pool creator
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(asset: Pubkey)]
+pub struct RegisterPriceFeed<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 32 + 8 + 2, // discriminator + asset + feed_id + max_staleness + max_confidence_bps
+        seeds = [b"price_feed", asset.as_ref()],
+        bump
+    )]
+    pub price_feed: Account<'info, PriceFeed>,
 
-#[derive(Accounts)]
-#[instruction(game_id: String)]
-pub struct SetWinner<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePriceFeed<'info> {
+    #[account(mut, seeds = [b"price_feed", price_feed.asset.as_ref()], bump)]
+    pub price_feed: Account<'info, PriceFeed>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRatingConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 4 * 6, // discriminator + rating params
+        seeds = [b"rating_config"],
+        bump
+    )]
+    pub rating_config: Account<'info, RatingConfig>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRatingConfig<'info> {
+    #[account(mut, seeds = [b"rating_config"], bump)]
+    pub rating_config: Account<'info, RatingConfig>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct OfferDouble<'info> {
     #[account(
         mut,
         seeds = [b"game", game_id.as_bytes()],
         bump
     )]
     pub game: Account<'info, Game>,
-    
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
     pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 #[instruction(game_id: String)]
-pub struct FinalizeGameWithReferral<'info> {
+pub struct DeclineDouble<'info> {
     #[account(
         mut,
         seeds = [b"game", game_id.as_bytes()],
         bump
     )]
     pub game: Account<'info, Game>,
-    
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", offerer.key().as_ref()],
+        bump
+    )]
+    pub offerer_profile: Account<'info, UserProfile>,
+
+    /// CHECK: Checked in the handler against the pending doubling offer
+    #[account(mut)]
+    pub offerer: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeJackpot<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 2 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 33 + 8 + 8, // discriminator + params + lifetime stats + last payout
+        seeds = [b"jackpot"],
+        bump
+    )]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct DrawJackpot<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(mut, seeds = [b"jackpot"], bump)]
+    pub jackpot: Account<'info, Jackpot>,
+
+    /// CHECK: This account is checked against the game's winner field
+    #[account(mut, address = game.winner.unwrap())]
+    pub winner: UncheckedAccount<'info>,
+
+    /// CHECK: SlotHashes sysvar, parsed by hand
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureJackpot<'info> {
+    #[account(mut, seeds = [b"jackpot"], bump)]
+    pub jackpot: Account<'info, Jackpot>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 32 + 2 + 2 + 1, // discriminator + admin + platform_wallet + draw_fee_bps + side_bet_fee_bps + max_stake_multiplier
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Only the program's upgrade authority may create the config
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = anchor_lang::solana_program::bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ GameError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeUserProfile<'info> {
+    /// CHECK: Owner and discriminator are checked before the account is resized
+    #[account(
+        mut,
+        seeds = [b"user_profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: UncheckedAccount<'info>,
+
+    /// CHECK: Only used to derive the profile address
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct UpgradeGame<'info> {
+    /// CHECK: Owner and discriminator are checked before the account is resized
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct OfferDraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct AcceptDraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
     #[account(
         mut,
         seeds = [b"user_profile", game.player1.as_ref()],
         bump
     )]
     pub player1_profile: Account<'info, UserProfile>,
-    
+
     #[account(
         mut,
         seeds = [b"user_profile", game.player2.unwrap().as_ref()],
         bump
     )]
     pub player2_profile: Account<'info, UserProfile>,
-    
+
     /// CHECK: This account is checked against the game's player1 field
     #[account(mut, address = game.player1)]
     pub player1: UncheckedAccount<'info>,
-    
+
     /// CHECK: This account is checked against the game's player2 field
     #[account(mut, address = game.player2.unwrap())]
     pub player2: UncheckedAccount<'info>,
-    
-    /// CHECK: Platform wallet for collecting fees
-    #[account(mut)]
+
+    /// CHECK: Checked against the platform wallet stored in config
+    #[account(mut, address = config.platform_wallet)]
     pub platform_wallet: UncheckedAccount<'info>,
-    
-    /// CHECK: Referrer account for commission payouts (can be same as platform_wallet if no referrer)
+
+    // The accounts below are only needed for SPL token and tokenized points games.
+    // The mint is writable so withheld Token-2022 transfer fees can be harvested to it.
     #[account(mut)]
-    pub referrer: UncheckedAccount<'info>,
-    
-    /// Referrer profile for tracking earnings (can be any profile if no referrer)
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"escrow", game.key().as_ref()], bump)]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
     #[account(mut)]
-    pub referrer_profile: Account<'info, UserProfile>,
-    
-    pub system_program: Program<'info, System>,
+    pub player1_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub player2_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub platform_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub user: Signer<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(game_id: String)]
-pub struct CancelGame<'info> {
+pub struct ProposeCancel<'info> {
     #[account(
         mut,
-        close = user, // Close account and return rent to creator (who signs the transaction)
         seeds = [b"game", game_id.as_bytes()],
         bump
     )]
     pub game: Account<'info, Game>,
-    
-    #[account(mut, signer)]
-    pub user: SystemAccount<'info>,
-    
-    pub system_program: Program<'info, System>,
+
+    pub user: Signer<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(game_id: String)]
-pub struct CloseGame<'info> {
+pub struct AcceptCancel<'info> {
     #[account(
         mut,
-        close = player1, // Close account and return rent to player1 (creator)
         seeds = [b"game", game_id.as_bytes()],
         bump
     )]
     pub game: Account<'info, Game>,
-    
-    /// CHECK: Rent is returned to player1 (creator), not to signer
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", game.player1.as_ref()],
+        bump
+    )]
+    pub player1_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", game.player2.unwrap().as_ref()],
+        bump
+    )]
+    pub player2_profile: Account<'info, UserProfile>,
+
+    /// CHECK: This account is checked against the game's player1 field
     #[account(mut, address = game.player1)]
     pub player1: UncheckedAccount<'info>,
-    
-    /// Service wallet or creator can sign this transaction
+
+    /// CHECK: This account is checked against the game's player2 field
+    #[account(mut, address = game.player2.unwrap())]
+    pub player2: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the platform wallet stored in config
+    #[account(mut, address = config.platform_wallet)]
+    pub platform_wallet: UncheckedAccount<'info>,
+
+    // The accounts below are only needed for SPL token and tokenized points games.
+    // The mint is writable so withheld Token-2022 transfer fees can be harvested to it.
+    #[account(mut)]
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"escrow", game.key().as_ref()], bump)]
+    pub escrow: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub player1_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub player2_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub platform_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
     pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
 }
 
 // Game state and user profile structures
@@ -795,6 +3323,83 @@ pub struct Game {
     pub player2_move_commitment: Option<[u8; 32]>,
     pub winner: Option<Pubkey>,
     pub created_at: i64,
+    pub draw_offered_by: Option<Pubkey>, // Player with a pending draw offer
+    pub cancel_proposed_by: Option<Pubkey>, // Player with a pending cancel proposal
+    pub rematch_requested_by: Option<Pubkey>, // Player with a pending rematch request
+    pub previous_game: Option<Pubkey>, // Game this one is a rematch of
+    pub next_game: Option<Pubkey>, // Rematch spawned from this game
+    pub parent: Option<Pubkey>, // Series or tournament holding the stake for this game
+    pub side_bets_open: bool, // A side bet pool must settle before the game can be closed
+    pub stake_multiplier: u8, // Doubling cube value; `stake_amount` already includes it
+    pub double_offered_by: Option<Pubkey>, // Player whose doubling top-up is escrowed here
+    pub cube_owner: Option<Pubkey>, // Only the owner may redouble; anyone while unset
+    pub mint: Option<Pubkey>, // Stake mint for SPL token games
+    pub usd_stake: Option<u64>, // USD value (6 decimals) the stake was converted from
+    pub ranked: bool, // Result moves the players' ratings
+    pub min_opponent_rating: Option<u32>, // Joiners must be rated at least this
+    pub max_opponent_rating: Option<u32>, // Joiners must be rated at most this
+    pub lobby: Option<Pubkey>, // Lobby page listing this game while it waits for a player
+    pub jackpot_draw_slot: Option<u64>, // Slot whose hash decides this game's pending jackpot draw
+    pub rent_payer: Option<Pubkey>, // Account refunded the rent on close when it wasn't player1
+}
+
+// discriminator + game data + draw/cancel offers + rematch request + previous/next game links + parent + side bet flag + doubling cube + mint + usd stake + ranked + rating band + lobby + jackpot draw slot + rent payer
+pub const GAME_SPACE: usize = 8 + (4 + 32) + 32 + 33 + 1 + 8 + 1 + 1 + 1 + 1 + 1 + 33 + 33 + 33 + 8 + 33 + 33 + 33 + 33 + 33 + 33 + 1 + 1 + 33 + 33 + 33 + 9 + 1 + 5 + 5 + 33 + 9 + 33;
+
+/// Oracle used to convert USD stakes into an asset's base units
+#[account]
+pub struct PriceFeed {
+    pub asset: Pubkey, // Mint priced by this feed, or the default key for SOL
+    pub feed_id: [u8; 32], // Pyth price feed id, matched against the posted price update
+    pub max_staleness: i64, // Seconds a price may age before it is refused
+    pub max_confidence_bps: u16, // Widest confidence interval accepted, relative to the price
+}
+
+/// Pari-mutuel spectator bets on a single game
+#[account]
+pub struct SideBetPool {
+    pub game: Pubkey,
+    pub creator: Pubkey, // Paid the pool's rent
+    pub fee_bps: u16, // Platform cut, fixed when the pool opens
+    pub total_on_player1: u64,
+    pub total_on_player2: u64,
+    pub open_bets: u32, // Bets not yet claimed
+    pub outcome: Option<SideBetOutcome>,
+    pub player1: Pubkey,
+    pub player2: Option<Pubkey>, // Recorded at settlement, as player2 may join after bets are placed
+    pub created_at: i64,
+}
+
+/// Seconds after which an unsettled pool can be refunded
+pub const SIDE_BET_EXPIRY: i64 = 7 * 24 * 60 * 60;
+
+#[account]
+pub struct SideBet {
+    pub pool: Pubkey,
+    pub bettor: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+}
+
+/// Progressive jackpot funded by a slice of SOL game fees
+#[account]
+pub struct Jackpot {
+    pub params: JackpotParams,
+    pub total_contributed: u64,
+    pub total_paid: u64,
+    pub contributions: u64,
+    pub payouts: u64,
+    pub last_winner: Option<Pubkey>,
+    pub last_payout: u64,
+    pub last_paid_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct JackpotParams {
+    pub fee_share_bps: u16, // Share of the platform fee routed into the jackpot
+    pub min_rounds_to_win: u8, // Shortest game whose flawless win enters the draw
+    pub min_stake: u64, // Smallest stake whose flawless win enters the draw
+    pub odds: u64, // A flawless win takes the jackpot with probability 1 / odds
 }
 
 #[account]
@@ -809,21 +3414,113 @@ pub struct UserProfile {
     pub referral_count: u32, // Number of users this user has referred
     pub referral_earnings: u64, // Total SOL earned from referrals in lamports
     pub created_at: i64,
+    pub draws: u32, // Games settled by mutual draw
+    pub last_claim_at: i64, // Last daily points claim, 0 if never claimed
+    pub claim_streak: u32, // Consecutive daily claims
+    pub points_migrated: bool, // Points are held as tokens of the points mint instead of on this ledger
+    pub active_points_games: u32, // Points games holding this player's stake
+    pub transfer_window_start: i64, // Start of the current daily transfer window
+    pub transferred_in_window: u64, // Points sent during the current window
+    pub net_sol_won: i64, // Lamports won minus lamports lost in finalized SOL games
+    pub rating: u32, // Skill rating, 0 until the first ranked game (the configured default applies)
+    pub rating_deviation: u32, // Rating uncertainty, shrinks with every ranked game; 0 until rated
+    pub ranked_wins: u32, // Wins in ranked games, for the leaderboards
+    pub ranked_net_sol_won: i64, // `net_sol_won` counting ranked games only
+}
+
+// discriminator + points_balance + wins + losses + total_games + total_points_earned + referral_code + referred_by + referral_count + referral_earnings + created_at + draws + last_claim_at + claim_streak + points_migrated + active_points_games + transfer_window_start + transferred_in_window + net_sol_won + rating + rating_deviation + ranked_wins + ranked_net_sol_won
+pub const USER_PROFILE_SPACE: usize = 8 + 8 + 4 + 4 + 4 + 8 + 8 + 33 + 4 + 8 + 8 + 4 + 8 + 4 + 1 + 4 + 8 + 8 + 8 + 4 + 4 + 4 + 8;
+
+/// Matchmaking rules chosen when a game is created
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct MatchRules {
+    pub ranked: bool, // Ranked games move ratings and count toward seasons and leaderboards
+    pub min_opponent_rating: Option<u32>,
+    pub max_opponent_rating: Option<u32>,
+}
+
+/// Skill rating settings
+#[account]
+pub struct RatingConfig {
+    pub params: RatingParams,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct RatingParams {
+    pub default_rating: u32, // Starting rating of every player
+    pub default_deviation: u32, // Starting rating deviation
+    pub min_deviation: u32, // Deviation floor reached after many ranked games
+    pub k_factor: u32, // Largest rating change per game at the default deviation
+    pub min_rating: u32,
+    pub max_rating: u32,
+}
+
+/// Expected score (in basis points) of the higher rated player, for rating
+/// gaps of 0, 50, 100, ... 800 points: 1 / (1 + 10^(-gap / 400))
+pub const EXPECTED_SCORE_BPS: [u64; 17] = [
+    5000, 5715, 6401, 7034, 7597, 8083, 8490, 8823, 9091, 9302, 9468, 9595, 9693, 9768, 9825, 9868, 9901,
+];
+pub const EXPECTED_SCORE_STEP: u64 = 50;
+
+/// Share of the deviation above the floor that each ranked game removes
+pub const DEVIATION_DECAY_DIVISOR: u32 = 10;
+
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub platform_wallet: Pubkey,
+    pub draw_fee_bps: u16, // Fee taken from each stake on draws and mutual cancels
+    pub side_bet_fee_bps: u16, // Platform cut of a side bet pool that pays out
+    pub max_stake_multiplier: u8, // Highest stake multiple the doubling cube can reach
+}
+
+// Events
+#[event]
+pub struct JackpotContributed {
+    pub game: Pubkey,
+    pub amount: u64,
+    pub jackpot_balance: u64,
+}
+
+#[event]
+pub struct JackpotWon {
+    pub game: Pubkey,
+    pub winner: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
 // Enums
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BetSide {
+    Player1,
+    Player2,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SideBetOutcome {
+    Player1,
+    Player2,
+    Refund,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
 pub enum GameStatus {
     WaitingForPlayer,
     InProgress,
     Finished,
     Abandoned,
+    Drawn,
+    Cancelled,
+    /// Finished and paid out by `finalize_game`
+    Settled,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum CurrencyType {
     Points,
     Sol,
+    SplToken,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -833,7 +3530,7 @@ pub enum Move {
     Scissors,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
 pub enum RoundResult {
     Player1Win,
     Player2Win,
@@ -883,8 +3580,251 @@ pub enum GameError {
     CannotReferYourself,
     #[msg("Referrer already set")]
     ReferrerAlreadySet,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid fee (max 10000 basis points)")]
+    InvalidFeeBps,
+    #[msg("Draw already offered")]
+    DrawAlreadyOffered,
+    #[msg("No draw offer")]
+    NoDrawOffer,
+    #[msg("Cannot accept own offer")]
+    CannotAcceptOwnOffer,
+    #[msg("Cancel already proposed")]
+    CancelAlreadyProposed,
+    #[msg("No cancel proposal")]
+    NoCancelProposal,
+    #[msg("Rematch request pending")]
+    RematchPending,
+    #[msg("No rematch request")]
+    NoRematchRequest,
+    #[msg("Rematch already played")]
+    RematchAlreadyPlayed,
+    #[msg("Invalid games to win")]
+    InvalidGamesToWin,
+    #[msg("Game stake is held by a series or tournament")]
+    ChildGame,
+    #[msg("A series game is already in progress")]
+    SeriesGameInProgress,
+    #[msg("Not the current series or tournament game")]
+    NotCurrentGame,
+    #[msg("Invalid max players")]
+    InvalidMaxPlayers,
+    #[msg("Invalid timestamp")]
+    InvalidTimestamp,
+    #[msg("Invalid timeout")]
+    InvalidTimeout,
+    #[msg("Prize split must add up to 10000 basis points")]
+    InvalidPrizeSplit,
+    #[msg("Registration closed")]
+    RegistrationClosed,
+    #[msg("Registration still open")]
+    RegistrationOpen,
+    #[msg("Tournament full")]
+    TournamentFull,
+    #[msg("Already registered")]
+    AlreadyRegistered,
+    #[msg("Invalid bracket node")]
+    InvalidBracketNode,
+    #[msg("Match already decided")]
+    MatchAlreadyDecided,
+    #[msg("Match already started")]
+    MatchAlreadyStarted,
+    #[msg("Match not ready")]
+    MatchNotReady,
+    #[msg("Already claimed")]
+    AlreadyClaimed,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Payouts still pending")]
+    PayoutsPending,
+    #[msg("Creator cannot leave")]
+    CreatorCannotLeave,
+    #[msg("Other players still joined")]
+    PlayersStillJoined,
+    #[msg("Not enough players")]
+    NotEnoughPlayers,
+    #[msg("Wrong phase")]
+    WrongPhase,
+    #[msg("Move already revealed")]
+    MoveAlreadyRevealed,
+    #[msg("Timeout not reached")]
+    TimeoutNotReached,
+    #[msg("Invalid team")]
+    InvalidTeam,
+    #[msg("Team full")]
+    TeamFull,
+    #[msg("Not available in this decision mode")]
+    WrongDecisionMode,
+    #[msg("Captain already elected")]
+    CaptainAlreadyElected,
+    #[msg("Captain not elected")]
+    CaptainNotElected,
+    #[msg("Not a teammate")]
+    NotATeammate,
+    #[msg("Only the team captain can vote")]
+    NotTeamCaptain,
+    #[msg("House exposure limit exceeded")]
+    HouseExposureExceeded,
+    #[msg("Insufficient house balance")]
+    InsufficientHouseBalance,
+    #[msg("Game already settled")]
+    GameAlreadySettled,
+    #[msg("Insufficient shares")]
+    InsufficientShares,
+    #[msg("No pending withdrawal")]
+    NoPendingWithdrawal,
+    #[msg("Withdrawal cooldown has not passed")]
+    WithdrawalCooldown,
+    #[msg("SlotHashes sysvar is required for the jackpot draw")]
+    MissingSlotHashes,
+    #[msg("Jackpot odds must be at least 1")]
+    InvalidJackpotOdds,
+    #[msg("Side bets must be settled before the game is closed")]
+    SideBetsUnsettled,
+    #[msg("Betting is closed for this game")]
+    BettingClosed,
+    #[msg("Players cannot bet on their own game")]
+    PlayerCannotBet,
+    #[msg("Stake multiplier cap must be at least 1")]
+    InvalidStakeMultiplier,
+    #[msg("A doubling offer is waiting for an answer")]
+    DoublePending,
+    #[msg("No doubling offer to answer")]
+    NoDoubleOffer,
+    #[msg("The stake can only be doubled between rounds")]
+    RoundInProgress,
+    #[msg("Only the cube owner can redouble")]
+    NotCubeOwner,
+    #[msg("Doubling would exceed the stake multiplier cap")]
+    StakeMultiplierCap,
+    #[msg("This action is not supported for SPL token games")]
+    UnsupportedCurrency,
+    #[msg("Token accounts are required for SPL token games")]
+    MissingTokenAccounts,
+    #[msg("Mint is not allowed for token games")]
+    MintNotAllowed,
+    #[msg("Mint does not match the game's mint")]
+    MintMismatch,
+    #[msg("Token account has the wrong owner")]
+    InvalidTokenAccount,
+    #[msg("Fee tiers must be ascending and within 10000 bps")]
+    InvalidFeeTiers,
+    #[msg("Mint has an extension or authority that could move, freeze or trap escrowed tokens")]
+    HostileMint,
+    #[msg("Mint's transfer hook program is not approved")]
+    TransferHookNotAllowed,
+    #[msg("Price feed does not match the stake asset")]
+    PriceFeedMismatch,
+    #[msg("Price account is not a valid, trading price")]
+    InvalidPriceAccount,
+    #[msg("Oracle price is too old")]
+    StalePrice,
+    #[msg("Oracle price confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("Stake conversion overflowed")]
+    ConversionOverflow,
+    #[msg("Daily points were already claimed; wait for the cooldown")]
+    ClaimCooldown,
+    #[msg("Points were already migrated to tokens")]
+    PointsAlreadyMigrated,
+    #[msg("User profile is required for points games")]
+    MissingUserProfile,
+    #[msg("Cannot transfer points to yourself")]
+    CannotTransferToSelf,
+    #[msg("Profile holds points as tokens; transfer the tokens instead")]
+    PointsMigrated,
+    #[msg("Points are locked in an active game")]
+    PointsLocked,
+    #[msg("Daily points transfer limit exceeded")]
+    TransferLimitExceeded,
+    #[msg("Max redemptions must be at least 1")]
+    InvalidMaxRedemptions,
+    #[msg("Promo code has expired")]
+    PromoExpired,
+    #[msg("Promo code has no redemptions left")]
+    PromoExhausted,
+    #[msg("Recipient count must be between 1 and the bitmap capacity")]
+    InvalidRecipientCount,
+    #[msg("Invalid merkle proof")]
+    InvalidProof,
+    #[msg("Reward already claimed")]
+    RewardAlreadyClaimed,
+    #[msg("Claims would exceed the distribution total")]
+    DistributionExceeded,
+    #[msg("Distributor has expired")]
+    DistributorExpired,
+    #[msg("Distributor has not expired yet")]
+    DistributorNotExpired,
+    #[msg("Rating params must satisfy 1 <= min <= default <= max, 1 <= min deviation <= default deviation and k-factor >= 1")]
+    InvalidRatingParams,
+    #[msg("Minimum opponent rating is above the maximum")]
+    InvalidRatingBand,
+    #[msg("Rating is outside the game's allowed band")]
+    RatingOutOfBand,
+    #[msg("Rating config is required for ranked and rating-gated games")]
+    MissingRatingConfig,
+    #[msg("Match queue is full")]
+    QueueFull,
+    #[msg("Already in this queue")]
+    AlreadyQueued,
+    #[msg("Not in this queue")]
+    NotQueued,
+    #[msg("Not enough players queued to make a match")]
+    NotEnoughQueued,
+    #[msg("Lobby page is full")]
+    LobbyFull,
+    #[msg("Lobby page does not match this game")]
+    LobbyMismatch,
+    #[msg("The lobby page listing this game is required")]
+    MissingLobby,
+    #[msg("Account is not an upgradable account of this program")]
+    InvalidLegacyAccount,
+    #[msg("Withdrawal request lapsed; request it again")]
+    WithdrawalExpired,
+    #[msg("Game has no pending jackpot draw")]
+    NoJackpotEntry,
+    #[msg("Jackpot draw slot has not been produced yet")]
+    JackpotDrawNotReady,
+    #[msg("Jackpot draw is still pending")]
+    JackpotDrawPending,
+    #[msg("This mode stakes ledger points; profiles migrated to points tokens can't join it")]
+    LedgerPointsOnly,
+    #[msg("Released more points stakes than the profile has locked")]
+    PointsLockUnderflow,
+    #[msg("Both players' stats accounts for the active season are required")]
+    MissingSeasonStats,
+    #[msg("Leaderboard does not belong to the active season")]
+    LeaderboardMismatch,
+    #[msg("No two queued players are within the queue's rating gap")]
+    NoCompatibleOpponents,
 }
 
+/// Upper bound for any fee expressed in basis points
+pub const MAX_FEE_BPS: u16 = 10000;
+
+/// Decimals of native SOL (lamports per SOL)
+pub const SOL_DECIMALS: u8 = 9;
+
+/// USD stakes are given with 6 decimals (micro-dollars)
+pub const USD_DECIMALS: u32 = 6;
+
+// Pyth pull oracle PriceUpdateV2 account layout
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+pub const PYTH_VERIFICATION_FULL: u8 = 1;
+pub const PRICE_UPDATE_V2_MIN_LEN: usize = 133;
+
+/// Upper bound for the jackpot's share of the platform fee, so the referral
+/// commission and the jackpot slice never exceed the fee itself
+pub const MAX_JACKPOT_SHARE_BPS: u16 = 5000;
+
+/// Slots between finalizing a flawless win and the slot whose hash draws its jackpot entry
+pub const JACKPOT_DRAW_DELAY: u64 = 2;
+
+/// Slots kept in the SlotHashes sysvar
+pub const SLOT_HASHES_MAX_ENTRIES: u64 = 512;
+
 /// Calculate platform fee based on stake amount
 /// Returns fee percentage as basis points (e.g., 500 = 5%)
 pub fn get_platform_fee_rate(stake_amount: u64) -> u64 {
@@ -900,6 +3840,569 @@ pub fn get_platform_fee_rate(stake_amount: u64) -> u64 {
     }
 }
 
+/// Return both stakes from the game escrow, minus `fee_bps` of each stake.
+/// SOL fees go to the platform wallet; points fees are burned.
+/// Returns the fee taken from each player. Child games of a series or tournament
+/// hold no stake and leave the parent's points lock in place.
+pub fn refund_both_stakes<'info>(
+    game: &Account<'info, Game>,
+    player1: &AccountInfo<'info>,
+    player2: &AccountInfo<'info>,
+    platform_wallet: &AccountInfo<'info>,
+    player1_profile: &mut UserProfile,
+    player2_profile: &mut UserProfile,
+    fee_bps: u16,
+) -> Result<u64> {
+    if game.parent.is_some() {
+        return Ok(0);
+    }
+
+    let stake_amount = game.stake_amount;
+    let fee_per_player = stake_amount * fee_bps as u64 / 10000;
+    let refund_amount = stake_amount - fee_per_player;
+
+    if game.currency_type == CurrencyType::Points {
+        player1_profile.points_balance += refund_amount;
+        player2_profile.points_balance += refund_amount;
+        unlock_points(player1_profile)?;
+        unlock_points(player2_profile)?;
+    } else if game.currency_type == CurrencyType::Sol {
+        let game_info = game.to_account_info();
+        move_lamports(&game_info, player1, refund_amount)?;
+        move_lamports(&game_info, player2, refund_amount)?;
+        move_lamports(&game_info, platform_wallet, fee_per_player * 2)?;
+    }
+
+    Ok(fee_per_player)
+}
+
+/// Price read from a Pyth price update
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+/// Grow a program account created under an older, shorter layout to `new_len`,
+/// topping up its rent from `payer`. New fields are appended at the end, so the
+/// zeroed tail reads as their empty values.
+pub fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    discriminator: &[u8],
+    new_len: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    require!(*account.owner == crate::ID, GameError::InvalidLegacyAccount);
+    require!(
+        account.data_len() >= 8 && account.try_borrow_data()?[..8] == *discriminator,
+        GameError::InvalidLegacyAccount
+    );
+    if account.data_len() >= new_len {
+        return Ok(());
+    }
+
+    let shortfall = Rent::get()?.minimum_balance(new_len).saturating_sub(account.lamports());
+    if shortfall > 0 {
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(&payer.key(), account.key, shortfall),
+            &[
+                payer.to_account_info(),
+                account.clone(),
+                system_program.to_account_info(),
+            ],
+        )?;
+    }
+    account.resize(new_len)?;
+    Ok(())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Parse a fully verified Pyth PriceUpdateV2 account posted by the receiver program
+/// for `feed_id`. Staleness is checked by the caller, as in `get_price_no_older_than`.
+pub fn read_pyth_price(info: &AccountInfo, feed_id: &[u8; 32]) -> Result<OraclePrice> {
+    require!(*info.owner == PYTH_RECEIVER_PROGRAM_ID, GameError::InvalidPriceAccount);
+    let data = info.try_borrow_data()?;
+    require!(data.len() >= PRICE_UPDATE_V2_MIN_LEN, GameError::InvalidPriceAccount);
+    require!(data[..8] == PRICE_UPDATE_V2_DISCRIMINATOR, GameError::InvalidPriceAccount);
+    // discriminator + write_authority, then the verification level; partially
+    // verified updates carry an extra signature count byte and are refused
+    require!(data[40] == PYTH_VERIFICATION_FULL, GameError::InvalidPriceAccount);
+    require!(data[41..73] == feed_id[..], GameError::PriceFeedMismatch);
+
+    Ok(OraclePrice {
+        price: read_u64(&data, 73) as i64,
+        conf: read_u64(&data, 81),
+        expo: read_u32(&data, 89) as i32,
+        publish_time: read_u64(&data, 93) as i64,
+    })
+}
+
+/// Convert `usd_stake` (6 decimals) into base units of an asset with `decimals`,
+/// refusing stale prices and prices with too wide a confidence interval
+pub fn usd_to_amount(
+    price_account: &AccountInfo,
+    price_feed: &PriceFeed,
+    usd_stake: u64,
+    decimals: u8,
+) -> Result<u64> {
+    let oracle = read_pyth_price(price_account, &price_feed.feed_id)?;
+    require!(oracle.price > 0, GameError::InvalidPriceAccount);
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(now - oracle.publish_time <= price_feed.max_staleness, GameError::StalePrice);
+    require!(
+        oracle.conf as u128 * 10000 <= oracle.price as u128 * price_feed.max_confidence_bps as u128,
+        GameError::PriceConfidenceTooWide
+    );
+
+    // amount = usd / 10^6 / (price * 10^expo) * 10^decimals
+    let pow = |exp: u32| 10u128.checked_pow(exp).ok_or(GameError::ConversionOverflow);
+    let mut numerator = (usd_stake as u128)
+        .checked_mul(pow(decimals as u32)?)
+        .ok_or(GameError::ConversionOverflow)?;
+    let mut denominator = (oracle.price as u128)
+        .checked_mul(pow(USD_DECIMALS)?)
+        .ok_or(GameError::ConversionOverflow)?;
+    if oracle.expo < 0 {
+        numerator = numerator
+            .checked_mul(pow(oracle.expo.unsigned_abs())?)
+            .ok_or(GameError::ConversionOverflow)?;
+    } else {
+        denominator = denominator
+            .checked_mul(pow(oracle.expo as u32)?)
+            .ok_or(GameError::ConversionOverflow)?;
+    }
+
+    let amount = u64::try_from(numerator / denominator).map_err(|_| GameError::ConversionOverflow)?;
+    require!(amount > 0, GameError::InvalidStakeAmount);
+    Ok(amount)
+}
+
+/// Shared body of `create_game` and `create_usd_game`
+pub fn open_game<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateGame<'info>>,
+    game_id: String,
+    stake_amount: u64,
+    currency_type: CurrencyType,
+    rounds_to_win: u8,
+    usd_stake: Option<u64>,
+    rules: MatchRules,
+) -> Result<()> {
+    // Validate inputs
+    require!(game_id.len() <= 32, GameError::GameIdTooLong);
+    require!(stake_amount > 0, GameError::InvalidStakeAmount);
+    require!(rounds_to_win > 0 && rounds_to_win <= 10, GameError::InvalidRoundsToWin);
+    if let (Some(min), Some(max)) = (rules.min_opponent_rating, rules.max_opponent_rating) {
+        require!(min <= max, GameError::InvalidRatingBand);
+    }
+    
+    // Migrated players stake points tokens, which are escrowed like any other token stake
+    let tokenized_points = currency_type == CurrencyType::Points && ctx.accounts.user_profile.points_migrated;
+    
+    // For points games, check if user has enough points
+    if tokenized_points {
+        let mint = ctx.accounts.mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        require!(mint.key() == points_mint_key(), GameError::MintMismatch);
+        
+        deposit_tokens(
+            ctx.accounts.user_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+            ctx.accounts.escrow.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+            mint,
+            &ctx.accounts.user,
+            ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+            ctx.remaining_accounts,
+            stake_amount,
+        )?;
+    } else if currency_type == CurrencyType::Points {
+        let user_profile = &ctx.accounts.user_profile;
+        require!(
+            user_profile.points_balance >= stake_amount,
+            GameError::InsufficientPoints
+        );
+    } else if currency_type == CurrencyType::Sol {
+        // For SOL games, transfer the stake amount to the game account
+        require!(
+            **ctx.accounts.user.lamports.borrow() >= stake_amount,
+            GameError::InsufficientSol
+        );
+        
+        // Transfer SOL from user to game account
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.user.key(),
+                &ctx.accounts.game.key(),
+                stake_amount,
+            ),
+            &[
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.game.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+    } else if currency_type == CurrencyType::SplToken {
+        let allowed_mint = ctx.accounts.allowed_mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let mint = ctx.accounts.mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        require!(allowed_mint.mint == mint.key() && allowed_mint.enabled, GameError::MintNotAllowed);
+        // A transfer hook program can be swapped after the mint was allowlisted
+        validate_mint_extensions(mint, allowed_mint.transfer_hook_program)?;
+        
+        // Move the stake into the game-owned escrow
+        let received = deposit_tokens(
+            ctx.accounts.user_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+            ctx.accounts.escrow.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+            mint,
+            &ctx.accounts.user,
+            ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?,
+            ctx.remaining_accounts,
+            stake_amount,
+        )?;
+        require!(received > 0, GameError::InvalidStakeAmount);
+        msg!("Escrowed {} tokens after transfer fees", received);
+    }
+    
+    let mint = if currency_type == CurrencyType::SplToken || tokenized_points {
+        ctx.accounts.mint.as_ref().map(|mint| mint.key())
+    } else {
+        None
+    };
+    let game = &mut ctx.accounts.game;
+    init_game(
+        game,
+        game_id,
+        ctx.accounts.user.key(),
+        None,
+        stake_amount,
+        currency_type,
+        rounds_to_win,
+    )?;
+    game.mint = mint;
+    game.usd_stake = usd_stake;
+    game.ranked = rules.ranked;
+    game.min_opponent_rating = rules.min_opponent_rating;
+    game.max_opponent_rating = rules.max_opponent_rating;
+    
+    // List the game so it can be found without scanning every game account
+    if let Some(lobby) = ctx.accounts.lobby.as_mut() {
+        require!(lobby.entries.len() < LOBBY_PAGE_SIZE, GameError::LobbyFull);
+        require!(
+            lobby.currency_type.is_none_or(|currency| currency == currency_type),
+            GameError::LobbyMismatch
+        );
+        lobby.entries.push(LobbyEntry {
+            game: game.key(),
+            currency_type,
+            stake_amount,
+            rounds_to_win,
+        });
+        game.lobby = Some(lobby.key());
+    }
+    
+    // Deduct points for points games
+    if currency_type == CurrencyType::Points && !tokenized_points {
+        let user_profile = &mut ctx.accounts.user_profile;
+        user_profile.points_balance -= stake_amount;
+        lock_points(user_profile);
+    }
+    
+    msg!("Game {} created by {}", game.game_id, ctx.accounts.user.key());
+    Ok(())
+}
+
+/// Reset a freshly created game account. The game starts in progress when
+/// both players are already known, otherwise it waits for a second player.
+pub fn init_game(
+    game: &mut Game,
+    game_id: String,
+    player1: Pubkey,
+    player2: Option<Pubkey>,
+    stake_amount: u64,
+    currency_type: CurrencyType,
+    rounds_to_win: u8,
+) -> Result<()> {
+    game.game_id = game_id;
+    game.player1 = player1;
+    game.player2 = player2;
+    game.game_status = if player2.is_some() {
+        GameStatus::InProgress
+    } else {
+        GameStatus::WaitingForPlayer
+    };
+    game.stake_amount = stake_amount;
+    game.currency_type = currency_type;
+    game.rounds_to_win = rounds_to_win;
+    game.current_round = 1;
+    game.player1_rounds_won = 0;
+    game.player2_rounds_won = 0;
+    game.player1_move_commitment = None;
+    game.player2_move_commitment = None;
+    game.winner = None;
+    game.created_at = Clock::get()?.unix_timestamp;
+    game.draw_offered_by = None;
+    game.cancel_proposed_by = None;
+    game.rematch_requested_by = None;
+    game.previous_game = None;
+    game.next_game = None;
+    game.parent = None;
+    game.side_bets_open = false;
+    game.stake_multiplier = 1;
+    game.double_offered_by = None;
+    game.cube_owner = None;
+    game.mint = None;
+    game.usd_stake = None;
+    game.ranked = false;
+    game.min_opponent_rating = None;
+    game.max_opponent_rating = None;
+    game.jackpot_draw_slot = None;
+    game.lobby = None;
+    game.rent_payer = None;
+    Ok(())
+}
+
+/// Stage of a bracket node counted from the final (0 = final, 1 = semifinals, ...)
+/// Winner of a two-player game abandoned mid-play: a player who committed in the
+/// current round beats one who did not, then rounds won decide, then player1.
+pub fn forfeit_winner(game: &Game) -> Pubkey {
+    match (
+        game.player1_move_commitment.is_some(),
+        game.player2_move_commitment.is_some(),
+    ) {
+        (true, false) => game.player1,
+        (false, true) => game.player2.unwrap(),
+        _ if game.player2_rounds_won > game.player1_rounds_won => game.player2.unwrap(),
+        _ => game.player1,
+    }
+}
+
+pub fn validate_jackpot_params(params: &JackpotParams) -> Result<()> {
+    require!(params.fee_share_bps <= MAX_JACKPOT_SHARE_BPS, GameError::InvalidFeeBps);
+    require!(params.min_rounds_to_win > 0, GameError::InvalidRoundsToWin);
+    require!(params.odds > 0, GameError::InvalidJackpotOdds);
+    Ok(())
+}
+
+/// Move a player's stake into `escrow`: ledger points are debited from the
+/// profile and counted as locked, and SOL is transferred from the player's wallet
+pub fn collect_stake<'info>(
+    escrow: &AccountInfo<'info>,
+    user: &Signer<'info>,
+    user_profile: &mut UserProfile,
+    system_program: &Program<'info, System>,
+    currency_type: CurrencyType,
+    amount: u64,
+) -> Result<()> {
+    if currency_type == CurrencyType::Points {
+        require!(!user_profile.points_migrated, GameError::LedgerPointsOnly);
+        require!(user_profile.points_balance >= amount, GameError::InsufficientPoints);
+        user_profile.points_balance -= amount;
+        lock_points(user_profile);
+    } else if currency_type == CurrencyType::Sol {
+        require!(user.lamports() >= amount, GameError::InsufficientSol);
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(&user.key(), escrow.key, amount),
+            &[
+                user.to_account_info(),
+                escrow.clone(),
+                system_program.to_account_info(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Return `amount` held in `escrow` to a player, as ledger points (releasing
+/// the lock taken by `collect_stake`) or lamports
+pub fn return_stake(
+    escrow: &AccountInfo,
+    user: &AccountInfo,
+    user_profile: &mut UserProfile,
+    currency_type: CurrencyType,
+    amount: u64,
+) -> Result<()> {
+    if currency_type == CurrencyType::Points {
+        user_profile.points_balance += amount;
+        unlock_points(user_profile)?;
+    } else if currency_type == CurrencyType::Sol {
+        move_lamports(escrow, user, amount)?;
+    }
+    Ok(())
+}
+
+/// Count a ledger points stake now held by a game or escrow
+pub fn lock_points(user_profile: &mut UserProfile) {
+    user_profile.active_points_games += 1;
+}
+
+/// Release a ledger points stake once it has been paid out or refunded. Releasing
+/// more stakes than were locked means one was released twice, so it fails.
+pub fn unlock_points(user_profile: &mut UserProfile) -> Result<()> {
+    user_profile.active_points_games = user_profile
+        .active_points_games
+        .checked_sub(1)
+        .ok_or(GameError::PointsLockUnderflow)?;
+    Ok(())
+}
+
+/// Move lamports out of a program-owned escrow account
+pub fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    **from.try_borrow_mut_lamports()? -= amount;
+    **to.try_borrow_mut_lamports()? += amount;
+    Ok(())
+}
+
+/// Escrow a doubling top-up (equal to the current stake) from `user` into the game
+pub fn escrow_double_top_up<'info>(
+    game: &Account<'info, Game>,
+    user: &Signer<'info>,
+    user_profile: &mut UserProfile,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    collect_stake(
+        &game.to_account_info(),
+        user,
+        user_profile,
+        system_program,
+        game.currency_type,
+        game.stake_amount,
+    )?;
+    // The top-up joins a stake that is already counted
+    if game.currency_type == CurrencyType::Points {
+        unlock_points(user_profile)?;
+    }
+    Ok(())
+}
+
+/// Bets are taken until the first move of round 1 is committed
+pub fn side_bets_accepted(game: &Game) -> bool {
+    match game.game_status {
+        GameStatus::WaitingForPlayer => true,
+        GameStatus::InProgress => {
+            game.current_round == 1
+                && game.player1_rounds_won == 0
+                && game.player2_rounds_won == 0
+                && game.player1_move_commitment.is_none()
+                && game.player2_move_commitment.is_none()
+        }
+        _ => false,
+    }
+}
+
+/// Jackpot lamports above rent
+pub fn jackpot_balance(info: &AccountInfo) -> Result<u64> {
+    let rent = Rent::get()?.minimum_balance(info.data_len());
+    Ok(info.lamports().saturating_sub(rent))
+}
+
+/// Draw for the jackpot from the hash of the first slot at or after `draw_slot`,
+/// mixed with the game address. `draw_slot` is fixed when the game is finalized,
+/// before its hash exists. Returns None once that slot has left SlotHashes.
+pub fn jackpot_draw(slot_hashes: &AccountInfo, draw_slot: u64, game: &Pubkey) -> Result<Option<u64>> {
+    let data = slot_hashes.try_borrow_data()?;
+    // Layout: u64 entry count, then (slot: u64, hash: [u8; 32]) entries, newest first
+    let count = if data.len() >= 8 { read_u64(&data, 0) as usize } else { 0 };
+    require!(count > 0 && data.len() >= 8 + count * 40, GameError::MissingSlotHashes);
+    require!(read_u64(&data, 8) >= draw_slot, GameError::JackpotDrawNotReady);
+    if read_u64(&data, 8 + (count - 1) * 40) > draw_slot {
+        return Ok(None);
+    }
+
+    let entry = (0..count)
+        .rev()
+        .map(|i| 8 + i * 40)
+        .find(|offset| read_u64(&data, *offset) >= draw_slot)
+        .unwrap();
+    let mixed = anchor_lang::solana_program::hash::hashv(&[&data[entry + 8..entry + 40], game.as_ref()]).to_bytes();
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&mixed[..8]);
+    Ok(Some(u64::from_le_bytes(value)))
+}
+
+/// Deserialize a program account if it has been initialized
+pub fn load_if_initialized<T: AccountDeserialize>(info: &AccountInfo) -> Result<Option<T>> {
+    if *info.owner != crate::ID || info.data_is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(T::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+pub fn validate_rating_params(params: &RatingParams) -> Result<()> {
+    require!(
+        params.min_rating >= 1
+            && params.min_rating <= params.default_rating
+            && params.default_rating <= params.max_rating
+            && params.min_deviation >= 1
+            && params.min_deviation <= params.default_deviation
+            && params.k_factor >= 1,
+        GameError::InvalidRatingParams
+    );
+    Ok(())
+}
+
+/// A profile's rating, or the default for players without a ranked game yet
+pub fn effective_rating(profile: &UserProfile, params: &RatingParams) -> u32 {
+    if profile.rating == 0 {
+        params.default_rating
+    } else {
+        profile.rating
+    }
+}
+
+/// Expected score of a player rated `gap` points above their opponent, in basis
+/// points. Interpolates `EXPECTED_SCORE_BPS`; gaps beyond 800 use the last entry.
+pub fn expected_score_bps(gap: i64) -> u64 {
+    let distance = gap.unsigned_abs().min(EXPECTED_SCORE_STEP * (EXPECTED_SCORE_BPS.len() as u64 - 1));
+    let index = (distance / EXPECTED_SCORE_STEP) as usize;
+    let remainder = distance % EXPECTED_SCORE_STEP;
+    let score = if remainder == 0 {
+        EXPECTED_SCORE_BPS[index]
+    } else {
+        let (low, high) = (EXPECTED_SCORE_BPS[index], EXPECTED_SCORE_BPS[index + 1]);
+        low + (high - low) * remainder / EXPECTED_SCORE_STEP
+    };
+    if gap >= 0 {
+        score
+    } else {
+        10000 - score
+    }
+}
+
+/// Apply a ranked result. The winner gains exactly what the loser gives up, so
+/// the sum of ratings is conserved; the change is capped so neither rating
+/// leaves [min_rating, max_rating]. Deviations shrink toward their floor, and
+/// the change scales with the players' average deviation. Returns the change.
+pub fn apply_rating_result(winner: &mut UserProfile, loser: &mut UserProfile, params: &RatingParams) -> u32 {
+    for profile in [&mut *winner, &mut *loser] {
+        if profile.rating == 0 {
+            profile.rating = params.default_rating;
+            profile.rating_deviation = params.default_deviation;
+        }
+    }
+
+    let gap = winner.rating as i64 - loser.rating as i64;
+    let k = params.k_factor as u64 * (winner.rating_deviation as u64 + loser.rating_deviation as u64)
+        / (2 * params.default_deviation as u64);
+    let change = (k * (10000 - expected_score_bps(gap)) + 5000) / 10000;
+    let change = (change as u32)
+        .min(params.max_rating.saturating_sub(winner.rating))
+        .min(loser.rating.saturating_sub(params.min_rating));
+
+    winner.rating += change;
+    loser.rating -= change;
+    for profile in [winner, loser] {
+        let excess = profile.rating_deviation.saturating_sub(params.min_deviation);
+        profile.rating_deviation -= excess.div_ceil(DEVIATION_DECAY_DIVISOR);
+    }
+    change
+}
+
 pub fn hash_move(player_move: Move, nonce: u64) -> [u8; 32] {
     let move_byte = match player_move {
         Move::Rock => 0u8,
@@ -938,3 +4441,161 @@ pub fn determine_winner(move1: Move, move2: Move) -> RoundResult {
         _ => RoundResult::Draw,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::hash::hashv;
+
+    fn parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        hashv(&[&[1u8], &left, &right]).to_bytes()
+    }
+
+    #[test]
+    fn distributor_leaf_binds_every_field() {
+        let player = Pubkey::new_unique();
+        let leaf = distributor_leaf(3, &player, 500);
+
+        let expected = hashv(&[&[0u8], &3u32.to_le_bytes(), player.as_ref(), &500u64.to_le_bytes()]).to_bytes();
+        assert_eq!(leaf, expected);
+        assert_ne!(leaf, distributor_leaf(4, &player, 500));
+        assert_ne!(leaf, distributor_leaf(3, &Pubkey::new_unique(), 500));
+        assert_ne!(leaf, distributor_leaf(3, &player, 501));
+    }
+
+    #[test]
+    fn merkle_proof_accepts_every_leaf_of_the_tree() {
+        let leaves: Vec<[u8; 32]> = (0..4).map(|i| distributor_leaf(i, &Pubkey::new_unique(), 100)).collect();
+        let left = parent(leaves[0], leaves[1]);
+        let right = parent(leaves[2], leaves[3]);
+        let root = parent(left, right);
+
+        assert!(verify_merkle_proof(&[leaves[1], right], root, leaves[0]));
+        assert!(verify_merkle_proof(&[leaves[0], right], root, leaves[1]));
+        assert!(verify_merkle_proof(&[leaves[3], left], root, leaves[2]));
+        assert!(verify_merkle_proof(&[leaves[2], left], root, leaves[3]));
+        // A single-leaf tree is its own root
+        assert!(verify_merkle_proof(&[], leaves[0], leaves[0]));
+    }
+
+    #[test]
+    fn merkle_proof_rejects_forged_claims() {
+        let leaves: Vec<[u8; 32]> = (0..4).map(|i| distributor_leaf(i, &Pubkey::new_unique(), 100)).collect();
+        let left = parent(leaves[0], leaves[1]);
+        let right = parent(leaves[2], leaves[3]);
+        let root = parent(left, right);
+
+        let forged = distributor_leaf(0, &Pubkey::new_unique(), 100);
+        assert!(!verify_merkle_proof(&[leaves[1], right], root, forged));
+        assert!(!verify_merkle_proof(&[leaves[2], right], root, leaves[0]));
+        assert!(!verify_merkle_proof(&[leaves[1]], root, leaves[0]));
+    }
+
+    #[test]
+    fn leaderboard_insert_keeps_scores_descending() {
+        let mut ranking = Vec::new();
+        let players: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        leaderboard_insert(&mut ranking, players[0], 10);
+        leaderboard_insert(&mut ranking, players[1], 30);
+        leaderboard_insert(&mut ranking, players[2], -5);
+        leaderboard_insert(&mut ranking, players[3], 20);
+
+        let scores: Vec<i64> = ranking.iter().map(|entry| entry.score).collect();
+        assert_eq!(scores, vec![30, 20, 10, -5]);
+        assert_eq!(ranking[0].player, players[1]);
+        assert_eq!(ranking[3].player, players[2]);
+    }
+
+    #[test]
+    fn leaderboard_insert_ranks_ties_by_arrival() {
+        let mut ranking = Vec::new();
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        leaderboard_insert(&mut ranking, first, 10);
+        leaderboard_insert(&mut ranking, second, 10);
+
+        assert_eq!(ranking[0].player, first);
+        assert_eq!(ranking[1].player, second);
+    }
+
+    #[test]
+    fn leaderboard_insert_replaces_a_players_previous_entry() {
+        let mut ranking = Vec::new();
+        let (player, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        leaderboard_insert(&mut ranking, player, 10);
+        leaderboard_insert(&mut ranking, other, 20);
+        leaderboard_insert(&mut ranking, player, 30);
+
+        assert_eq!(ranking.len(), 2);
+        assert_eq!(ranking[0].player, player);
+        assert_eq!(ranking[0].score, 30);
+
+        // A lower score moves the player down rather than keeping the best one
+        leaderboard_insert(&mut ranking, player, 5);
+        assert_eq!(ranking.len(), 2);
+        assert_eq!(ranking[1].player, player);
+        assert_eq!(ranking[1].score, 5);
+    }
+
+    #[test]
+    fn leaderboard_insert_truncates_to_the_board_size() {
+        let mut ranking = Vec::new();
+        for score in 0..LEADERBOARD_SIZE as i64 {
+            leaderboard_insert(&mut ranking, Pubkey::new_unique(), score);
+        }
+        assert_eq!(ranking.len(), LEADERBOARD_SIZE);
+
+        // Below the lowest score on a full board: not listed
+        let low = Pubkey::new_unique();
+        leaderboard_insert(&mut ranking, low, -1);
+        assert_eq!(ranking.len(), LEADERBOARD_SIZE);
+        assert!(ranking.iter().all(|entry| entry.player != low));
+
+        // A new top score pushes the lowest entry off the board
+        let high = Pubkey::new_unique();
+        leaderboard_insert(&mut ranking, high, 1_000);
+        assert_eq!(ranking.len(), LEADERBOARD_SIZE);
+        assert_eq!(ranking[0].player, high);
+        assert_eq!(ranking.last().unwrap().score, 1);
+    }
+
+    fn queue_with_ratings(ranked: bool, max_rating_gap: u32, ratings: &[u32]) -> MatchQueue {
+        MatchQueue {
+            currency_type: CurrencyType::Points,
+            stake_amount: 10,
+            rounds_to_win: 1,
+            ranked,
+            max_rating_gap,
+            entries: ratings
+                .iter()
+                .enumerate()
+                .map(|(i, &rating)| QueueEntry {
+                    player: Pubkey::new_unique(),
+                    enqueued_at: i as i64,
+                    rating,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn queue_pairs_the_oldest_compatible_entries() {
+        // Casual queues ignore ratings
+        assert_eq!(find_queue_pair(&queue_with_ratings(false, 0, &[1000, 2000])), Some((0, 1)));
+
+        // The oldest player is skipped until someone close enough to them arrives
+        assert_eq!(find_queue_pair(&queue_with_ratings(true, 100, &[2000, 1200, 1500, 1250])), Some((1, 3)));
+        assert_eq!(find_queue_pair(&queue_with_ratings(true, 100, &[2000, 1200, 1950, 1250])), Some((0, 2)));
+        // The gap is inclusive
+        assert_eq!(find_queue_pair(&queue_with_ratings(true, 100, &[1200, 1300])), Some((0, 1)));
+        assert_eq!(find_queue_pair(&queue_with_ratings(true, 100, &[1200, 1301])), None);
+    }
+
+    #[test]
+    fn token_fee_does_not_overflow_on_large_pots() {
+        assert_eq!(token_fee(u64::MAX, 10000), u64::MAX);
+        assert_eq!(token_fee(u64::MAX, 500), (u64::MAX as u128 * 500 / 10000) as u64);
+        assert_eq!(token_fee(1_000, 250), 25);
+        assert_eq!(token_fee(1_000, 0), 0);
+    }
+}
@@ -0,0 +1,104 @@
+//! Paginated index of open games, so clients can find games without scanning program accounts
+
+use crate::*;
+
+pub(crate) fn create_lobby_page(
+    ctx: Context<CreateLobbyPage>,
+    currency_type: Option<CurrencyType>,
+    page: u32,
+) -> Result<()> {
+    let lobby = &mut ctx.accounts.lobby;
+    lobby.currency_type = currency_type;
+    lobby.page = page;
+    lobby.entries = Vec::new();
+
+    msg!("Lobby page {} created", page);
+    Ok(())
+}
+
+pub(crate) fn expire_lobby_entry(ctx: Context<ExpireLobbyEntry>, _game_id: String) -> Result<()> {
+    let game = &mut ctx.accounts.game;
+    require!(
+        Clock::get()?.unix_timestamp - game.created_at >= LOBBY_ENTRY_TTL,
+        GameError::TimeoutNotReached
+    );
+    unlist_game(game.key(), game.lobby, Some(&mut ctx.accounts.lobby))?;
+    game.lobby = None;
+
+    msg!("Game {} expired from the lobby", game.game_id);
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(currency_type: Option<CurrencyType>, page: u32)]
+pub struct CreateLobbyPage<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = LOBBY_SPACE,
+        seeds = [b"lobby", [lobby_currency_seed(currency_type)].as_ref(), page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct ExpireLobbyEntry<'info> {
+    #[account(
+        mut,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(mut)]
+    pub lobby: Account<'info, Lobby>,
+}
+
+/// Games listed on one lobby page
+pub const LOBBY_PAGE_SIZE: usize = 32;
+
+/// How long a waiting game stays listed before anyone may expire it
+pub const LOBBY_ENTRY_TTL: i64 = 24 * 60 * 60;
+
+// discriminator + currency_type + page + entries
+pub const LOBBY_SPACE: usize = 8 + 2 + 4 + 4 + LOBBY_PAGE_SIZE * (32 + 1 + 8 + 1);
+
+/// One page of the open-games index, so clients can find joinable games
+/// without scanning every game account
+#[account]
+pub struct Lobby {
+    pub currency_type: Option<CurrencyType>, // Only games of this currency; any when unset
+    pub page: u32,
+    pub entries: Vec<LobbyEntry>, // Oldest first
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct LobbyEntry {
+    pub game: Pubkey,
+    pub currency_type: CurrencyType,
+    pub stake_amount: u64,
+    pub rounds_to_win: u8,
+}
+
+/// Seed byte for a lobby page's currency; `u8::MAX` for pages open to any currency
+pub fn lobby_currency_seed(currency_type: Option<CurrencyType>) -> u8 {
+    currency_type.map_or(u8::MAX, |currency| currency as u8)
+}
+
+/// Remove a game from the lobby page it is listed in. Unlisted games need no page.
+pub fn unlist_game(game: Pubkey, listed_in: Option<Pubkey>, lobby: Option<&mut Account<Lobby>>) -> Result<()> {
+    let Some(listed_in) = listed_in else {
+        return Ok(());
+    };
+    let lobby = lobby.ok_or(GameError::MissingLobby)?;
+    require!(lobby.key() == listed_in, GameError::LobbyMismatch);
+    lobby.entries.retain(|entry| entry.game != game);
+    Ok(())
+}
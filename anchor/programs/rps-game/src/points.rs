@@ -0,0 +1,734 @@
+//! Points economy: the daily faucet, transfers, the points mint, promo codes and the reward distributor
+
+use crate::*;
+
+pub(crate) fn initialize_points_config(ctx: Context<InitializePointsConfig>, params: PointsParams) -> Result<()> {
+    require!(params.claim_cooldown > 0, GameError::InvalidTimeout);
+    require!(params.transfer_fee_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+    ctx.accounts.points_config.params = params;
+
+    msg!("Points faucet initialized: {} points every {}s", params.daily_amount, params.claim_cooldown);
+    Ok(())
+}
+
+pub(crate) fn update_points_config(ctx: Context<UpdatePointsConfig>, params: PointsParams) -> Result<()> {
+    require!(params.claim_cooldown > 0, GameError::InvalidTimeout);
+    require!(params.transfer_fee_bps <= MAX_FEE_BPS, GameError::InvalidFeeBps);
+    ctx.accounts.points_config.params = params;
+
+    msg!("Points faucet updated: {} points every {}s", params.daily_amount, params.claim_cooldown);
+    Ok(())
+}
+
+pub(crate) fn claim_daily_points(ctx: Context<ClaimDailyPoints>) -> Result<()> {
+    let params = ctx.accounts.points_config.params;
+    let profile = &mut ctx.accounts.user_profile;
+    let now = Clock::get()?.unix_timestamp;
+
+    let since_last_claim = now - profile.last_claim_at;
+    require!(
+        profile.last_claim_at == 0 || since_last_claim >= params.claim_cooldown,
+        GameError::ClaimCooldown
+    );
+
+    profile.claim_streak = if profile.last_claim_at != 0 && since_last_claim < params.claim_cooldown * 2 {
+        profile.claim_streak + 1
+    } else {
+        1
+    };
+    let bonus_days = (profile.claim_streak - 1).min(params.max_streak_bonus_days);
+    let amount = params.daily_amount + params.streak_bonus * bonus_days as u64;
+
+    profile.last_claim_at = now;
+    if profile.points_migrated {
+        let points_mint = ctx.accounts.points_mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let user_token_account = ctx.accounts.user_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let token_program = ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let bump = ctx.bumps.points_mint.ok_or(GameError::MissingTokenAccounts)?;
+        mint_points(points_mint, bump, user_token_account, token_program, amount)?;
+    } else {
+        profile.points_balance += amount;
+    }
+
+    msg!(
+        "{} claimed {} daily points (streak {})",
+        ctx.accounts.user.key(),
+        amount,
+        profile.claim_streak
+    );
+    Ok(())
+}
+
+pub(crate) fn transfer_points(ctx: Context<TransferPoints>, amount: u64) -> Result<()> {
+    let params = ctx.accounts.points_config.params;
+    let sender = ctx.accounts.sender.key();
+    let recipient = ctx.accounts.recipient.key();
+    require!(sender != recipient, GameError::CannotTransferToSelf);
+    require!(amount > 0, GameError::InvalidStakeAmount);
+
+    let sender_profile = &mut ctx.accounts.sender_profile;
+    let recipient_profile = &mut ctx.accounts.recipient_profile;
+    // Migrated points are tokens and move with a regular token transfer
+    require!(
+        !sender_profile.points_migrated && !recipient_profile.points_migrated,
+        GameError::PointsMigrated
+    );
+    require!(sender_profile.active_points_games == 0, GameError::PointsLocked);
+    require!(sender_profile.points_balance >= amount, GameError::InsufficientPoints);
+
+    let now = Clock::get()?.unix_timestamp;
+    if now - sender_profile.transfer_window_start >= POINTS_TRANSFER_WINDOW {
+        sender_profile.transfer_window_start = now;
+        sender_profile.transferred_in_window = 0;
+    }
+    require!(
+        sender_profile.transferred_in_window + amount <= params.transfer_daily_limit,
+        GameError::TransferLimitExceeded
+    );
+
+    let fee = amount * params.transfer_fee_bps as u64 / 10000;
+    let received = amount - fee;
+    sender_profile.points_balance -= amount;
+    sender_profile.transferred_in_window += amount;
+    recipient_profile.points_balance += received;
+
+    emit!(PointsTransferred {
+        sender,
+        recipient,
+        amount: received,
+        burned: fee,
+        timestamp: now,
+    });
+    msg!("{} sent {} points to {} ({} burned)", sender, received, recipient, fee);
+    Ok(())
+}
+
+pub(crate) fn initialize_points_mint(ctx: Context<InitializePointsMint>) -> Result<()> {
+    msg!("Points mint {} initialized", ctx.accounts.points_mint.key());
+    Ok(())
+}
+
+pub(crate) fn migrate_points_to_tokens(ctx: Context<MigratePoints>) -> Result<()> {
+    let info = ctx.accounts.user_profile.to_account_info();
+    grow_account(
+        &info,
+        UserProfile::DISCRIMINATOR,
+        USER_PROFILE_SPACE,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+    let mut profile = UserProfile::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    require!(!profile.points_migrated, GameError::PointsAlreadyMigrated);
+    // Stakes still held by games would be refunded to a ledger the player can no longer use
+    require!(profile.active_points_games == 0, GameError::PointsLocked);
+
+    let amount = profile.points_balance;
+    profile.points_balance = 0;
+    profile.points_migrated = true;
+    profile.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+    if amount > 0 {
+        mint_points(
+            &ctx.accounts.points_mint,
+            ctx.bumps.points_mint,
+            &ctx.accounts.owner_token_account,
+            &ctx.accounts.token_program,
+            amount,
+        )?;
+    }
+
+    msg!("Migrated {} points of {} to tokens", amount, ctx.accounts.owner.key());
+    Ok(())
+}
+
+pub(crate) fn create_promo(
+    ctx: Context<CreatePromo>,
+    code_hash: [u8; 32],
+    amount: u64,
+    max_redemptions: u32,
+    expires_at: i64,
+) -> Result<()> {
+    require!(amount > 0, GameError::InvalidStakeAmount);
+    require!(max_redemptions > 0, GameError::InvalidMaxRedemptions);
+    require!(expires_at > Clock::get()?.unix_timestamp, GameError::InvalidTimestamp);
+
+    let promo = &mut ctx.accounts.promo;
+    promo.code_hash = code_hash;
+    promo.amount = amount;
+    promo.max_redemptions = max_redemptions;
+    promo.redemptions = 0;
+    promo.expires_at = expires_at;
+
+    msg!("Promo created: {} points, {} redemptions, expires at {}", amount, max_redemptions, expires_at);
+    Ok(())
+}
+
+pub(crate) fn redeem_promo(ctx: Context<RedeemPromo>, _code: String) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let promo = &mut ctx.accounts.promo;
+    require!(now < promo.expires_at, GameError::PromoExpired);
+    require!(promo.redemptions < promo.max_redemptions, GameError::PromoExhausted);
+    promo.redemptions += 1;
+    let amount = promo.amount;
+
+    let redemption = &mut ctx.accounts.redemption;
+    redemption.promo = promo.key();
+    redemption.user = ctx.accounts.user.key();
+    redemption.redeemed_at = now;
+
+    let profile = &mut ctx.accounts.user_profile;
+    if profile.points_migrated {
+        let points_mint = ctx.accounts.points_mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let user_token_account = ctx.accounts.user_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let token_program = ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+        let bump = ctx.bumps.points_mint.ok_or(GameError::MissingTokenAccounts)?;
+        mint_points(points_mint, bump, user_token_account, token_program, amount)?;
+    } else {
+        profile.points_balance += amount;
+    }
+
+    msg!("{} redeemed a promo for {} points", ctx.accounts.user.key(), amount);
+    Ok(())
+}
+
+pub(crate) fn create_distributor(
+    ctx: Context<CreateDistributor>,
+    distributor_id: String,
+    merkle_root: [u8; 32],
+    currency_type: CurrencyType,
+    total_amount: u64,
+    num_recipients: u32,
+    expires_at: i64,
+) -> Result<()> {
+    require!(distributor_id.len() <= 32, GameError::GameIdTooLong);
+    require!(currency_type != CurrencyType::SplToken, GameError::UnsupportedCurrency);
+    require!(total_amount > 0, GameError::InvalidStakeAmount);
+    require!(
+        num_recipients > 0 && num_recipients as usize <= MAX_DISTRIBUTOR_RECIPIENTS,
+        GameError::InvalidRecipientCount
+    );
+    require!(expires_at > Clock::get()?.unix_timestamp, GameError::InvalidTimestamp);
+
+    if currency_type == CurrencyType::Sol {
+        anchor_lang::solana_program::program::invoke(
+            &anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.admin.key(),
+                &ctx.accounts.distributor.key(),
+                total_amount,
+            ),
+            &[
+                ctx.accounts.admin.to_account_info(),
+                ctx.accounts.distributor.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+    }
+
+    let distributor = &mut ctx.accounts.distributor;
+    distributor.distributor_id = distributor_id;
+    distributor.merkle_root = merkle_root;
+    distributor.currency_type = currency_type;
+    distributor.total_amount = total_amount;
+    distributor.total_claimed = 0;
+    distributor.num_recipients = num_recipients;
+    distributor.expires_at = expires_at;
+    distributor.claimed = [0; DISTRIBUTOR_BITMAP_BYTES];
+
+    msg!(
+        "Distributor {} created: {} to {} recipients, expires at {}",
+        distributor.distributor_id,
+        total_amount,
+        num_recipients,
+        expires_at
+    );
+    Ok(())
+}
+
+pub(crate) fn claim_reward(
+    ctx: Context<ClaimReward>,
+    _distributor_id: String,
+    index: u32,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let player = ctx.accounts.player.key();
+    let distributor = &mut ctx.accounts.distributor;
+    require!(Clock::get()?.unix_timestamp < distributor.expires_at, GameError::DistributorExpired);
+    require!(index < distributor.num_recipients, GameError::InvalidProof);
+
+    let leaf = distributor_leaf(index, &player, amount);
+    require!(verify_merkle_proof(&proof, distributor.merkle_root, leaf), GameError::InvalidProof);
+
+    let (byte, bit) = (index as usize / 8, 1u8 << (index % 8));
+    require!(distributor.claimed[byte] & bit == 0, GameError::RewardAlreadyClaimed);
+    require!(
+        distributor.total_claimed + amount <= distributor.total_amount,
+        GameError::DistributionExceeded
+    );
+    distributor.claimed[byte] |= bit;
+    distributor.total_claimed += amount;
+
+    if distributor.currency_type == CurrencyType::Sol {
+        move_lamports(&distributor.to_account_info(), &ctx.accounts.player.to_account_info(), amount)?;
+    } else {
+        let profile = ctx.accounts.user_profile.as_mut().ok_or(GameError::MissingUserProfile)?;
+        if profile.points_migrated {
+            let points_mint = ctx.accounts.points_mint.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let user_token_account = ctx.accounts.user_token_account.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let token_program = ctx.accounts.token_program.as_ref().ok_or(GameError::MissingTokenAccounts)?;
+            let bump = ctx.bumps.points_mint.ok_or(GameError::MissingTokenAccounts)?;
+            mint_points(points_mint, bump, user_token_account, token_program, amount)?;
+        } else {
+            profile.points_balance += amount;
+        }
+        profile.total_points_earned += amount;
+    }
+
+    emit!(RewardClaimed {
+        distributor: distributor.key(),
+        player,
+        index,
+        amount,
+    });
+    msg!("{} claimed reward {} of {} from distributor {}", player, index, amount, distributor.distributor_id);
+    Ok(())
+}
+
+pub(crate) fn clawback_distributor(ctx: Context<ClawbackDistributor>, _distributor_id: String) -> Result<()> {
+    let distributor = &ctx.accounts.distributor;
+    require!(
+        Clock::get()?.unix_timestamp >= distributor.expires_at,
+        GameError::DistributorNotExpired
+    );
+
+    msg!(
+        "Distributor {} clawed back, {} of {} unclaimed",
+        distributor.distributor_id,
+        distributor.total_amount - distributor.total_claimed,
+        distributor.total_amount
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializePointsConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 8 + 8 + 8 + 4 + 8 + 8 + 2, // discriminator + faucet and transfer params
+        seeds = [b"points_config"],
+        bump
+    )]
+    pub points_config: Account<'info, PointsConfig>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePointsConfig<'info> {
+    #[account(mut, seeds = [b"points_config"], bump)]
+    pub points_config: Account<'info, PointsConfig>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDailyPoints<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(seeds = [b"points_config"], bump)]
+    pub points_config: Account<'info, PointsConfig>,
+
+    pub user: Signer<'info>,
+
+    // The accounts below are only needed once the profile has migrated to points tokens
+    #[account(mut, seeds = [b"points_mint"], bump)]
+    pub points_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, token::authority = user)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct TransferPoints<'info> {
+    #[account(
+        mut,
+        seeds = [b"user_profile", sender.key().as_ref()],
+        bump
+    )]
+    pub sender_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", recipient.key().as_ref()],
+        bump
+    )]
+    pub recipient_profile: Account<'info, UserProfile>,
+
+    pub sender: Signer<'info>,
+
+    /// CHECK: Recipient wallet, validated by the recipient_profile seeds
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(seeds = [b"points_config"], bump)]
+    pub points_config: Account<'info, PointsConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(code_hash: [u8; 32])]
+pub struct CreatePromo<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 8 + 4 + 4 + 8, // discriminator + code_hash + amount + max_redemptions + redemptions + expires_at
+        seeds = [b"promo", code_hash.as_ref()],
+        bump
+    )]
+    pub promo: Account<'info, Promo>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct RedeemPromo<'info> {
+    #[account(
+        mut,
+        seeds = [b"promo", hash_promo_code(&code).as_ref()],
+        bump
+    )]
+    pub promo: Account<'info, Promo>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + 32 + 32 + 8, // discriminator + promo + user + redeemed_at
+        seeds = [b"promo_redemption", promo.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub redemption: Account<'info, PromoRedemption>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    // The accounts below are only needed once the profile has migrated to points tokens
+    #[account(mut, seeds = [b"points_mint"], bump)]
+    pub points_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, token::authority = user)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(distributor_id: String)]
+pub struct CreateDistributor<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = DISTRIBUTOR_SPACE,
+        seeds = [b"distributor", distributor_id.as_bytes()],
+        bump
+    )]
+    pub distributor: Account<'info, Distributor>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(distributor_id: String)]
+pub struct ClaimReward<'info> {
+    #[account(
+        mut,
+        seeds = [b"distributor", distributor_id.as_bytes()],
+        bump
+    )]
+    pub distributor: Account<'info, Distributor>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    /// Player's profile, required for points distributions
+    #[account(
+        mut,
+        seeds = [b"user_profile", player.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Option<Account<'info, UserProfile>>,
+
+    // The accounts below are only needed once the profile has migrated to points tokens
+    #[account(mut, seeds = [b"points_mint"], bump)]
+    pub points_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, token::authority = player)]
+    pub user_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+#[instruction(distributor_id: String)]
+pub struct ClawbackDistributor<'info> {
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"distributor", distributor_id.as_bytes()],
+        bump
+    )]
+    pub distributor: Account<'info, Distributor>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePointsMint<'info> {
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = 0,
+        mint::authority = points_mint,
+        mint::token_program = token_program,
+        seeds = [b"points_mint"],
+        bump
+    )]
+    pub points_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePoints<'info> {
+    /// CHECK: Profile PDA, possibly in the pre-upgrade layout; grown and parsed in the handler
+    #[account(
+        mut,
+        seeds = [b"user_profile", owner.key().as_ref()],
+        bump
+    )]
+    pub user_profile: UncheckedAccount<'info>,
+
+    /// CHECK: Profile owner, validated by the user_profile seeds
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"points_mint"], bump)]
+    pub points_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        token::mint = points_mint,
+        token::authority = owner,
+        token::token_program = token_program
+    )]
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Points faucet settings
+#[account]
+pub struct PointsConfig {
+    pub params: PointsParams,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PointsParams {
+    pub daily_amount: u64, // Points granted per daily claim
+    pub claim_cooldown: i64, // Seconds between claims
+    pub streak_bonus: u64, // Extra points per consecutive claim
+    pub max_streak_bonus_days: u32, // Streak length at which the bonus stops growing
+    pub starter_grant: u64, // Points given to every new profile
+    pub transfer_daily_limit: u64, // Points a profile may send per day, 0 disables transfers
+    pub transfer_fee_bps: u16, // Share of each transfer burned as a points sink
+}
+
+/// Length of the points transfer limit window
+pub const POINTS_TRANSFER_WINDOW: i64 = 24 * 60 * 60;
+
+/// Admin-issued promo code, addressed by the hash of the code
+#[account]
+pub struct Promo {
+    pub code_hash: [u8; 32],
+    pub amount: u64, // Points granted per redemption
+    pub max_redemptions: u32,
+    pub redemptions: u32,
+    pub expires_at: i64,
+}
+
+/// Largest number of leaves a distributor's claim bitmap can track
+pub const MAX_DISTRIBUTOR_RECIPIENTS: usize = 2048;
+
+pub const DISTRIBUTOR_BITMAP_BYTES: usize = MAX_DISTRIBUTOR_RECIPIENTS / 8;
+
+// discriminator + distributor_id + merkle_root + currency_type + total_amount
+// + total_claimed + num_recipients + expires_at + claimed bitmap
+pub const DISTRIBUTOR_SPACE: usize = 8 + (4 + 32) + 32 + 1 + 8 + 8 + 4 + 8 + DISTRIBUTOR_BITMAP_BYTES;
+
+/// Batch reward payout. Leaves are `distributor_leaf(index, player, amount)`.
+#[account]
+pub struct Distributor {
+    pub distributor_id: String,
+    pub merkle_root: [u8; 32],
+    pub currency_type: CurrencyType,
+    pub total_amount: u64, // SOL held by this account, or the points budget
+    pub total_claimed: u64,
+    pub num_recipients: u32,
+    pub expires_at: i64, // Claims close and the remainder can be clawed back
+    pub claimed: [u8; DISTRIBUTOR_BITMAP_BYTES], // Bit `index` is set once leaf `index` is claimed
+}
+
+/// Marks that a user redeemed a promo; it exists at most once per (promo, user)
+#[account]
+pub struct PromoRedemption {
+    pub promo: Pubkey,
+    pub user: Pubkey,
+    pub redeemed_at: i64,
+}
+
+#[event]
+pub struct PointsTransferred {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64, // Points credited to the recipient
+    pub burned: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RewardClaimed {
+    pub distributor: Pubkey,
+    pub player: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+}
+
+/// Address of the points mint PDA
+pub fn points_mint_key() -> Pubkey {
+    Pubkey::find_program_address(&[b"points_mint"], &crate::ID).0
+}
+
+/// Mint `amount` points tokens, signed by the points mint PDA (its own authority)
+pub fn mint_points<'info>(
+    points_mint: &InterfaceAccount<'info, Mint>,
+    points_mint_bump: u8,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[b"points_mint", &[points_mint_bump]];
+    token_interface::mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token_interface::MintTo {
+                mint: points_mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: points_mint.to_account_info(),
+            },
+            &[seeds],
+        ),
+        amount,
+    )
+}
+
+/// Merkle leaf for a distributor claim. Leaves and inner nodes use different
+/// prefixes so an inner node can never be passed off as a leaf.
+pub fn distributor_leaf(index: u32, player: &Pubkey, amount: u64) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        &[0u8],
+        &index.to_le_bytes(),
+        player.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Check a merkle proof built with sorted sibling pairs
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        let (left, right) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        anchor_lang::solana_program::hash::hashv(&[&[1u8], &left, &right]).to_bytes()
+    });
+    computed == root
+}
+
+/// Hash of a promo code, used as its account seed
+pub fn hash_promo_code(code: &str) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hash(code.as_bytes()).to_bytes()
+}
@@ -0,0 +1,247 @@
+//! Matchmaking queues that pair compatible players through a permissionless crank
+
+use crate::*;
+
+pub(crate) fn create_match_queue(
+    ctx: Context<CreateMatchQueue>,
+    currency_type: CurrencyType,
+    stake_amount: u64,
+    rounds_to_win: u8,
+    ranked: bool,
+    max_rating_gap: u32,
+) -> Result<()> {
+    require!(currency_type != CurrencyType::SplToken, GameError::UnsupportedCurrency);
+    require!(stake_amount > 0, GameError::InvalidStakeAmount);
+    require!(rounds_to_win > 0 && rounds_to_win <= 10, GameError::InvalidRoundsToWin);
+
+    let queue = &mut ctx.accounts.queue;
+    queue.currency_type = currency_type;
+    queue.stake_amount = stake_amount;
+    queue.rounds_to_win = rounds_to_win;
+    queue.ranked = ranked;
+    queue.max_rating_gap = max_rating_gap;
+    queue.entries = Vec::new();
+
+    msg!("Match queue created for stake {} ({} rounds to win)", stake_amount, rounds_to_win);
+    Ok(())
+}
+
+pub(crate) fn enqueue(ctx: Context<Enqueue>) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let queue = &ctx.accounts.queue;
+    require!(queue.entries.len() < MAX_QUEUE_ENTRIES, GameError::QueueFull);
+    require!(queue.entries.iter().all(|entry| entry.player != player), GameError::AlreadyQueued);
+    let rating = if queue.ranked {
+        let rating_config = ctx.accounts.rating_config.as_ref().ok_or(GameError::MissingRatingConfig)?;
+        effective_rating(&ctx.accounts.user_profile, &rating_config.params)
+    } else {
+        0
+    };
+
+    let stake_amount = queue.stake_amount;
+    collect_stake(
+        &ctx.accounts.queue.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        queue.currency_type,
+        stake_amount,
+    )?;
+
+    let queue = &mut ctx.accounts.queue;
+    queue.entries.push(QueueEntry {
+        player,
+        enqueued_at: Clock::get()?.unix_timestamp,
+        rating,
+    });
+
+    msg!("{} joined the queue for stake {}, {} waiting", player, stake_amount, queue.entries.len());
+    Ok(())
+}
+
+pub(crate) fn dequeue(ctx: Context<Enqueue>) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let queue = &mut ctx.accounts.queue;
+    let position = queue
+        .entries
+        .iter()
+        .position(|entry| entry.player == player)
+        .ok_or(GameError::NotQueued)?;
+    queue.entries.remove(position);
+
+    let stake_amount = queue.stake_amount;
+    return_stake(
+        &queue.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &mut ctx.accounts.user_profile,
+        queue.currency_type,
+        stake_amount,
+    )?;
+
+    msg!("{} left the queue, {} refunded", player, stake_amount);
+    Ok(())
+}
+
+pub(crate) fn match_players(ctx: Context<MatchPlayers>, game_id: String) -> Result<()> {
+    require!(game_id.len() <= 32, GameError::GameIdTooLong);
+    let queue = &mut ctx.accounts.queue;
+    require!(queue.entries.len() >= 2, GameError::NotEnoughQueued);
+    let (first, second) = find_queue_pair(queue).ok_or(GameError::NoCompatibleOpponents)?;
+    let player2 = queue.entries.remove(second).player;
+    let player1 = queue.entries.remove(first).player;
+
+    // Both deposits move from the queue into the game's escrow
+    if queue.currency_type == CurrencyType::Sol {
+        move_lamports(
+            &queue.to_account_info(),
+            &ctx.accounts.game.to_account_info(),
+            queue.stake_amount * 2,
+        )?;
+    }
+
+    let game = &mut ctx.accounts.game;
+    init_game(
+        game,
+        game_id,
+        player1,
+        Some(player2),
+        queue.stake_amount,
+        queue.currency_type,
+        queue.rounds_to_win,
+    )?;
+    game.ranked = queue.ranked;
+    // The cranker fronts the game's rent and gets it back when the game is closed
+    game.rent_payer = Some(ctx.accounts.cranker.key());
+
+    emit!(PlayersMatched {
+        queue: queue.key(),
+        game: game.key(),
+        player1,
+        player2,
+    });
+    msg!("Matched {} and {} into game {}", player1, player2, game.game_id);
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(currency_type: CurrencyType, stake_amount: u64)]
+pub struct CreateMatchQueue<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = MATCH_QUEUE_SPACE,
+        seeds = [b"match_queue", [currency_type as u8].as_ref(), stake_amount.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub queue: Account<'info, MatchQueue>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Enqueue<'info> {
+    #[account(
+        mut,
+        seeds = [b"match_queue", [queue.currency_type as u8].as_ref(), queue.stake_amount.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub queue: Account<'info, MatchQueue>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    /// Rating settings, required to join a ranked queue
+    #[account(seeds = [b"rating_config"], bump)]
+    pub rating_config: Option<Account<'info, RatingConfig>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct MatchPlayers<'info> {
+    #[account(
+        mut,
+        seeds = [b"match_queue", [queue.currency_type as u8].as_ref(), queue.stake_amount.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub queue: Account<'info, MatchQueue>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = GAME_SPACE,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Players waiting in the matchmaking queue
+pub const MAX_QUEUE_ENTRIES: usize = 32;
+
+// discriminator + currency_type + stake_amount + rounds_to_win + ranked + max_rating_gap + entries
+pub const MATCH_QUEUE_SPACE: usize = 8 + 1 + 8 + 1 + 1 + 4 + 4 + MAX_QUEUE_ENTRIES * (32 + 8 + 4);
+
+/// Matchmaking queue for one (currency, stake) tier. Queued SOL stakes are held
+/// by this account; queued points stakes are taken off the players' ledgers.
+#[account]
+pub struct MatchQueue {
+    pub currency_type: CurrencyType,
+    pub stake_amount: u64,
+    pub rounds_to_win: u8,
+    pub ranked: bool,
+    pub max_rating_gap: u32, // Widest rating difference a ranked queue will pair
+    pub entries: Vec<QueueEntry>, // Oldest first
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct QueueEntry {
+    pub player: Pubkey,
+    pub enqueued_at: i64,
+    pub rating: u32, // Effective rating when queued; 0 in casual queues
+}
+
+#[event]
+pub struct PlayersMatched {
+    pub queue: Pubkey,
+    pub game: Pubkey,
+    pub player1: Pubkey,
+    pub player2: Pubkey,
+}
+
+/// Entries to pair from a queue: the longest-waiting player who has an opponent
+/// within the queue's rating gap, and the longest-waiting such opponent. Casual
+/// queues pair the first two entries.
+pub fn find_queue_pair(queue: &MatchQueue) -> Option<(usize, usize)> {
+    let entries = &queue.entries;
+    (0..entries.len()).find_map(|first| {
+        (first + 1..entries.len())
+            .find(|&second| {
+                !queue.ranked || entries[first].rating.abs_diff(entries[second].rating) <= queue.max_rating_gap
+            })
+            .map(|second| (first, second))
+    })
+}
@@ -0,0 +1,593 @@
+//! Battle royale: N-player simultaneous rounds where the losing move is eliminated
+
+use crate::*;
+
+pub(crate) fn create_battle_royale(
+    ctx: Context<CreateBattleRoyale>,
+    game_id: String,
+    stake_amount: u64,
+    currency_type: CurrencyType,
+    max_players: u8,
+    move_timeout: i64,
+) -> Result<()> {
+    require!(game_id.len() <= 32, GameError::GameIdTooLong);
+    require!(currency_type != CurrencyType::SplToken, GameError::UnsupportedCurrency);
+    require!(stake_amount > 0, GameError::InvalidStakeAmount);
+    require!(
+        max_players as usize >= MIN_ROYALE_PLAYERS && max_players as usize <= MAX_ROYALE_PLAYERS,
+        GameError::InvalidMaxPlayers
+    );
+    require!(move_timeout > 0, GameError::InvalidTimeout);
+
+    collect_stake(
+        &ctx.accounts.battle_royale.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        currency_type,
+        stake_amount,
+    )?;
+
+    let royale = &mut ctx.accounts.battle_royale;
+    royale.game_id = game_id;
+    royale.creator = ctx.accounts.user.key();
+    royale.status = GameStatus::WaitingForPlayer;
+    royale.stake_amount = stake_amount;
+    royale.currency_type = currency_type;
+    royale.max_players = max_players;
+    royale.players = vec![ctx.accounts.user.key()];
+    royale.alive = 0;
+    royale.commitments = [None; MAX_ROYALE_PLAYERS];
+    royale.revealed_moves = [None; MAX_ROYALE_PLAYERS];
+    royale.round = 0;
+    royale.phase = RoyalePhase::Commit;
+    royale.move_timeout = move_timeout;
+    royale.phase_deadline = 0;
+    royale.winner = None;
+    royale.created_at = Clock::get()?.unix_timestamp;
+
+    msg!("Battle royale {} created by {}", royale.game_id, royale.creator);
+    Ok(())
+}
+
+pub(crate) fn join_battle_royale(ctx: Context<JoinBattleRoyale>, _game_id: String) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let royale = &ctx.accounts.battle_royale;
+
+    require!(royale.status == GameStatus::WaitingForPlayer, GameError::GameNotJoinable);
+    require!(royale.players.len() < royale.max_players as usize, GameError::GameAlreadyFull);
+    require!(!royale.players.contains(&player), GameError::AlreadyRegistered);
+
+    let stake_amount = royale.stake_amount;
+    collect_stake(
+        &ctx.accounts.battle_royale.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        royale.currency_type,
+        stake_amount,
+    )?;
+
+    let royale = &mut ctx.accounts.battle_royale;
+    royale.players.push(player);
+
+    msg!(
+        "Player {} joined battle royale {} ({}/{})",
+        player,
+        royale.game_id,
+        royale.players.len(),
+        royale.max_players
+    );
+    Ok(())
+}
+
+pub(crate) fn leave_battle_royale(ctx: Context<LeaveBattleRoyale>, _game_id: String) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let royale = &ctx.accounts.battle_royale;
+
+    require!(royale.status == GameStatus::WaitingForPlayer, GameError::GameAlreadyStarted);
+    require!(player != royale.creator, GameError::CreatorCannotLeave);
+    let index = royale
+        .players
+        .iter()
+        .position(|p| *p == player)
+        .ok_or(GameError::NotAPlayer)?;
+
+    let stake_amount = royale.stake_amount;
+    return_stake(
+        &ctx.accounts.battle_royale.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &mut ctx.accounts.user_profile,
+        royale.currency_type,
+        stake_amount,
+    )?;
+
+    let royale = &mut ctx.accounts.battle_royale;
+    royale.players.remove(index);
+
+    msg!("Player {} left battle royale {}", player, royale.game_id);
+    Ok(())
+}
+
+pub(crate) fn cancel_battle_royale(ctx: Context<CancelBattleRoyale>, _game_id: String) -> Result<()> {
+    let royale = &ctx.accounts.battle_royale;
+    require!(royale.status == GameStatus::WaitingForPlayer, GameError::GameAlreadyStarted);
+    require!(royale.players.len() == 1, GameError::PlayersStillJoined);
+
+    let stake_amount = royale.stake_amount;
+    return_stake(
+        &ctx.accounts.battle_royale.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &mut ctx.accounts.user_profile,
+        royale.currency_type,
+        stake_amount,
+    )?;
+
+    msg!("Battle royale {} cancelled. Refunding {} to creator {}.", royale.game_id, stake_amount, royale.creator);
+    Ok(())
+}
+
+pub(crate) fn start_battle_royale(ctx: Context<StartBattleRoyale>, _game_id: String) -> Result<()> {
+    let royale = &mut ctx.accounts.battle_royale;
+    require!(royale.status == GameStatus::WaitingForPlayer, GameError::GameAlreadyStarted);
+    require!(royale.players.len() >= MIN_ROYALE_PLAYERS, GameError::NotEnoughPlayers);
+
+    royale.status = GameStatus::InProgress;
+    royale.alive = ((1u32 << royale.players.len()) - 1) as u16;
+    royale.round = 1;
+    royale.phase = RoyalePhase::Commit;
+    royale.phase_deadline = Clock::get()?.unix_timestamp + royale.move_timeout;
+
+    msg!("Battle royale {} started with {} players", royale.game_id, royale.players.len());
+    Ok(())
+}
+
+pub(crate) fn commit_battle_royale_move(
+    ctx: Context<CommitBattleRoyaleMove>,
+    _game_id: String,
+    move_commitment: [u8; 32],
+) -> Result<()> {
+    let royale = &mut ctx.accounts.battle_royale;
+    let index = royale_player_index(royale, ctx.accounts.user.key())?;
+
+    require!(royale.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(royale.phase == RoyalePhase::Commit, GameError::WrongPhase);
+    require!(royale.commitments[index].is_none(), GameError::MoveAlreadyCommitted);
+
+    royale.commitments[index] = Some(move_commitment);
+
+    if royale_alive_indices(royale).all(|i| royale.commitments[i].is_some()) {
+        royale.phase = RoyalePhase::Reveal;
+        royale.phase_deadline = Clock::get()?.unix_timestamp + royale.move_timeout;
+        msg!("Battle royale {} round {}: all moves committed", royale.game_id, royale.round);
+    }
+    Ok(())
+}
+
+pub(crate) fn reveal_battle_royale_move(
+    ctx: Context<RevealBattleRoyaleMove>,
+    _game_id: String,
+    player_move: Move,
+    nonce: u64,
+) -> Result<()> {
+    let royale = &mut ctx.accounts.battle_royale;
+    let index = royale_player_index(royale, ctx.accounts.user.key())?;
+
+    require!(royale.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(royale.phase == RoyalePhase::Reveal, GameError::WrongPhase);
+    require!(royale.revealed_moves[index].is_none(), GameError::MoveAlreadyRevealed);
+    let commitment = royale.commitments[index].ok_or(GameError::MoveNotCommitted)?;
+    require!(commitment == hash_move(player_move, nonce), GameError::InvalidCommitment);
+
+    royale.revealed_moves[index] = Some(player_move);
+
+    if royale_alive_indices(royale).all(|i| royale.revealed_moves[i].is_some()) {
+        resolve_battle_royale_round(royale)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn resolve_battle_royale_timeout(
+    ctx: Context<ResolveBattleRoyaleTimeout>,
+    _game_id: String,
+) -> Result<()> {
+    let royale = &mut ctx.accounts.battle_royale;
+    require!(royale.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(
+        Clock::get()?.unix_timestamp >= royale.phase_deadline,
+        GameError::TimeoutNotReached
+    );
+
+    let acted: Vec<usize> = royale_alive_indices(royale)
+        .filter(|i| match royale.phase {
+            RoyalePhase::Commit => royale.commitments[*i].is_some(),
+            RoyalePhase::Reveal => royale.revealed_moves[*i].is_some(),
+        })
+        .collect();
+
+    if acted.is_empty() {
+        royale.status = GameStatus::Drawn;
+        msg!("Battle royale {} round {}: nobody acted, survivors split the pot", royale.game_id, royale.round);
+        return Ok(());
+    }
+
+    royale.alive = acted.iter().fold(0u16, |alive, i| alive | (1 << i));
+    msg!(
+        "Battle royale {} round {}: {} players left after timeout",
+        royale.game_id,
+        royale.round,
+        acted.len()
+    );
+
+    if royale.phase == RoyalePhase::Commit && acted.len() > 1 {
+        royale.phase = RoyalePhase::Reveal;
+        royale.phase_deadline = Clock::get()?.unix_timestamp + royale.move_timeout;
+        return Ok(());
+    }
+    resolve_battle_royale_round(royale)
+}
+
+pub(crate) fn finalize_battle_royale(ctx: Context<FinalizeBattleRoyale>, _game_id: String) -> Result<()> {
+    let royale = &ctx.accounts.battle_royale;
+    require!(
+        royale.status == GameStatus::Finished || royale.status == GameStatus::Drawn,
+        GameError::GameNotFinished
+    );
+    require!(
+        ctx.remaining_accounts.len() == 2 * royale.players.len(),
+        GameError::MissingUserProfile
+    );
+
+    let total_pot = royale.stake_amount * royale.players.len() as u64;
+    let total_fees = if royale.currency_type == CurrencyType::Sol {
+        total_pot * get_platform_fee_rate(royale.stake_amount) / 10000 // fee_rate is in basis points
+    } else {
+        0
+    };
+    // Points left over from an uneven split are burned; lamports go to the platform
+    let share = (total_pot - total_fees) / royale.alive.count_ones() as u64;
+    let royale_info = ctx.accounts.battle_royale.to_account_info();
+
+    for (index, player) in royale.players.iter().enumerate() {
+        let profile_info = &ctx.remaining_accounts[2 * index];
+        let wallet_info = &ctx.remaining_accounts[2 * index + 1];
+        require!(wallet_info.key() == *player, GameError::NotAPlayer);
+
+        let mut profile = load_player_profile(profile_info, player)?;
+        if royale.currency_type == CurrencyType::Points {
+            unlock_points(&mut profile)?;
+        }
+        profile.total_games += 1;
+        if royale.alive & (1 << index) == 0 {
+            profile.losses += 1;
+        } else {
+            if royale.status == GameStatus::Finished {
+                profile.wins += 1;
+            } else {
+                profile.draws += 1;
+            }
+
+            if royale.currency_type == CurrencyType::Points {
+                profile.points_balance += share;
+            } else if royale.currency_type == CurrencyType::Sol {
+                move_lamports(&royale_info, wallet_info, share)?;
+            }
+            profile.total_points_earned += share;
+        }
+        save_player_profile(profile_info, &profile)?;
+    }
+
+    if royale.currency_type == CurrencyType::Sol {
+        let platform_amount = total_pot - share * royale.alive.count_ones() as u64;
+        move_lamports(&royale_info, &ctx.accounts.platform_wallet.to_account_info(), platform_amount)?;
+    }
+
+    msg!(
+        "Battle royale {} finalized! {} survivor(s) paid {} each, Pot: {}",
+        royale.game_id,
+        royale.alive.count_ones(),
+        share,
+        total_pot
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CreateBattleRoyale<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = BATTLE_ROYALE_SPACE,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct JoinBattleRoyale<'info> {
+    #[account(
+        mut,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct LeaveBattleRoyale<'info> {
+    #[account(
+        mut,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CancelBattleRoyale<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump,
+        constraint = battle_royale.creator == user.key() @ GameError::NotAPlayer
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct StartBattleRoyale<'info> {
+    #[account(
+        mut,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump,
+        constraint = battle_royale.creator == user.key() @ GameError::NotAPlayer
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CommitBattleRoyaleMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct RevealBattleRoyaleMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct ResolveBattleRoyaleTimeout<'info> {
+    #[account(
+        mut,
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct FinalizeBattleRoyale<'info> {
+    #[account(
+        mut,
+        close = creator, // Return rent to the creator once paid out
+        seeds = [b"battle_royale", game_id.as_bytes()],
+        bump
+    )]
+    pub battle_royale: Account<'info, BattleRoyale>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: This account is checked against the battle royale's creator field
+    #[account(mut, address = battle_royale.creator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the platform wallet stored in config
+    #[account(mut, address = config.platform_wallet)]
+    pub platform_wallet: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+}
+
+pub const MIN_ROYALE_PLAYERS: usize = 3;
+
+pub const MAX_ROYALE_PLAYERS: usize = 16;
+
+pub const MAX_ROYALE_ROUNDS: u8 = 50; // Rounds played before the survivors split the pot
+
+// discriminator + settings + players + alive bitmap + commitments + revealed moves + round state + winner + created_at
+pub const BATTLE_ROYALE_SPACE: usize = 8
+    + (4 + 32) + 32 + 1 + 8 + 1 + 1
+    + (4 + 32 * MAX_ROYALE_PLAYERS) + 2
+    + 33 * MAX_ROYALE_PLAYERS
+    + 2 * MAX_ROYALE_PLAYERS
+    + 1 + 1 + 8 + 8
+    + 33 + 8;
+
+/// N-player elimination game. All surviving players commit and reveal each
+/// round; players holding the beaten move are knocked out.
+#[account]
+pub struct BattleRoyale {
+    pub game_id: String,
+    pub creator: Pubkey,
+    pub status: GameStatus,
+    pub stake_amount: u64,
+    pub currency_type: CurrencyType,
+    pub max_players: u8,
+    pub players: Vec<Pubkey>,
+    pub alive: u16, // Bitmap over `players` still in the game
+    pub commitments: [Option<[u8; 32]>; MAX_ROYALE_PLAYERS],
+    pub revealed_moves: [Option<Move>; MAX_ROYALE_PLAYERS],
+    pub round: u8,
+    pub phase: RoyalePhase,
+    pub move_timeout: i64, // Seconds allowed for each commit or reveal phase
+    pub phase_deadline: i64,
+    pub winner: Option<Pubkey>,
+    pub created_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RoyalePhase {
+    Commit,
+    Reveal,
+}
+
+/// Index of a surviving battle-royale player
+pub fn royale_player_index(royale: &BattleRoyale, player: Pubkey) -> Result<usize> {
+    let index = royale
+        .players
+        .iter()
+        .position(|p| *p == player)
+        .ok_or(GameError::NotAPlayer)?;
+    require!(royale.alive & (1 << index) != 0, GameError::NotAPlayer);
+    Ok(index)
+}
+
+pub fn royale_alive_indices(royale: &BattleRoyale) -> impl Iterator<Item = usize> + '_ {
+    (0..royale.players.len()).filter(move |i| royale.alive & (1 << i) != 0)
+}
+
+/// Knock out every surviving player holding the beaten move. If all three
+/// moves or only one move were revealed the round is a draw. Survivors who
+/// did not reveal (only possible after a timeout) are already eliminated.
+pub fn resolve_battle_royale_round(royale: &mut BattleRoyale) -> Result<()> {
+    let mut present = [false; 3];
+    for i in royale_alive_indices(royale) {
+        if let Some(player_move) = royale.revealed_moves[i] {
+            present[player_move as usize] = true;
+        }
+    }
+
+    let losing_move = match present {
+        [true, true, false] => Some(Move::Rock),
+        [true, false, true] => Some(Move::Scissors),
+        [false, true, true] => Some(Move::Paper),
+        _ => None,
+    };
+
+    match losing_move {
+        Some(losing_move) => {
+            let eliminated: Vec<usize> = royale_alive_indices(royale)
+                .filter(|i| royale.revealed_moves[*i] == Some(losing_move))
+                .collect();
+            for i in eliminated {
+                royale.alive &= !(1 << i);
+            }
+            msg!(
+                "Battle royale {} round {}: {} players left",
+                royale.game_id,
+                royale.round,
+                royale.alive.count_ones()
+            );
+        }
+        None => msg!("Battle royale {} round {} is a draw", royale.game_id, royale.round),
+    }
+
+    if royale.alive.count_ones() == 1 {
+        let winner = royale_alive_indices(royale).next().unwrap();
+        royale.winner = Some(royale.players[winner]);
+        royale.status = GameStatus::Finished;
+        msg!("Battle royale {} completed! Winner: {}", royale.game_id, royale.players[winner]);
+        return Ok(());
+    }
+    if royale.round >= MAX_ROYALE_ROUNDS {
+        royale.status = GameStatus::Drawn;
+        msg!("Battle royale {} reached the round limit, survivors split the pot", royale.game_id);
+        return Ok(());
+    }
+
+    royale.round += 1;
+    royale.commitments = [None; MAX_ROYALE_PLAYERS];
+    royale.revealed_moves = [None; MAX_ROYALE_PLAYERS];
+    royale.phase = RoyalePhase::Commit;
+    royale.phase_deadline = Clock::get()?.unix_timestamp + royale.move_timeout;
+    Ok(())
+}
+
+/// Load the profile of `player` passed as a remaining account
+pub fn load_player_profile(info: &AccountInfo, player: &Pubkey) -> Result<UserProfile> {
+    let (expected, _) = Pubkey::find_program_address(&[b"user_profile", player.as_ref()], &crate::ID);
+    require!(
+        info.key() == expected && *info.owner == crate::ID && info.is_writable,
+        GameError::MissingUserProfile
+    );
+    UserProfile::try_deserialize(&mut &info.try_borrow_data()?[..])
+}
+
+/// Write back a profile loaded with `load_player_profile`
+pub fn save_player_profile(info: &AccountInfo, profile: &UserProfile) -> Result<()> {
+    profile.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
@@ -0,0 +1,266 @@
+//! Seasons with per-season player stats, rollover and the top-N leaderboards
+
+use crate::*;
+
+pub(crate) fn initialize_season(ctx: Context<InitializeSeason>, duration: i64) -> Result<()> {
+    require!(duration > 0, GameError::InvalidTimeout);
+    let now = Clock::get()?.unix_timestamp;
+
+    let season = &mut ctx.accounts.season;
+    season.season_id = 1;
+    season.started_at = now;
+    season.ends_at = now + duration;
+    season.duration = duration;
+
+    msg!("Season 1 started, ends at {}", season.ends_at);
+    Ok(())
+}
+
+pub(crate) fn start_new_season(ctx: Context<StartNewSeason>, duration: i64) -> Result<()> {
+    require!(duration > 0, GameError::InvalidTimeout);
+    let season = &mut ctx.accounts.season;
+    season.duration = duration;
+    advance_season(season, Clock::get()?.unix_timestamp);
+
+    msg!("Season {} started, ends at {}", season.season_id, season.ends_at);
+    Ok(())
+}
+
+pub(crate) fn open_season_stats(ctx: Context<OpenSeasonStats>) -> Result<()> {
+    let stats = &mut ctx.accounts.season_stats;
+    stats.season_id = ctx.accounts.season.season_id;
+    stats.player = ctx.accounts.user.key();
+    stats.wins = 0;
+    stats.losses = 0;
+    stats.total_games = 0;
+    stats.net_sol_won = 0;
+
+    msg!("Season {} stats opened for {}", stats.season_id, stats.player);
+    Ok(())
+}
+
+pub(crate) fn initialize_leaderboard(ctx: Context<InitializeLeaderboard>, season_id: u32) -> Result<()> {
+    let leaderboard = &mut ctx.accounts.leaderboard;
+    leaderboard.season_id = season_id;
+    leaderboard.by_wins = Vec::new();
+    leaderboard.by_net_sol = Vec::new();
+    leaderboard.by_rating = Vec::new();
+
+    msg!("Leaderboard initialized for season {}", season_id);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeSeason<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 4 + 8 + 8 + 8, // discriminator + season_id + started_at + ends_at + duration
+        seeds = [b"season"],
+        bump
+    )]
+    pub season: Account<'info, Season>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartNewSeason<'info> {
+    #[account(mut, seeds = [b"season"], bump)]
+    pub season: Account<'info, Season>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct OpenSeasonStats<'info> {
+    #[account(seeds = [b"season"], bump)]
+    pub season: Account<'info, Season>,
+
+    #[account(
+        init,
+        payer = user,
+        space = SEASON_STATS_SPACE,
+        seeds = [b"season_stats", season.season_id.to_le_bytes().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub season_stats: Account<'info, SeasonStats>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(season_id: u32)]
+pub struct InitializeLeaderboard<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = LEADERBOARD_SPACE,
+        seeds = [b"leaderboard", season_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// The active season. Past seasons live on in their `SeasonStats` accounts.
+#[account]
+pub struct Season {
+    pub season_id: u32,
+    pub started_at: i64,
+    pub ends_at: i64,
+    pub duration: i64, // Length of the next season on rollover
+}
+
+/// A player's record for one season, keyed by (season_id, player)
+#[account]
+pub struct SeasonStats {
+    pub season_id: u32,
+    pub player: Pubkey,
+    pub wins: u32,
+    pub losses: u32,
+    pub total_games: u32,
+    pub net_sol_won: i64,
+}
+
+// discriminator + season_id + player + wins + losses + total_games + net_sol_won
+pub const SEASON_STATS_SPACE: usize = 8 + 4 + 32 + 4 + 4 + 4 + 8;
+
+/// Entries kept per leaderboard ranking
+pub const LEADERBOARD_SIZE: usize = 64;
+
+// discriminator + season_id + by_wins + by_net_sol + by_rating
+pub const LEADERBOARD_SPACE: usize = 8 + 4 + 3 * (4 + LEADERBOARD_SIZE * (32 + 8));
+
+/// Top players of a season (season 0 is all time), each ranking sorted by
+/// score, highest first
+#[account]
+pub struct Leaderboard {
+    pub season_id: u32,
+    pub by_wins: Vec<LeaderboardEntry>,
+    pub by_net_sol: Vec<LeaderboardEntry>, // Net lamports won
+    pub by_rating: Vec<LeaderboardEntry>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct LeaderboardEntry {
+    pub player: Pubkey,
+    pub score: i64,
+}
+
+/// Place `player` at `score` in a ranking, replacing their previous entry.
+/// The ranking stays sorted highest first and is capped at `LEADERBOARD_SIZE`;
+/// ties keep the earlier entry ahead.
+pub fn leaderboard_insert(ranking: &mut Vec<LeaderboardEntry>, player: Pubkey, score: i64) {
+    ranking.retain(|entry| entry.player != player);
+    let position = ranking.partition_point(|entry| entry.score >= score);
+    if position < LEADERBOARD_SIZE {
+        ranking.insert(position, LeaderboardEntry { player, score });
+        ranking.truncate(LEADERBOARD_SIZE);
+    }
+}
+
+/// Load a player's stats for `season_id` from their PDA, creating the account
+/// (paid by `payer`) when it doesn't exist yet
+pub fn load_or_create_season_stats<'info>(
+    info: &AccountInfo<'info>,
+    season_id: u32,
+    player: &Pubkey,
+    payer: Option<&Signer<'info>>,
+    system_program: &Program<'info, System>,
+) -> Result<SeasonStats> {
+    let season_id_bytes = season_id.to_le_bytes();
+    let (expected, bump) =
+        Pubkey::find_program_address(&[b"season_stats", season_id_bytes.as_ref(), player.as_ref()], &crate::ID);
+    require!(info.key() == expected && info.is_writable, GameError::MissingSeasonStats);
+    if let Some(stats) = load_if_initialized::<SeasonStats>(info)? {
+        return Ok(stats);
+    }
+
+    let payer = payer.ok_or(GameError::MissingSeasonStats)?;
+    let seeds: &[&[u8]] = &[b"season_stats", season_id_bytes.as_ref(), player.as_ref(), &[bump]];
+    create_pda_account(info, payer, system_program, SEASON_STATS_SPACE, seeds)?;
+    Ok(SeasonStats {
+        season_id,
+        player: *player,
+        wins: 0,
+        losses: 0,
+        total_games: 0,
+        net_sol_won: 0,
+    })
+}
+
+/// Create a program-owned PDA of `space` bytes, paid by `payer`. Lamports already
+/// sent to the address are kept and only topped up to rent exemption.
+pub fn create_pda_account<'info>(
+    info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount, Transfer};
+
+    let rent = Rent::get()?.minimum_balance(space);
+    let signer = &[signer_seeds];
+    let program = system_program.to_account_info();
+    if info.lamports() == 0 {
+        let accounts = CreateAccount { from: payer.to_account_info(), to: info.clone() };
+        system_program::create_account(
+            CpiContext::new_with_signer(program, accounts, signer),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+        return Ok(());
+    }
+
+    let shortfall = rent.saturating_sub(info.lamports());
+    if shortfall > 0 {
+        let accounts = Transfer { from: payer.to_account_info(), to: info.clone() };
+        system_program::transfer(CpiContext::new(program.clone(), accounts), shortfall)?;
+    }
+    let accounts = Allocate { account_to_allocate: info.clone() };
+    system_program::allocate(CpiContext::new_with_signer(program.clone(), accounts, signer), space as u64)?;
+    let accounts = Assign { account_to_assign: info.clone() };
+    system_program::assign(CpiContext::new_with_signer(program, accounts, signer), &crate::ID)?;
+    Ok(())
+}
+
+/// Roll over to the next season, starting now
+pub fn advance_season(season: &mut Season, now: i64) {
+    season.season_id += 1;
+    season.started_at = now;
+    season.ends_at = now + season.duration;
+}
@@ -0,0 +1,462 @@
+//! Best-of-N series played as a chain of child games with one aggregate payout
+
+use crate::*;
+
+pub(crate) fn create_series(
+    ctx: Context<CreateSeries>,
+    series_id: String,
+    stake_amount: u64,
+    currency_type: CurrencyType,
+    games_to_win: u8,
+    rounds_to_win: u8,
+    game_timeout: i64,
+) -> Result<()> {
+    require!(series_id.len() <= 32, GameError::GameIdTooLong);
+    require!(currency_type != CurrencyType::SplToken, GameError::UnsupportedCurrency);
+    require!(stake_amount > 0, GameError::InvalidStakeAmount);
+    require!(games_to_win > 0 && games_to_win <= 10, GameError::InvalidGamesToWin);
+    require!(rounds_to_win > 0 && rounds_to_win <= 10, GameError::InvalidRoundsToWin);
+    require!(game_timeout > 0, GameError::InvalidTimeout);
+
+    collect_stake(
+        &ctx.accounts.series.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        currency_type,
+        stake_amount,
+    )?;
+
+    let series = &mut ctx.accounts.series;
+    series.series_id = series_id;
+    series.player1 = ctx.accounts.user.key();
+    series.player2 = None;
+    series.status = GameStatus::WaitingForPlayer;
+    series.stake_amount = stake_amount;
+    series.currency_type = currency_type;
+    series.games_to_win = games_to_win;
+    series.rounds_to_win = rounds_to_win;
+    series.games_played = 0;
+    series.player1_games_won = 0;
+    series.player2_games_won = 0;
+    series.current_game = None;
+    series.winner = None;
+    series.created_at = Clock::get()?.unix_timestamp;
+    series.game_timeout = game_timeout;
+    series.game_started_at = 0;
+
+    msg!("Series {} created by {}", series.series_id, ctx.accounts.user.key());
+    Ok(())
+}
+
+pub(crate) fn join_series(ctx: Context<JoinSeries>, _series_id: String) -> Result<()> {
+    let series = &ctx.accounts.series;
+    require!(series.status == GameStatus::WaitingForPlayer, GameError::GameNotJoinable);
+    require!(series.player1 != ctx.accounts.user.key(), GameError::CannotJoinOwnGame);
+
+    let stake_amount = series.stake_amount;
+    collect_stake(
+        &ctx.accounts.series.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        series.currency_type,
+        stake_amount,
+    )?;
+
+    let series = &mut ctx.accounts.series;
+    series.player2 = Some(ctx.accounts.user.key());
+    series.status = GameStatus::InProgress;
+
+    msg!("Player {} joined series {}", ctx.accounts.user.key(), series.series_id);
+    Ok(())
+}
+
+pub(crate) fn cancel_series(ctx: Context<CancelSeries>, _series_id: String) -> Result<()> {
+    let series = &ctx.accounts.series;
+    require!(series.status == GameStatus::WaitingForPlayer, GameError::GameAlreadyStarted);
+
+    let stake_amount = series.stake_amount;
+    return_stake(
+        &ctx.accounts.series.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &mut ctx.accounts.user_profile,
+        series.currency_type,
+        stake_amount,
+    )?;
+
+    msg!("Series {} cancelled. Refunding {} to creator {}.", series.series_id, stake_amount, series.player1);
+    Ok(())
+}
+
+pub(crate) fn start_series_game(
+    ctx: Context<StartSeriesGame>,
+    _series_id: String,
+    game_id: String,
+) -> Result<()> {
+    require!(game_id.len() <= 32, GameError::GameIdTooLong);
+
+    let series = &ctx.accounts.series;
+    let player = ctx.accounts.user.key();
+    require!(series.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(
+        player == series.player1 || Some(player) == series.player2,
+        GameError::NotAPlayer
+    );
+    require!(series.current_game.is_none(), GameError::SeriesGameInProgress);
+
+    let series_key = series.key();
+    let game = &mut ctx.accounts.game;
+    init_game(
+        game,
+        game_id,
+        series.player1,
+        series.player2,
+        0,
+        series.currency_type,
+        series.rounds_to_win,
+    )?;
+    game.parent = Some(series_key);
+
+    let game_key = game.key();
+    let series = &mut ctx.accounts.series;
+    series.current_game = Some(game_key);
+    series.game_started_at = Clock::get()?.unix_timestamp;
+
+    msg!(
+        "Series {} game {} started: {}",
+        series.series_id,
+        series.games_played + 1,
+        ctx.accounts.game.game_id
+    );
+    Ok(())
+}
+
+pub(crate) fn record_series_game(
+    ctx: Context<RecordSeriesGame>,
+    _series_id: String,
+    _game_id: String,
+) -> Result<()> {
+    let game = &mut ctx.accounts.game;
+    let series = &mut ctx.accounts.series;
+
+    require!(series.current_game == Some(game.key()), GameError::NotCurrentGame);
+
+    match game.game_status {
+        GameStatus::Finished => {
+            let winner = game.winner.ok_or(GameError::NoWinner)?;
+            if winner == series.player1 {
+                series.player1_games_won += 1;
+            } else {
+                series.player2_games_won += 1;
+            }
+        }
+        GameStatus::Drawn | GameStatus::Cancelled => {
+            msg!("Series {} game {} ended without a winner", series.series_id, game.game_id);
+        }
+        _ => {
+            require!(
+                Clock::get()?.unix_timestamp >= series.game_started_at + series.game_timeout,
+                GameError::GameNotFinished
+            );
+
+            let winner = forfeit_winner(game);
+            game.winner = Some(winner);
+            game.game_status = GameStatus::Abandoned;
+            if winner == series.player1 {
+                series.player1_games_won += 1;
+            } else {
+                series.player2_games_won += 1;
+            }
+            msg!("Series {} game {} forfeited on timeout by {}", series.series_id, game.game_id, winner);
+        }
+    }
+
+    series.games_played += 1;
+    series.current_game = None;
+
+    if series.player1_games_won >= series.games_to_win {
+        series.status = GameStatus::Finished;
+        series.winner = Some(series.player1);
+    } else if series.player2_games_won >= series.games_to_win {
+        series.status = GameStatus::Finished;
+        series.winner = series.player2;
+    } else if series.games_played - series.player1_games_won - series.player2_games_won
+        >= MAX_SERIES_UNDECIDED_GAMES
+    {
+        series.status = GameStatus::Drawn;
+        msg!("Series {} drawn after {} games without a winner", series.series_id, MAX_SERIES_UNDECIDED_GAMES);
+    }
+
+    msg!(
+        "Series {} score: {} - {}",
+        series.series_id,
+        series.player1_games_won,
+        series.player2_games_won
+    );
+    Ok(())
+}
+
+pub(crate) fn finalize_series(ctx: Context<FinalizeSeries>, _series_id: String) -> Result<()> {
+    let series = &ctx.accounts.series;
+    require!(
+        matches!(series.status, GameStatus::Finished | GameStatus::Drawn),
+        GameError::GameNotFinished
+    );
+
+    if series.status == GameStatus::Drawn {
+        let series_info = series.to_account_info();
+        let (currency_type, stake_amount) = (series.currency_type, series.stake_amount);
+        let players = [
+            (&mut ctx.accounts.player1_profile, ctx.accounts.player1.to_account_info()),
+            (&mut ctx.accounts.player2_profile, ctx.accounts.player2.to_account_info()),
+        ];
+        for (profile, player) in players {
+            return_stake(&series_info, &player, profile, currency_type, stake_amount)?;
+            profile.draws += 1;
+            profile.total_games += 1;
+        }
+
+        msg!("Series {} finalized as a draw, stakes refunded", ctx.accounts.series.series_id);
+        return Ok(());
+    }
+
+    let winner_key = series.winner.ok_or(GameError::NoWinner)?;
+
+    let total_pot = series.stake_amount * 2;
+    let (winner_profile, loser_profile, winner_info) = if winner_key == series.player1 {
+        (
+            &mut ctx.accounts.player1_profile,
+            &mut ctx.accounts.player2_profile,
+            ctx.accounts.player1.to_account_info(),
+        )
+    } else {
+        (
+            &mut ctx.accounts.player2_profile,
+            &mut ctx.accounts.player1_profile,
+            ctx.accounts.player2.to_account_info(),
+        )
+    };
+
+    winner_profile.wins += 1;
+    winner_profile.total_games += 1;
+    loser_profile.losses += 1;
+    loser_profile.total_games += 1;
+
+    if series.currency_type == CurrencyType::Points {
+        unlock_points(winner_profile)?;
+        unlock_points(loser_profile)?;
+        winner_profile.points_balance += total_pot;
+        winner_profile.total_points_earned += total_pot;
+    } else if series.currency_type == CurrencyType::Sol {
+        let fee_rate = get_platform_fee_rate(series.stake_amount);
+        let total_fees = total_pot * fee_rate / 10000; // fee_rate is in basis points
+        let winner_amount = total_pot - total_fees;
+
+        let series_info = ctx.accounts.series.to_account_info();
+        move_lamports(&series_info, &ctx.accounts.platform_wallet.to_account_info(), total_fees)?;
+        move_lamports(&series_info, &winner_info, winner_amount)?;
+
+        winner_profile.total_points_earned += winner_amount;
+
+        msg!(
+            "Transferred {} lamports to series winner {}, platform fee {} lamports",
+            winner_amount,
+            winner_key,
+            total_fees
+        );
+    }
+
+    msg!(
+        "Series {} finalized! Winner: {}, Pot: {}",
+        ctx.accounts.series.series_id,
+        winner_key,
+        total_pot
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: String)]
+pub struct CreateSeries<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = 8 + (4 + 32) + 32 + 33 + 1 + 8 + 1 + 1 + 1 + 1 + 1 + 1 + 33 + 33 + 8 + 8 + 8, // discriminator + series data + game timeout
+        seeds = [b"series", series_id.as_bytes()],
+        bump
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: String)]
+pub struct JoinSeries<'info> {
+    #[account(
+        mut,
+        seeds = [b"series", series_id.as_bytes()],
+        bump
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: String)]
+pub struct CancelSeries<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"series", series_id.as_bytes()],
+        bump,
+        constraint = series.player1 == user.key() @ GameError::NotAPlayer
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: String, game_id: String)]
+pub struct StartSeriesGame<'info> {
+    #[account(
+        mut,
+        seeds = [b"series", series_id.as_bytes()],
+        bump
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(
+        init,
+        payer = user,
+        space = GAME_SPACE,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: String, game_id: String)]
+pub struct RecordSeriesGame<'info> {
+    #[account(
+        mut,
+        seeds = [b"series", series_id.as_bytes()],
+        bump
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(
+        mut,
+        close = player1,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    /// CHECK: Rent is returned to the child game's player1
+    #[account(mut, address = game.player1)]
+    pub player1: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(series_id: String)]
+pub struct FinalizeSeries<'info> {
+    #[account(
+        mut,
+        close = player1, // Return rent to the series creator once paid out
+        seeds = [b"series", series_id.as_bytes()],
+        bump
+    )]
+    pub series: Account<'info, Series>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", series.player1.as_ref()],
+        bump
+    )]
+    pub player1_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", series.player2.unwrap().as_ref()],
+        bump
+    )]
+    pub player2_profile: Account<'info, UserProfile>,
+
+    /// CHECK: This account is checked against the series' player1 field
+    #[account(mut, address = series.player1)]
+    pub player1: UncheckedAccount<'info>,
+
+    /// CHECK: This account is checked against the series' player2 field
+    #[account(mut, address = series.player2.unwrap())]
+    pub player2: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the platform wallet stored in config
+    #[account(mut, address = config.platform_wallet)]
+    pub platform_wallet: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+}
+
+#[account]
+pub struct Series {
+    pub series_id: String,
+    pub player1: Pubkey,
+    pub player2: Option<Pubkey>,
+    pub status: GameStatus,
+    pub stake_amount: u64, // Per-player stake for the whole series
+    pub currency_type: CurrencyType,
+    pub games_to_win: u8,
+    pub rounds_to_win: u8, // Passed on to every child game
+    pub games_played: u8,
+    pub player1_games_won: u8,
+    pub player2_games_won: u8,
+    pub current_game: Option<Pubkey>, // Child game being played
+    pub winner: Option<Pubkey>,
+    pub created_at: i64,
+    pub game_timeout: i64, // Seconds before an unfinished child game can be forfeited
+    pub game_started_at: i64, // When the current child game was started
+}
+
+/// Child games a series may end without a winner before the series is drawn
+pub const MAX_SERIES_UNDECIDED_GAMES: u8 = 3;
@@ -0,0 +1,659 @@
+//! 2v2 team games where each team's move is picked by its captain or by majority vote
+
+use crate::*;
+
+pub(crate) fn create_team_game(
+    ctx: Context<CreateTeamGame>,
+    game_id: String,
+    stake_amount: u64,
+    currency_type: CurrencyType,
+    rounds_to_win: u8,
+    decision: TeamDecision,
+    move_timeout: i64,
+) -> Result<()> {
+    require!(game_id.len() <= 32, GameError::GameIdTooLong);
+    require!(currency_type != CurrencyType::SplToken, GameError::UnsupportedCurrency);
+    require!(stake_amount > 0, GameError::InvalidStakeAmount);
+    require!(rounds_to_win > 0 && rounds_to_win <= 10, GameError::InvalidRoundsToWin);
+    require!(move_timeout > 0, GameError::InvalidTimeout);
+
+    collect_stake(
+        &ctx.accounts.team_game.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        currency_type,
+        stake_amount,
+    )?;
+
+    let team_game = &mut ctx.accounts.team_game;
+    team_game.game_id = game_id;
+    team_game.creator = ctx.accounts.user.key();
+    team_game.status = GameStatus::WaitingForPlayer;
+    team_game.stake_amount = stake_amount;
+    team_game.currency_type = currency_type;
+    team_game.rounds_to_win = rounds_to_win;
+    team_game.decision = decision;
+    team_game.teams = [[Pubkey::default(); TEAM_SIZE]; 2];
+    team_game.teams[0][0] = ctx.accounts.user.key();
+    team_game.captain_votes = [[Pubkey::default(); TEAM_SIZE]; 2];
+    team_game.captains = [None; 2];
+    team_game.commitments = [[None; TEAM_SIZE]; 2];
+    team_game.revealed_moves = [[None; TEAM_SIZE]; 2];
+    team_game.revealed_nonces = [[0; TEAM_SIZE]; 2];
+    team_game.current_round = 1;
+    team_game.team_rounds_won = [0; 2];
+    team_game.winning_team = None;
+    team_game.created_at = Clock::get()?.unix_timestamp;
+    team_game.phase = RoyalePhase::Commit;
+    team_game.move_timeout = move_timeout;
+    team_game.phase_deadline = 0;
+
+    msg!("Team game {} created by {}", team_game.game_id, team_game.creator);
+    Ok(())
+}
+
+pub(crate) fn join_team_game(ctx: Context<JoinTeamGame>, _game_id: String, team: u8) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let team_game = &ctx.accounts.team_game;
+
+    require!(team_game.status == GameStatus::WaitingForPlayer, GameError::GameNotJoinable);
+    require!((team as usize) < 2, GameError::InvalidTeam);
+    require!(team_seat(team_game, player).is_none(), GameError::AlreadyRegistered);
+    let seat = team_game.teams[team as usize]
+        .iter()
+        .position(|p| *p == Pubkey::default())
+        .ok_or(GameError::TeamFull)?;
+
+    let stake_amount = team_game.stake_amount;
+    collect_stake(
+        &ctx.accounts.team_game.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        team_game.currency_type,
+        stake_amount,
+    )?;
+
+    let team_game = &mut ctx.accounts.team_game;
+    team_game.teams[team as usize][seat] = player;
+    if team_game.teams.iter().flatten().all(|p| *p != Pubkey::default()) {
+        team_game.status = GameStatus::InProgress;
+        team_game.phase_deadline = Clock::get()?.unix_timestamp + team_game.move_timeout;
+    }
+
+    msg!("Player {} joined team {} in team game {}", player, team, team_game.game_id);
+    Ok(())
+}
+
+pub(crate) fn leave_team_game(ctx: Context<LeaveTeamGame>, _game_id: String) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let team_game = &ctx.accounts.team_game;
+
+    require!(team_game.status == GameStatus::WaitingForPlayer, GameError::GameAlreadyStarted);
+    require!(player != team_game.creator, GameError::CreatorCannotLeave);
+    let (team, seat) = team_seat(team_game, player).ok_or(GameError::NotAPlayer)?;
+
+    let stake_amount = team_game.stake_amount;
+    return_stake(
+        &ctx.accounts.team_game.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &mut ctx.accounts.user_profile,
+        team_game.currency_type,
+        stake_amount,
+    )?;
+
+    let team_game = &mut ctx.accounts.team_game;
+    team_game.teams[team][seat] = Pubkey::default();
+    team_game.captain_votes[team] = [Pubkey::default(); TEAM_SIZE];
+    team_game.captains[team] = None;
+
+    msg!("Player {} left team game {}", player, team_game.game_id);
+    Ok(())
+}
+
+pub(crate) fn cancel_team_game(ctx: Context<CancelTeamGame>, _game_id: String) -> Result<()> {
+    let team_game = &ctx.accounts.team_game;
+    require!(team_game.status == GameStatus::WaitingForPlayer, GameError::GameAlreadyStarted);
+    require!(
+        team_game.teams.iter().flatten().filter(|p| **p != Pubkey::default()).count() == 1,
+        GameError::PlayersStillJoined
+    );
+
+    let stake_amount = team_game.stake_amount;
+    return_stake(
+        &ctx.accounts.team_game.to_account_info(),
+        &ctx.accounts.user.to_account_info(),
+        &mut ctx.accounts.user_profile,
+        team_game.currency_type,
+        stake_amount,
+    )?;
+
+    msg!("Team game {} cancelled. Refunding {} to creator {}.", team_game.game_id, stake_amount, team_game.creator);
+    Ok(())
+}
+
+pub(crate) fn vote_captain(ctx: Context<VoteCaptain>, _game_id: String, candidate: Pubkey) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let team_game = &mut ctx.accounts.team_game;
+
+    require!(team_game.decision == TeamDecision::Captain, GameError::WrongDecisionMode);
+    require!(
+        team_game.status == GameStatus::WaitingForPlayer || team_game.status == GameStatus::InProgress,
+        GameError::GameNotInProgress
+    );
+    let (team, seat) = team_seat(team_game, player).ok_or(GameError::NotAPlayer)?;
+    require!(team_game.captains[team].is_none(), GameError::CaptainAlreadyElected);
+    require!(team_game.teams[team].contains(&candidate), GameError::NotATeammate);
+
+    team_game.captain_votes[team][seat] = candidate;
+    if team_game.captain_votes[team].iter().all(|vote| *vote == candidate)
+        && team_game.teams[team].iter().all(|p| *p != Pubkey::default())
+    {
+        team_game.captains[team] = Some(candidate);
+        msg!("Team {} elected captain {} in team game {}", team, candidate, team_game.game_id);
+    }
+    Ok(())
+}
+
+pub(crate) fn commit_team_move(
+    ctx: Context<CommitTeamMove>,
+    _game_id: String,
+    move_commitment: [u8; 32],
+) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let team_game = &mut ctx.accounts.team_game;
+
+    require!(team_game.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(team_game.phase == RoyalePhase::Commit, GameError::WrongPhase);
+    let (team, seat) = team_seat(team_game, player).ok_or(GameError::NotAPlayer)?;
+    require!(team_voter(team_game, team, seat)?, GameError::NotTeamCaptain);
+    require!(team_game.commitments[team][seat].is_none(), GameError::MoveAlreadyCommitted);
+
+    team_game.commitments[team][seat] = Some(move_commitment);
+
+    msg!("Move committed for player {} (team {}) in team game {}", player, team, team_game.game_id);
+    if team_acted(team_game, 0) && team_acted(team_game, 1) {
+        team_game.phase = RoyalePhase::Reveal;
+        team_game.phase_deadline = Clock::get()?.unix_timestamp + team_game.move_timeout;
+        msg!("Team game {} round {}: all votes committed", team_game.game_id, team_game.current_round);
+    }
+    Ok(())
+}
+
+pub(crate) fn reveal_team_move(
+    ctx: Context<RevealTeamMove>,
+    _game_id: String,
+    player_move: Move,
+    nonce: u64,
+) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let team_game = &mut ctx.accounts.team_game;
+
+    require!(team_game.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(team_game.phase == RoyalePhase::Reveal, GameError::WrongPhase);
+    let (team, seat) = team_seat(team_game, player).ok_or(GameError::NotAPlayer)?;
+    require!(team_game.revealed_moves[team][seat].is_none(), GameError::MoveAlreadyRevealed);
+    let commitment = team_game.commitments[team][seat].ok_or(GameError::MoveNotCommitted)?;
+    require!(commitment == hash_move(player_move, nonce), GameError::InvalidCommitment);
+
+    team_game.revealed_moves[team][seat] = Some(player_move);
+    team_game.revealed_nonces[team][seat] = nonce;
+
+    let (Some(team0_move), Some(team1_move)) = (team_move(team_game, 0)?, team_move(team_game, 1)?) else {
+        return Ok(());
+    };
+
+    match determine_winner(team0_move, team1_move) {
+        RoundResult::Player1Win => {
+            team_game.team_rounds_won[0] += 1;
+            msg!("Round {} won by team 0", team_game.current_round);
+        }
+        RoundResult::Player2Win => {
+            team_game.team_rounds_won[1] += 1;
+            msg!("Round {} won by team 1", team_game.current_round);
+        }
+        RoundResult::Draw => {
+            msg!("Round {} is a draw", team_game.current_round);
+        }
+    }
+
+    if let Some(winning_team) = team_game
+        .team_rounds_won
+        .iter()
+        .position(|won| *won >= team_game.rounds_to_win)
+    {
+        team_game.status = GameStatus::Finished;
+        team_game.winning_team = Some(winning_team as u8);
+        msg!("Team game {} completed! Winner: team {}", team_game.game_id, winning_team);
+    } else {
+        team_game.current_round += 1;
+        team_game.commitments = [[None; TEAM_SIZE]; 2];
+        team_game.revealed_moves = [[None; TEAM_SIZE]; 2];
+        team_game.revealed_nonces = [[0; TEAM_SIZE]; 2];
+        team_game.phase = RoyalePhase::Commit;
+        team_game.phase_deadline = Clock::get()?.unix_timestamp + team_game.move_timeout;
+    }
+    Ok(())
+}
+
+pub(crate) fn resolve_team_game_timeout(ctx: Context<ResolveTeamGameTimeout>, _game_id: String) -> Result<()> {
+    let team_game = &mut ctx.accounts.team_game;
+    require!(team_game.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(
+        Clock::get()?.unix_timestamp >= team_game.phase_deadline,
+        GameError::TimeoutNotReached
+    );
+
+    match (team_acted(team_game, 0), team_acted(team_game, 1)) {
+        (true, false) | (false, true) => {
+            let winning_team = if team_acted(team_game, 0) { 0 } else { 1 };
+            team_game.status = GameStatus::Finished;
+            team_game.winning_team = Some(winning_team);
+            msg!("Team game {} forfeited on timeout to team {}", team_game.game_id, winning_team);
+        }
+        (false, false) => {
+            team_game.status = GameStatus::Drawn;
+            msg!("Team game {}: neither team acted, stakes will be returned", team_game.game_id);
+        }
+        // Both teams acting moves the game on by itself
+        (true, true) => return err!(GameError::TimeoutNotReached),
+    }
+    Ok(())
+}
+
+pub(crate) fn finalize_team_game(ctx: Context<FinalizeTeamGame>, _game_id: String) -> Result<()> {
+    let team_game = &ctx.accounts.team_game;
+    require!(
+        team_game.status == GameStatus::Finished || team_game.status == GameStatus::Drawn,
+        GameError::GameNotFinished
+    );
+    let winning_team = team_game.winning_team.map(|team| team as usize);
+    let currency_type = team_game.currency_type;
+
+    let total_pot = team_game.stake_amount * (2 * TEAM_SIZE) as u64;
+    let mut total_fees = 0;
+    let share = if winning_team.is_some() {
+        if currency_type == CurrencyType::Sol {
+            let fee_rate = get_platform_fee_rate(team_game.stake_amount);
+            total_fees = total_pot * fee_rate / 10000; // fee_rate is in basis points
+        }
+        let share = (total_pot - total_fees) / TEAM_SIZE as u64;
+        // Rounding dust goes to the platform along with the fee
+        total_fees = total_pot - share * TEAM_SIZE as u64;
+        share
+    } else {
+        team_game.stake_amount
+    };
+
+    let profiles = [
+        &mut ctx.accounts.team0_player1_profile,
+        &mut ctx.accounts.team0_player2_profile,
+        &mut ctx.accounts.team1_player1_profile,
+        &mut ctx.accounts.team1_player2_profile,
+    ];
+    let wallets = [
+        ctx.accounts.team0_player1.to_account_info(),
+        ctx.accounts.team0_player2.to_account_info(),
+        ctx.accounts.team1_player1.to_account_info(),
+        ctx.accounts.team1_player2.to_account_info(),
+    ];
+    let team_game_info = ctx.accounts.team_game.to_account_info();
+    for (index, (profile, wallet)) in profiles.into_iter().zip(wallets).enumerate() {
+        if currency_type == CurrencyType::Points {
+            unlock_points(profile)?;
+        }
+        profile.total_games += 1;
+        let payout = match winning_team {
+            Some(team) if index / TEAM_SIZE == team => {
+                profile.wins += 1;
+                profile.total_points_earned += share;
+                share
+            }
+            Some(_) => {
+                profile.losses += 1;
+                0
+            }
+            None => {
+                profile.draws += 1;
+                share
+            }
+        };
+
+        if currency_type == CurrencyType::Points {
+            profile.points_balance += payout;
+        } else if currency_type == CurrencyType::Sol && payout > 0 {
+            move_lamports(&team_game_info, &wallet, payout)?;
+        }
+    }
+
+    if currency_type == CurrencyType::Sol && total_fees > 0 {
+        move_lamports(&team_game_info, &ctx.accounts.platform_wallet.to_account_info(), total_fees)?;
+    }
+
+    match winning_team {
+        Some(team) => msg!(
+            "Team game {} finalized! Winner: team {}, {} to each member, Pot: {}",
+            ctx.accounts.team_game.game_id,
+            team,
+            share,
+            total_pot
+        ),
+        None => msg!("Team game {} finalized as a draw, stakes returned", ctx.accounts.team_game.game_id),
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CreateTeamGame<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = TEAM_GAME_SPACE,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct JoinTeamGame<'info> {
+    #[account(
+        mut,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct LeaveTeamGame<'info> {
+    #[account(
+        mut,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CancelTeamGame<'info> {
+    #[account(
+        mut,
+        close = user,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump,
+        constraint = team_game.creator == user.key() @ GameError::NotAPlayer
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct VoteCaptain<'info> {
+    #[account(
+        mut,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct CommitTeamMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct RevealTeamMove<'info> {
+    #[account(
+        mut,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct ResolveTeamGameTimeout<'info> {
+    #[account(
+        mut,
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(game_id: String)]
+pub struct FinalizeTeamGame<'info> {
+    #[account(
+        mut,
+        close = creator, // Return rent to the creator once paid out
+        seeds = [b"team_game", game_id.as_bytes()],
+        bump
+    )]
+    pub team_game: Account<'info, TeamGame>,
+
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", team_game.teams[0][0].as_ref()],
+        bump
+    )]
+    pub team0_player1_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", team_game.teams[0][1].as_ref()],
+        bump
+    )]
+    pub team0_player2_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", team_game.teams[1][0].as_ref()],
+        bump
+    )]
+    pub team1_player1_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", team_game.teams[1][1].as_ref()],
+        bump
+    )]
+    pub team1_player2_profile: Account<'info, UserProfile>,
+
+    /// CHECK: This account is checked against the first seat of team 0
+    #[account(mut, address = team_game.teams[0][0])]
+    pub team0_player1: UncheckedAccount<'info>,
+
+    /// CHECK: This account is checked against the second seat of team 0
+    #[account(mut, address = team_game.teams[0][1])]
+    pub team0_player2: UncheckedAccount<'info>,
+
+    /// CHECK: This account is checked against the first seat of team 1
+    #[account(mut, address = team_game.teams[1][0])]
+    pub team1_player1: UncheckedAccount<'info>,
+
+    /// CHECK: This account is checked against the second seat of team 1
+    #[account(mut, address = team_game.teams[1][1])]
+    pub team1_player2: UncheckedAccount<'info>,
+
+    /// CHECK: This account is checked against the team game's creator field
+    #[account(mut, address = team_game.creator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Checked against the platform wallet stored in config
+    #[account(mut, address = config.platform_wallet)]
+    pub platform_wallet: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+}
+
+pub const TEAM_SIZE: usize = 2;
+
+// discriminator + settings + seats + captain votes + captains + per-seat commitments/moves/nonces + round state + created_at + phase timing
+pub const TEAM_GAME_SPACE: usize = 8
+    + (4 + 32) + 32 + 1 + 8 + 1 + 1 + 1
+    + 32 * 2 * TEAM_SIZE
+    + 32 * 2 * TEAM_SIZE
+    + 33 * 2
+    + 33 * 2 * TEAM_SIZE
+    + 2 * 2 * TEAM_SIZE
+    + 8 * 2 * TEAM_SIZE
+    + 1 + 2 + 2 + 8
+    + 1 + 8 + 8;
+
+/// 2v2 game. Team 0 plays as player 1 in `determine_winner`.
+#[account]
+pub struct TeamGame {
+    pub game_id: String,
+    pub creator: Pubkey,
+    pub status: GameStatus,
+    pub stake_amount: u64, // Per-player stake
+    pub currency_type: CurrencyType,
+    pub rounds_to_win: u8,
+    pub decision: TeamDecision,
+    pub teams: [[Pubkey; TEAM_SIZE]; 2], // Default pubkey = free seat
+    pub captain_votes: [[Pubkey; TEAM_SIZE]; 2],
+    pub captains: [Option<Pubkey>; 2],
+    pub commitments: [[Option<[u8; 32]>; TEAM_SIZE]; 2],
+    pub revealed_moves: [[Option<Move>; TEAM_SIZE]; 2],
+    pub revealed_nonces: [[u64; TEAM_SIZE]; 2], // Seed the split-vote tiebreak
+    pub current_round: u8,
+    pub team_rounds_won: [u8; 2],
+    pub winning_team: Option<u8>,
+    pub created_at: i64,
+    pub phase: RoyalePhase, // Votes are all committed before any is revealed
+    pub move_timeout: i64, // Seconds allowed for each commit or reveal phase
+    pub phase_deadline: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TeamDecision {
+    Captain,      // The elected captain's move is the team move
+    MajorityVote, // Members vote; a split vote is broken by a committed coin flip
+}
+
+/// Team index and seat of a team game member
+pub fn team_seat(team_game: &TeamGame, player: Pubkey) -> Option<(usize, usize)> {
+    team_game.teams.iter().enumerate().find_map(|(team, seats)| {
+        seats.iter().position(|p| *p == player).map(|seat| (team, seat))
+    })
+}
+
+/// Whether the member at (team, seat) casts a move vote this round
+pub fn team_voter(team_game: &TeamGame, team: usize, seat: usize) -> Result<bool> {
+    match team_game.decision {
+        TeamDecision::Captain => {
+            let captain = team_game.captains[team].ok_or(GameError::CaptainNotElected)?;
+            Ok(captain == team_game.teams[team][seat])
+        }
+        TeamDecision::MajorityVote => Ok(true),
+    }
+}
+
+/// Whether every voter of `team` has committed (commit phase) or revealed
+/// (reveal phase) this round. A team still without a captain has not.
+pub fn team_acted(team_game: &TeamGame, team: usize) -> bool {
+    (0..TEAM_SIZE).all(|seat| match team_voter(team_game, team, seat) {
+        Ok(true) => match team_game.phase {
+            RoyalePhase::Commit => team_game.commitments[team][seat].is_some(),
+            RoyalePhase::Reveal => team_game.revealed_moves[team][seat].is_some(),
+        },
+        Ok(false) => true,
+        Err(_) => false,
+    })
+}
+
+/// The team's move for the current round, or None while votes are missing.
+/// The most common vote wins; a tie is broken by a coin flip seeded with the
+/// voters' nonces, which were fixed when the votes were committed.
+pub fn team_move(team_game: &TeamGame, team: usize) -> Result<Option<Move>> {
+    let mut counts = [0u8; 3];
+    let mut seed = Vec::with_capacity(8 * TEAM_SIZE);
+    for seat in 0..TEAM_SIZE {
+        if team_voter(team_game, team, seat)? {
+            match team_game.revealed_moves[team][seat] {
+                Some(vote) => counts[vote as usize] += 1,
+                None => return Ok(None),
+            }
+            seed.extend_from_slice(&team_game.revealed_nonces[team][seat].to_le_bytes());
+        }
+    }
+
+    let top = *counts.iter().max().unwrap();
+    let tied: Vec<Move> = [Move::Rock, Move::Paper, Move::Scissors]
+        .into_iter()
+        .filter(|vote| counts[*vote as usize] == top)
+        .collect();
+    let pick = anchor_lang::solana_program::hash::hash(&seed).to_bytes()[0] as usize % tied.len();
+    Ok(Some(tied[pick]))
+}
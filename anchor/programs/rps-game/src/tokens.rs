@@ -0,0 +1,330 @@
+//! SPL token and Token-2022 stakes: allowed mints, fee tiers and the per-game escrow
+
+use crate::*;
+use anchor_spl::token_2022::spl_token_2022;
+use spl_token_2022::extension::default_account_state::DefaultAccountState;
+use spl_token_2022::extension::transfer_fee::TransferFeeAmount;
+use spl_token_2022::extension::transfer_hook::TransferHook;
+use spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+
+pub(crate) fn add_allowed_mint(
+    ctx: Context<AddAllowedMint>,
+    fee_tiers: [FeeTier; 3],
+    transfer_hook_program: Option<Pubkey>,
+) -> Result<()> {
+    validate_fee_tiers(&fee_tiers)?;
+    validate_mint_extensions(&ctx.accounts.mint, transfer_hook_program)?;
+
+    let allowed_mint = &mut ctx.accounts.allowed_mint;
+    allowed_mint.mint = ctx.accounts.mint.key();
+    allowed_mint.enabled = true;
+    allowed_mint.fee_tiers = fee_tiers;
+    allowed_mint.transfer_hook_program = transfer_hook_program;
+
+    msg!("Mint {} allowed for token games", allowed_mint.mint);
+    Ok(())
+}
+
+pub(crate) fn update_allowed_mint(
+    ctx: Context<UpdateAllowedMint>,
+    enabled: bool,
+    fee_tiers: [FeeTier; 3],
+    transfer_hook_program: Option<Pubkey>,
+) -> Result<()> {
+    validate_fee_tiers(&fee_tiers)?;
+    validate_mint_extensions(&ctx.accounts.mint, transfer_hook_program)?;
+
+    let allowed_mint = &mut ctx.accounts.allowed_mint;
+    allowed_mint.enabled = enabled;
+    allowed_mint.fee_tiers = fee_tiers;
+    allowed_mint.transfer_hook_program = transfer_hook_program;
+
+    msg!("Mint {} updated, enabled: {}", allowed_mint.mint, enabled);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AddAllowedMint<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + 32 + 1 + 3 * (8 + 2) + 33, // discriminator + mint + enabled + fee tiers + transfer hook program
+        seeds = [b"allowed_mint", mint.key().as_ref()],
+        bump
+    )]
+    pub allowed_mint: Account<'info, AllowedMint>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAllowedMint<'info> {
+    #[account(mut, seeds = [b"allowed_mint", mint.key().as_ref()], bump)]
+    pub allowed_mint: Account<'info, AllowedMint>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+/// Admin allowlist entry for a mint usable in SPL token games
+#[account]
+pub struct AllowedMint {
+    pub mint: Pubkey,
+    pub enabled: bool,
+    pub fee_tiers: [FeeTier; 3], // Checked in order; stakes above every tier use the last one
+    pub transfer_hook_program: Option<Pubkey>, // Approved Token-2022 transfer hook, if any
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct FeeTier {
+    pub max_stake: u64, // In the mint's base units
+    pub fee_bps: u16,
+}
+
+/// Token counterpart of `refund_both_stakes`: each player gets back half of what the
+/// escrow holds, less `fee_bps`, and the escrow is closed to player1. Tokenized points
+/// games are fee free. The fees, and any unit left over by transfer fees, go to the
+/// platform's token account.
+#[allow(clippy::too_many_arguments)]
+pub fn refund_both_token_stakes<'info>(
+    game: &Account<'info, Game>,
+    game_bump: u8,
+    mint: Option<&InterfaceAccount<'info, Mint>>,
+    escrow: Option<&InterfaceAccount<'info, TokenAccount>>,
+    player1_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    player2_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    platform_token_account: Option<&InterfaceAccount<'info, TokenAccount>>,
+    token_program: Option<&Interface<'info, TokenInterface>>,
+    player1: &AccountInfo<'info>,
+    platform_wallet: &AccountInfo<'info>,
+    extra_accounts: &[AccountInfo<'info>],
+    fee_bps: u16,
+) -> Result<u64> {
+    let mint = mint.ok_or(GameError::MissingTokenAccounts)?;
+    let escrow = escrow.ok_or(GameError::MissingTokenAccounts)?;
+    let player1_token_account = player1_token_account.ok_or(GameError::MissingTokenAccounts)?;
+    let player2_token_account = player2_token_account.ok_or(GameError::MissingTokenAccounts)?;
+    let token_program = token_program.ok_or(GameError::MissingTokenAccounts)?;
+    require!(game.mint == Some(mint.key()), GameError::MintMismatch);
+    require!(player1_token_account.owner == game.player1, GameError::InvalidTokenAccount);
+    require!(Some(player2_token_account.owner) == game.player2, GameError::InvalidTokenAccount);
+
+    let fee_bps = if game.currency_type == CurrencyType::Points { 0 } else { fee_bps };
+    let half = escrow.amount / 2;
+    let fee_per_player = token_fee(half, fee_bps);
+    let refund_amount = half - fee_per_player;
+    let platform_amount = escrow.amount - refund_amount * 2;
+
+    withdraw_tokens(game, game_bump, escrow, mint, &player1_token_account.to_account_info(), token_program, extra_accounts, refund_amount)?;
+    withdraw_tokens(game, game_bump, escrow, mint, &player2_token_account.to_account_info(), token_program, extra_accounts, refund_amount)?;
+    if platform_amount > 0 {
+        let platform_token_account = platform_token_account.ok_or(GameError::MissingTokenAccounts)?;
+        require!(platform_token_account.owner == platform_wallet.key(), GameError::InvalidTokenAccount);
+        withdraw_tokens(game, game_bump, escrow, mint, &platform_token_account.to_account_info(), token_program, extra_accounts, platform_amount)?;
+    }
+
+    close_escrow(game, game_bump, escrow, mint, player1, token_program)?;
+    Ok(fee_per_player)
+}
+
+pub fn validate_fee_tiers(fee_tiers: &[FeeTier; 3]) -> Result<()> {
+    require!(
+        fee_tiers.iter().all(|tier| tier.fee_bps <= MAX_FEE_BPS),
+        GameError::InvalidFeeTiers
+    );
+    require!(
+        fee_tiers.windows(2).all(|pair| pair[0].max_stake <= pair[1].max_stake),
+        GameError::InvalidFeeTiers
+    );
+    Ok(())
+}
+
+/// Fee in basis points for a token game, picked from the mint's tiers like `get_platform_fee_rate`
+pub fn token_fee_bps(allowed_mint: &AllowedMint, stake_amount: u64) -> u16 {
+    allowed_mint
+        .fee_tiers
+        .iter()
+        .find(|tier| stake_amount <= tier.max_stake)
+        .unwrap_or(&allowed_mint.fee_tiers[2])
+        .fee_bps
+}
+
+/// `fee_bps` of a token amount. Raw token amounts can use the whole u64 range, so
+/// the product is taken in u128; the result never exceeds `amount`.
+pub fn token_fee(amount: u64, fee_bps: u16) -> u64 {
+    (amount as u128 * fee_bps as u128 / 10000) as u64
+}
+
+/// Refuse mints whose authorities could freeze or seize escrowed tokens.
+/// Classic mints only need the freeze authority check. Token-2022 extensions are
+/// allowlisted: extensions this build does not know (Pausable among them) fail to
+/// parse and the mint is refused with them. A transfer hook must be the approved one.
+pub fn validate_mint_extensions(
+    mint: &InterfaceAccount<Mint>,
+    approved_transfer_hook: Option<Pubkey>,
+) -> Result<()> {
+    require!(mint.freeze_authority.is_none(), GameError::HostileMint);
+    let info = mint.to_account_info();
+    if *info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    let extensions = state.get_extension_types().map_err(|_| error!(GameError::HostileMint))?;
+    for extension in extensions {
+        match extension {
+            ExtensionType::TransferFeeConfig
+            | ExtensionType::MintCloseAuthority
+            | ExtensionType::InterestBearingConfig
+            | ExtensionType::MetadataPointer
+            | ExtensionType::TokenMetadata
+            | ExtensionType::GroupPointer
+            | ExtensionType::TokenGroup
+            | ExtensionType::GroupMemberPointer
+            | ExtensionType::TokenGroupMember => {}
+            ExtensionType::DefaultAccountState => {
+                let default_state = state.get_extension::<DefaultAccountState>()?.state;
+                require!(
+                    default_state != spl_token_2022::state::AccountState::Frozen as u8,
+                    GameError::HostileMint
+                );
+            }
+            ExtensionType::TransferHook => {
+                let hook_program: Option<Pubkey> = state.get_extension::<TransferHook>()?.program_id.into();
+                require!(
+                    hook_program.is_none() || hook_program == approved_transfer_hook,
+                    GameError::TransferHookNotAllowed
+                );
+            }
+            _ => return err!(GameError::HostileMint),
+        }
+    }
+    Ok(())
+}
+
+/// Token balance read straight from account data, for use after a CPI
+pub fn token_balance(info: &AccountInfo) -> Result<u64> {
+    let data = info.try_borrow_data()?;
+    Ok(StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?.base.amount)
+}
+
+/// Move `amount` tokens from a player's token account into a game escrow.
+/// Returns what the escrow actually received after any Token-2022 transfer fee.
+/// `extra_accounts` carries the accounts an approved transfer hook needs.
+pub fn deposit_tokens<'info>(
+    from: &InterfaceAccount<'info, TokenAccount>,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &Signer<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    extra_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<u64> {
+    let escrow_info = escrow.to_account_info();
+    let before = token_balance(&escrow_info)?;
+    spl_token_2022::onchain::invoke_transfer_checked(
+        token_program.key,
+        from.to_account_info(),
+        mint.to_account_info(),
+        escrow_info.clone(),
+        authority.to_account_info(),
+        extra_accounts,
+        amount,
+        mint.decimals,
+        &[],
+    )?;
+    Ok(token_balance(&escrow_info)? - before)
+}
+
+/// Pay `amount` tokens out of a game escrow, signed by the game PDA
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_tokens<'info>(
+    game: &Account<'info, Game>,
+    game_bump: u8,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    extra_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[b"game", game.game_id.as_bytes(), &[game_bump]];
+    spl_token_2022::onchain::invoke_transfer_checked(
+        token_program.key,
+        escrow.to_account_info(),
+        mint.to_account_info(),
+        to.clone(),
+        game.to_account_info(),
+        extra_accounts,
+        amount,
+        mint.decimals,
+        &[seeds],
+    )?;
+    Ok(())
+}
+
+/// Close an emptied game escrow, sending its rent to `destination`. Transfer fees
+/// withheld in a Token-2022 escrow are first harvested to the mint, as an account
+/// holding withheld fees cannot be closed.
+pub fn close_escrow<'info>(
+    game: &Account<'info, Game>,
+    game_bump: u8,
+    escrow: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    destination: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    let escrow_info = escrow.to_account_info();
+    let has_withheld_fees = {
+        let data = escrow_info.try_borrow_data()?;
+        let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+        state
+            .get_extension::<TransferFeeAmount>()
+            .map(|fees| u64::from(fees.withheld_amount) > 0)
+            .unwrap_or(false)
+    };
+    if has_withheld_fees {
+        token_interface::harvest_withheld_tokens_to_mint(
+            CpiContext::new(
+                token_program.to_account_info(),
+                token_interface::HarvestWithheldTokensToMint {
+                    token_program_id: token_program.to_account_info(),
+                    mint: mint.to_account_info(),
+                },
+            ),
+            vec![escrow_info],
+        )?;
+    }
+
+    let seeds: &[&[u8]] = &[b"game", game.game_id.as_bytes(), &[game_bump]];
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: escrow.to_account_info(),
+            destination: destination.clone(),
+            authority: game.to_account_info(),
+        },
+        &[seeds],
+    ))
+}
@@ -0,0 +1,569 @@
+//! Single-elimination tournaments with entry fees and a split prize pool
+
+use crate::*;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_tournament(
+    ctx: Context<CreateTournament>,
+    tournament_id: String,
+    currency_type: CurrencyType,
+    entry_fee: u64,
+    max_players: u8,
+    registration_ends_at: i64,
+    match_timeout: i64,
+    rounds_to_win_by_stage: [u8; 4],
+    prize_split_bps: [u16; 3],
+) -> Result<()> {
+    require!(tournament_id.len() <= 32, GameError::GameIdTooLong);
+    require!(currency_type != CurrencyType::SplToken, GameError::UnsupportedCurrency);
+    require!(entry_fee > 0, GameError::InvalidStakeAmount);
+    require!(
+        max_players >= 2 && max_players as usize <= MAX_TOURNAMENT_PLAYERS,
+        GameError::InvalidMaxPlayers
+    );
+    require!(
+        registration_ends_at > Clock::get()?.unix_timestamp,
+        GameError::InvalidTimestamp
+    );
+    require!(match_timeout > 0, GameError::InvalidTimeout);
+    require!(
+        rounds_to_win_by_stage.iter().all(|r| *r > 0 && *r <= 10),
+        GameError::InvalidRoundsToWin
+    );
+    require!(
+        prize_split_bps.iter().map(|bps| *bps as u64).sum::<u64>() == 10000,
+        GameError::InvalidPrizeSplit
+    );
+
+    let tournament = &mut ctx.accounts.tournament;
+    tournament.tournament_id = tournament_id;
+    tournament.organizer = ctx.accounts.admin.key();
+    tournament.status = GameStatus::WaitingForPlayer;
+    tournament.currency_type = currency_type;
+    tournament.entry_fee = entry_fee;
+    tournament.max_players = max_players;
+    tournament.registration_ends_at = registration_ends_at;
+    tournament.match_timeout = match_timeout;
+    tournament.rounds_to_win_by_stage = rounds_to_win_by_stage;
+    tournament.prize_split_bps = prize_split_bps;
+    tournament.players = Vec::new();
+    tournament.bracket_size = 0;
+    tournament.bracket = [Pubkey::default(); TOURNAMENT_BRACKET_SLOTS];
+    tournament.match_games = [Pubkey::default(); TOURNAMENT_MATCHES];
+    tournament.match_started_at = [0; TOURNAMENT_MATCHES];
+    tournament.prize_pool = 0;
+    tournament.claimed = 0;
+    tournament.payouts_remaining = 0;
+
+    msg!("Tournament {} created, registration ends at {}", tournament.tournament_id, registration_ends_at);
+    Ok(())
+}
+
+pub(crate) fn register_tournament(ctx: Context<RegisterTournament>, _tournament_id: String) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let tournament = &ctx.accounts.tournament;
+
+    require!(tournament.status == GameStatus::WaitingForPlayer, GameError::RegistrationClosed);
+    require!(
+        Clock::get()?.unix_timestamp < tournament.registration_ends_at,
+        GameError::RegistrationClosed
+    );
+    require!(
+        tournament.players.len() < tournament.max_players as usize,
+        GameError::TournamentFull
+    );
+    require!(!tournament.players.contains(&player), GameError::AlreadyRegistered);
+
+    let entry_fee = tournament.entry_fee;
+    collect_stake(
+        &ctx.accounts.tournament.to_account_info(),
+        &ctx.accounts.user,
+        &mut ctx.accounts.user_profile,
+        &ctx.accounts.system_program,
+        tournament.currency_type,
+        entry_fee,
+    )?;
+
+    let tournament = &mut ctx.accounts.tournament;
+    tournament.players.push(player);
+    tournament.prize_pool += entry_fee;
+
+    msg!(
+        "Player {} registered for tournament {} ({}/{})",
+        player,
+        tournament.tournament_id,
+        tournament.players.len(),
+        tournament.max_players
+    );
+    Ok(())
+}
+
+pub(crate) fn start_tournament(ctx: Context<StartTournament>, _tournament_id: String) -> Result<()> {
+    let tournament = &mut ctx.accounts.tournament;
+    require!(tournament.status == GameStatus::WaitingForPlayer, GameError::GameAlreadyStarted);
+    require!(
+        tournament.players.len() == tournament.max_players as usize
+            || Clock::get()?.unix_timestamp >= tournament.registration_ends_at,
+        GameError::RegistrationOpen
+    );
+
+    let player_count = tournament.players.len();
+    if player_count < 2 {
+        tournament.status = GameStatus::Cancelled;
+        tournament.payouts_remaining = player_count as u8;
+        msg!("Tournament {} cancelled: not enough players", tournament.tournament_id);
+        return Ok(());
+    }
+
+    let size = player_count.next_power_of_two();
+    let first_leaf = size - 1;
+    // Fill the first seat of every first-round match before any second seat,
+    // so every first-round match has at least one player
+    for (index, player) in tournament.players.clone().into_iter().enumerate() {
+        let seat = if index < size / 2 {
+            2 * index
+        } else {
+            2 * (index - size / 2) + 1
+        };
+        tournament.bracket[first_leaf + seat] = player;
+    }
+
+    // Players with an empty second seat advance on a bye
+    for node in (size / 2 - 1)..first_leaf {
+        if tournament.bracket[2 * node + 2] == Pubkey::default() {
+            tournament.bracket[node] = tournament.bracket[2 * node + 1];
+        }
+    }
+
+    tournament.bracket_size = size as u8;
+    tournament.status = GameStatus::InProgress;
+
+    msg!("Tournament {} started with {} players", tournament.tournament_id, player_count);
+    Ok(())
+}
+
+pub(crate) fn start_tournament_match(
+    ctx: Context<StartTournamentMatch>,
+    _tournament_id: String,
+    node: u8,
+    game_id: String,
+) -> Result<()> {
+    require!(game_id.len() <= 32, GameError::GameIdTooLong);
+
+    let tournament = &ctx.accounts.tournament;
+    let node = node as usize;
+    require!(tournament.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(node + 1 < tournament.bracket_size as usize, GameError::InvalidBracketNode);
+    require!(tournament.bracket[node] == Pubkey::default(), GameError::MatchAlreadyDecided);
+    require!(tournament.match_games[node] == Pubkey::default(), GameError::MatchAlreadyStarted);
+
+    let player1 = tournament.bracket[2 * node + 1];
+    let player2 = tournament.bracket[2 * node + 2];
+    require!(
+        player1 != Pubkey::default() && player2 != Pubkey::default(),
+        GameError::MatchNotReady
+    );
+
+    let tournament_key = tournament.key();
+    let rounds_to_win = tournament.rounds_to_win_by_stage[bracket_stage(node)];
+    let game = &mut ctx.accounts.game;
+    init_game(
+        game,
+        game_id,
+        player1,
+        Some(player2),
+        0,
+        tournament.currency_type,
+        rounds_to_win,
+    )?;
+    game.parent = Some(tournament_key);
+
+    let game_key = game.key();
+    let tournament = &mut ctx.accounts.tournament;
+    tournament.match_games[node] = game_key;
+    tournament.match_started_at[node] = Clock::get()?.unix_timestamp;
+
+    msg!(
+        "Tournament {} match {} started: {} vs {}",
+        tournament.tournament_id,
+        node,
+        player1,
+        player2
+    );
+    Ok(())
+}
+
+pub(crate) fn record_tournament_match(
+    ctx: Context<RecordTournamentMatch>,
+    _tournament_id: String,
+    node: u8,
+    _game_id: String,
+) -> Result<()> {
+    let node = node as usize;
+    let game_key = ctx.accounts.game.key();
+    let tournament = &mut ctx.accounts.tournament;
+    let game = &mut ctx.accounts.game;
+
+    require!(tournament.status == GameStatus::InProgress, GameError::GameNotInProgress);
+    require!(
+        node < TOURNAMENT_MATCHES && tournament.match_games[node] == game_key,
+        GameError::NotCurrentGame
+    );
+    require!(game.parent == Some(tournament.key()), GameError::NotCurrentGame);
+    require!(tournament.bracket[node] == Pubkey::default(), GameError::MatchAlreadyDecided);
+
+    match game.game_status {
+        GameStatus::Finished => {
+            tournament.bracket[node] = game.winner.ok_or(GameError::NoWinner)?;
+        }
+        GameStatus::Drawn | GameStatus::Cancelled => {
+            tournament.match_games[node] = Pubkey::default();
+            msg!("Tournament {} match {} ended without a winner and will be replayed", tournament.tournament_id, node);
+            return Ok(());
+        }
+        _ => {
+            require!(
+                Clock::get()?.unix_timestamp
+                    >= tournament.match_started_at[node] + tournament.match_timeout,
+                GameError::GameNotFinished
+            );
+
+            let winner = forfeit_winner(game);
+            game.winner = Some(winner);
+            game.game_status = GameStatus::Abandoned;
+            tournament.bracket[node] = winner;
+            msg!("Tournament {} match {} forfeited on timeout", tournament.tournament_id, node);
+        }
+    }
+
+    msg!("Tournament {} match {} won by {}", tournament.tournament_id, node, tournament.bracket[node]);
+
+    // The loser's entry is settled now, and the final also ends the champion's run
+    if tournament.currency_type == CurrencyType::Points {
+        let loser = tournament_loser(tournament, node);
+        for (player, profile) in [
+            (game.player1, &mut ctx.accounts.player1_profile),
+            (game.player2.unwrap_or_default(), &mut ctx.accounts.player2_profile),
+        ] {
+            if player == loser || node == 0 {
+                unlock_points(profile)?;
+            }
+        }
+    }
+
+    if node == 0 {
+        // Champion, runner-up and both semifinal losers (when they exist) may be owed a prize
+        let mut candidates = vec![tournament.bracket[0], tournament_loser(tournament, 0)];
+        for semifinal in [1, 2] {
+            if semifinal < tournament.bracket_size as usize - 1 {
+                candidates.push(tournament_loser(tournament, semifinal));
+            }
+        }
+        let payees = candidates
+            .into_iter()
+            .filter(|p| *p != Pubkey::default() && tournament_prize(tournament, *p) > 0)
+            .count() as u8;
+        tournament.status = GameStatus::Finished;
+        tournament.payouts_remaining = payees;
+        msg!("Tournament {} completed! Champion: {}", tournament.tournament_id, tournament.bracket[0]);
+    }
+    Ok(())
+}
+
+pub(crate) fn claim_tournament_payout(ctx: Context<ClaimTournamentPayout>, _tournament_id: String) -> Result<()> {
+    let player = ctx.accounts.user.key();
+    let tournament = &ctx.accounts.tournament;
+
+    let index = tournament
+        .players
+        .iter()
+        .position(|p| *p == player)
+        .ok_or(GameError::NotAPlayer)?;
+    require!(tournament.claimed & (1 << index) == 0, GameError::AlreadyClaimed);
+
+    let amount = match tournament.status {
+        GameStatus::Cancelled => tournament.entry_fee,
+        GameStatus::Finished => tournament_prize(tournament, player),
+        _ => return err!(GameError::GameNotFinished),
+    };
+    require!(amount > 0, GameError::NothingToClaim);
+
+    if tournament.currency_type == CurrencyType::Points {
+        // Prize winners' entries were already released when their run ended
+        if tournament.status == GameStatus::Cancelled {
+            unlock_points(&mut ctx.accounts.user_profile)?;
+        }
+        ctx.accounts.user_profile.points_balance += amount;
+        ctx.accounts.user_profile.total_points_earned += amount;
+    } else if tournament.currency_type == CurrencyType::Sol {
+        move_lamports(
+            &ctx.accounts.tournament.to_account_info(),
+            &ctx.accounts.user.to_account_info(),
+            amount,
+        )?;
+    }
+
+    let tournament = &mut ctx.accounts.tournament;
+    tournament.claimed |= 1 << index;
+    tournament.payouts_remaining -= 1;
+
+    msg!("Player {} claimed {} from tournament {}", player, amount, tournament.tournament_id);
+    Ok(())
+}
+
+pub(crate) fn close_tournament(ctx: Context<CloseTournament>, _tournament_id: String) -> Result<()> {
+    let tournament = &ctx.accounts.tournament;
+    require!(
+        tournament.status == GameStatus::Finished || tournament.status == GameStatus::Cancelled,
+        GameError::GameNotFinished
+    );
+    require!(tournament.payouts_remaining == 0, GameError::PayoutsPending);
+
+    msg!("Tournament {} closed", tournament.tournament_id);
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String)]
+pub struct CreateTournament<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = TOURNAMENT_SPACE,
+        seeds = [b"tournament", tournament_id.as_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    #[account(
+        seeds = [b"config"],
+        bump,
+        has_one = admin @ GameError::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String)]
+pub struct RegisterTournament<'info> {
+    #[account(
+        mut,
+        seeds = [b"tournament", tournament_id.as_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String)]
+pub struct StartTournament<'info> {
+    #[account(
+        mut,
+        seeds = [b"tournament", tournament_id.as_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String, node: u8, game_id: String)]
+pub struct StartTournamentMatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"tournament", tournament_id.as_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    #[account(
+        init,
+        payer = user,
+        space = GAME_SPACE,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String, node: u8, game_id: String)]
+pub struct RecordTournamentMatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"tournament", tournament_id.as_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    #[account(
+        mut,
+        close = player1,
+        seeds = [b"game", game_id.as_bytes()],
+        bump
+    )]
+    pub game: Account<'info, Game>,
+
+    /// CHECK: Rent is returned to the child game's player1
+    #[account(mut, address = game.player1)]
+    pub player1: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", game.player1.as_ref()],
+        bump
+    )]
+    pub player1_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", game.player2.unwrap().as_ref()],
+        bump
+    )]
+    pub player2_profile: Account<'info, UserProfile>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String)]
+pub struct ClaimTournamentPayout<'info> {
+    #[account(
+        mut,
+        seeds = [b"tournament", tournament_id.as_bytes()],
+        bump
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    #[account(
+        mut,
+        seeds = [b"user_profile", user.key().as_ref()],
+        bump
+    )]
+    pub user_profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: String)]
+pub struct CloseTournament<'info> {
+    #[account(
+        mut,
+        close = organizer,
+        seeds = [b"tournament", tournament_id.as_bytes()],
+        bump,
+        has_one = organizer @ GameError::Unauthorized
+    )]
+    pub tournament: Account<'info, Tournament>,
+
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+}
+
+pub const MAX_TOURNAMENT_PLAYERS: usize = 16;
+
+pub const TOURNAMENT_BRACKET_SLOTS: usize = 2 * MAX_TOURNAMENT_PLAYERS - 1;
+
+pub const TOURNAMENT_MATCHES: usize = MAX_TOURNAMENT_PLAYERS - 1;
+
+// discriminator + settings + players + bracket + match games + match start times + payout tracking
+pub const TOURNAMENT_SPACE: usize = 8
+    + (4 + 32) + 32 + 1 + 1 + 8 + 1 + 8 + 8 + 4 + (2 * 3)
+    + (4 + 32 * MAX_TOURNAMENT_PLAYERS) + 1
+    + 32 * TOURNAMENT_BRACKET_SLOTS
+    + 32 * TOURNAMENT_MATCHES
+    + 8 * TOURNAMENT_MATCHES
+    + 8 + 2 + 1;
+
+/// Single-elimination bracket stored as a binary heap: node 0 is the final,
+/// node `i` is played between the winners stored at `2i + 1` and `2i + 2`,
+/// and the last `bracket_size` slots seat the registered players.
+#[account]
+pub struct Tournament {
+    pub tournament_id: String,
+    pub organizer: Pubkey,
+    pub status: GameStatus,
+    pub currency_type: CurrencyType,
+    pub entry_fee: u64,
+    pub max_players: u8,
+    pub registration_ends_at: i64,
+    pub match_timeout: i64, // Seconds before an unfinished match can be forfeited
+    pub rounds_to_win_by_stage: [u8; 4], // Final, semifinals, quarterfinals, round of 16
+    pub prize_split_bps: [u16; 3], // 1st, 2nd, 3rd (shared by both semifinal losers)
+    pub players: Vec<Pubkey>,
+    pub bracket_size: u8, // Seats in the first round (power of two), 0 until started
+    pub bracket: [Pubkey; TOURNAMENT_BRACKET_SLOTS], // Default pubkey = undecided or bye
+    pub match_games: [Pubkey; TOURNAMENT_MATCHES], // Child game per bracket node
+    pub match_started_at: [i64; TOURNAMENT_MATCHES],
+    pub prize_pool: u64,
+    pub claimed: u16, // Bitmap over `players` of claimed prizes or refunds
+    pub payouts_remaining: u8,
+}
+
+pub fn bracket_stage(node: usize) -> usize {
+    (usize::BITS - 1 - (node + 1).leading_zeros()) as usize
+}
+
+/// Player knocked out at a decided bracket node (default pubkey for a bye)
+pub fn tournament_loser(tournament: &Tournament, node: usize) -> Pubkey {
+    let winner = tournament.bracket[node];
+    let upper = tournament.bracket[2 * node + 1];
+    if upper == winner {
+        tournament.bracket[2 * node + 2]
+    } else {
+        upper
+    }
+}
+
+/// Prize owed to `player` in a completed tournament. The champion also
+/// receives any unassigned third-place share and rounding dust.
+pub fn tournament_prize(tournament: &Tournament, player: Pubkey) -> u64 {
+    let pool = tournament.prize_pool;
+    let runner_up_share = pool * tournament.prize_split_bps[1] as u64 / 10000;
+    let third_share = pool * tournament.prize_split_bps[2] as u64 / 10000 / 2;
+
+    let mut third_place_paid = 0;
+    let mut is_third = false;
+    for semifinal in [1, 2] {
+        if semifinal < tournament.bracket_size as usize - 1 {
+            let loser = tournament_loser(tournament, semifinal);
+            if loser != Pubkey::default() {
+                third_place_paid += third_share;
+                is_third |= loser == player;
+            }
+        }
+    }
+
+    if player == tournament.bracket[0] {
+        pool - runner_up_share - third_place_paid
+    } else if player == tournament_loser(tournament, 0) {
+        runner_up_share
+    } else if is_third {
+        third_share
+    } else {
+        0
+    }
+}
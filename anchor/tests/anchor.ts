@@ -21,6 +21,9 @@ describe("rps_game", () => {
     return crypto.createHash('sha256').update(data).digest();
   };
 
+  // Match rules for games that don't touch ratings or leaderboards
+  const casualRules = { ranked: false, minOpponentRating: null, maxOpponentRating: null };
+
   // Helper function to get account SOL balance
   const getBalance = async (pubkey: PublicKey): Promise<number> => {
     const balance = await provider.connection.getBalance(pubkey);
@@ -47,6 +50,388 @@ describe("rps_game", () => {
     }
   };
 
+  const [globalConfigPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+  const [pointsConfigPda] = PublicKey.findProgramAddressSync([Buffer.from("points_config")], program.programId);
+  // Only the program's upgrade authority may create the global config
+  const [programDataPda] = PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+  );
+
+  const userProfilePda = (user: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("user_profile"), user.toBuffer()], program.programId)[0];
+
+  const gamePdaOf = (gameId: string) =>
+    PublicKey.findProgramAddressSync([Buffer.from("game"), Buffer.from(gameId)], program.programId)[0];
+
+  const uniqueId = (prefix: string) => prefix + "_" + Math.floor(Math.random() * 1000000);
+
+  // Faucet and transfer settings shared by the points tests
+  const pointsParams = {
+    dailyAmount: new anchor.BN(100),
+    claimCooldown: new anchor.BN(24 * 60 * 60),
+    streakBonus: new anchor.BN(10),
+    maxStreakBonusDays: 7,
+    starterGrant: new anchor.BN(300),
+    transferDailyLimit: new anchor.BN(100),
+    transferFeeBps: 1000,
+  };
+
+  // Config and the points config are global, so they may already exist from an earlier run
+  const ensureConfigs = async () => {
+    if (!(await provider.connection.getAccountInfo(globalConfigPda))) {
+      await program.methods
+        .initializeConfig(provider.publicKey, 0, 0, 8)
+        .accounts({
+          config: globalConfigPda,
+          admin: provider.publicKey,
+          programData: programDataPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+    if (!(await provider.connection.getAccountInfo(pointsConfigPda))) {
+      await program.methods
+        .initializePointsConfig(pointsParams)
+        .accounts({
+          pointsConfig: pointsConfigPda,
+          config: globalConfigPda,
+          admin: provider.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    } else {
+      await program.methods
+        .updatePointsConfig(pointsParams)
+        .accounts({ pointsConfig: pointsConfigPda, config: globalConfigPda, admin: provider.publicKey })
+        .rpc();
+    }
+  };
+
+  // Funded player with a profile holding the starter grant
+  const createPlayer = async () => {
+    const user = Keypair.generate();
+    await fundWallet(user);
+    await program.methods
+      .initializeUserProfile()
+      .accountsPartial({
+        userProfile: userProfilePda(user.publicKey),
+        user: user.publicKey,
+        pointsConfig: pointsConfigPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc();
+    return user;
+  };
+
+  const createGame = async (
+    gameId: string,
+    creator: Keypair,
+    stake: anchor.BN,
+    currency: { points: {} } | { sol: {} },
+    lobby: PublicKey | null = null
+  ) => {
+    await program.methods
+      .createGame(gameId, stake, currency, 1, casualRules)
+      .accountsPartial({
+        game: gamePdaOf(gameId),
+        userProfile: userProfilePda(creator.publicKey),
+        user: creator.publicKey,
+        mint: null,
+        allowedMint: null,
+        escrow: null,
+        userTokenAccount: null,
+        tokenProgram: null,
+        priceFeed: null,
+        priceAccount: null,
+        lobby,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+  };
+
+  const joinGame = async (gameId: string, joiner: Keypair, lobby: PublicKey | null = null) => {
+    await program.methods
+      .joinGame(gameId)
+      .accountsPartial({
+        game: gamePdaOf(gameId),
+        userProfile: userProfilePda(joiner.publicKey),
+        user: joiner.publicKey,
+        mint: null,
+        allowedMint: null,
+        escrow: null,
+        userTokenAccount: null,
+        tokenProgram: null,
+        ratingConfig: null,
+        lobby,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([joiner])
+      .rpc();
+  };
+
+  // Plays the deciding round of a one-round game: `winner` throws rock, `loser` scissors
+  const playWinningRound = async (gameId: string, player1: Keypair, player2: Keypair, winner: Keypair) => {
+    const nonces = { player1: BigInt(333), player2: BigInt(444) };
+    const move1 = winner === player1 ? 0 : 2;
+    const move2 = winner === player1 ? 2 : 0;
+    for (const [user, commitment] of [
+      [player1, createMoveCommitment(move1, nonces.player1)],
+      [player2, createMoveCommitment(move2, nonces.player2)],
+    ] as [Keypair, Buffer][]) {
+      await program.methods
+        .commitMove(gameId, Array.from(commitment))
+        .accounts({ game: gamePdaOf(gameId), user: user.publicKey })
+        .signers([user])
+        .rpc();
+    }
+    const moveArg = (move: number) => [{ rock: {} }, { paper: {} }, { scissors: {} }][move];
+    await program.methods
+      .processRound(
+        gameId,
+        moveArg(move1),
+        new anchor.BN(nonces.player1.toString()),
+        moveArg(move2),
+        new anchor.BN(nonces.player2.toString())
+      )
+      .accounts({ game: gamePdaOf(gameId), user: player1.publicKey })
+      .signers([player1])
+      .rpc();
+  };
+
+  const pointsOf = async (user: Keypair) =>
+    (await program.account.userProfile.fetch(userProfilePda(user.publicKey))).pointsBalance.toNumber();
+
+  // finalize_game's season accounts: both players' stats once seasons have started, and
+  // the active season's leaderboard (ignored until then)
+  const seasonAccountsFor = async (player1: PublicKey, player2: PublicKey) => {
+    const seasonSeed = (seasonId: number) => {
+      const bytes = Buffer.alloc(4);
+      bytes.writeUInt32LE(seasonId);
+      return bytes;
+    };
+    const [seasonPda] = PublicKey.findProgramAddressSync([Buffer.from("season")], program.programId);
+    const season = await program.account.season.fetchNullable(seasonPda);
+    let seasonId = 1;
+    if (season) {
+      const now = await provider.connection.getBlockTime(await provider.connection.getSlot("confirmed"));
+      seasonId = now >= season.endsAt.toNumber() ? season.seasonId + 1 : season.seasonId;
+    }
+    const statsPda = (player: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("season_stats"), seasonSeed(seasonId), player.toBuffer()],
+        program.programId
+      )[0];
+    return {
+      player1SeasonStats: season ? statsPda(player1) : null,
+      player2SeasonStats: season ? statsPda(player2) : null,
+      payer: season ? provider.publicKey : null,
+      seasonLeaderboard: PublicKey.findProgramAddressSync(
+        [Buffer.from("leaderboard"), seasonSeed(seasonId)],
+        program.programId
+      )[0],
+    };
+  };
+
+  // SPL token helpers built from raw instructions, so the tests don't need @solana/spl-token
+  const splTokenProgramId = new PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+  const token2022ProgramId = new PublicKey("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+  // Mint with the provider as mint authority. `extensions` initializes any Token-2022
+  // extensions, which must come before InitializeMint2 and need `space` to fit them.
+  const createMint = async (
+    tokenProgram: PublicKey,
+    options: {
+      space?: number;
+      freezeAuthority?: PublicKey;
+      extensions?: (mint: PublicKey) => anchor.web3.TransactionInstruction[];
+    } = {}
+  ) => {
+    const mint = Keypair.generate();
+    const space = options.space ?? 82;
+    const freezeAuthority = options.freezeAuthority
+      ? Buffer.concat([Buffer.from([1]), options.freezeAuthority.toBuffer()])
+      : Buffer.from([0]);
+    const initializeMint2 = new anchor.web3.TransactionInstruction({
+      programId: tokenProgram,
+      keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+      data: Buffer.concat([Buffer.from([20, 6]), provider.publicKey.toBuffer(), freezeAuthority]),
+    });
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: provider.publicKey,
+        newAccountPubkey: mint.publicKey,
+        lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+        space,
+        programId: tokenProgram,
+      }),
+      ...(options.extensions ? options.extensions(mint.publicKey) : []),
+      initializeMint2
+    );
+    await provider.sendAndConfirm(tx, [mint]);
+    return mint.publicKey;
+  };
+
+  const createTokenAccountFor = async (tokenProgram: PublicKey, mint: PublicKey, owner: PublicKey, space = 165) => {
+    const account = Keypair.generate();
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: provider.publicKey,
+        newAccountPubkey: account.publicKey,
+        lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+        space,
+        programId: tokenProgram,
+      }),
+      new anchor.web3.TransactionInstruction({
+        programId: tokenProgram,
+        keys: [
+          { pubkey: account.publicKey, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+        ],
+        data: Buffer.concat([Buffer.from([18]), owner.toBuffer()]),
+      })
+    );
+    await provider.sendAndConfirm(tx, [account]);
+    return account.publicKey;
+  };
+
+  const mintTokens = async (tokenProgram: PublicKey, mint: PublicKey, to: PublicKey, amount: number) => {
+    const amountBytes = Buffer.alloc(8);
+    amountBytes.writeBigUInt64LE(BigInt(amount));
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        new anchor.web3.TransactionInstruction({
+          programId: tokenProgram,
+          keys: [
+            { pubkey: mint, isSigner: false, isWritable: true },
+            { pubkey: to, isSigner: false, isWritable: true },
+            { pubkey: provider.publicKey, isSigner: true, isWritable: false },
+          ],
+          data: Buffer.concat([Buffer.from([7]), amountBytes]),
+        })
+      )
+    );
+  };
+
+  const tokenBalance = async (account: PublicKey) =>
+    Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
+
+  const allowedMintPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("allowed_mint"), mint.toBuffer()], program.programId)[0];
+  const escrowPda = (gameId: string) =>
+    PublicKey.findProgramAddressSync([Buffer.from("escrow"), gamePdaOf(gameId).toBuffer()], program.programId)[0];
+  // Every stake pays the top tier's 5% fee
+  const tokenFeeTiers = [
+    { maxStake: new anchor.BN(0), feeBps: 500 },
+    { maxStake: new anchor.BN(0), feeBps: 500 },
+    { maxStake: new anchor.BN(0), feeBps: 500 },
+  ];
+
+  const allowMint = async (mint: PublicKey) => {
+    await program.methods
+      .addAllowedMint(tokenFeeTiers, null)
+      .accounts({
+        allowedMint: allowedMintPda(mint),
+        mint,
+        config: globalConfigPda,
+        admin: provider.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  };
+
+  const tokenAccounts = (gameId: string, mint: PublicKey, tokenProgram: PublicKey, userTokenAccount: PublicKey) => ({
+    mint,
+    allowedMint: allowedMintPda(mint),
+    escrow: escrowPda(gameId),
+    userTokenAccount,
+    tokenProgram,
+  });
+
+  const createTokenGame = async (
+    gameId: string,
+    creator: Keypair,
+    stake: number,
+    mint: PublicKey,
+    tokenProgram: PublicKey,
+    creatorTokenAccount: PublicKey
+  ) => {
+    await program.methods
+      .createGame(gameId, new anchor.BN(stake), { splToken: {} }, 1, casualRules)
+      .accountsPartial({
+        game: gamePdaOf(gameId),
+        userProfile: userProfilePda(creator.publicKey),
+        user: creator.publicKey,
+        ...tokenAccounts(gameId, mint, tokenProgram, creatorTokenAccount),
+        priceFeed: null,
+        priceAccount: null,
+        lobby: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([creator])
+      .rpc();
+  };
+
+  const joinTokenGame = async (
+    gameId: string,
+    joiner: Keypair,
+    mint: PublicKey,
+    tokenProgram: PublicKey,
+    joinerTokenAccount: PublicKey
+  ) => {
+    await program.methods
+      .joinGame(gameId)
+      .accountsPartial({
+        game: gamePdaOf(gameId),
+        userProfile: userProfilePda(joiner.publicKey),
+        user: joiner.publicKey,
+        ...tokenAccounts(gameId, mint, tokenProgram, joinerTokenAccount),
+        ratingConfig: null,
+        lobby: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([joiner])
+      .rpc();
+  };
+
+  // Pays the escrowed pot of a decided token game to `winnerTokenAccount`
+  const finalizeTokenGame = async (
+    gameId: string,
+    player1: Keypair,
+    player2: Keypair,
+    mint: PublicKey,
+    tokenProgram: PublicKey,
+    winnerTokenAccount: PublicKey,
+    platformTokenAccount: PublicKey
+  ) => {
+    const bystander = await createPlayer();
+    await program.methods
+      .finalizeGame(gameId)
+      .accountsPartial({
+        game: gamePdaOf(gameId),
+        player1Profile: userProfilePda(player1.publicKey),
+        player2Profile: userProfilePda(player2.publicKey),
+        player1: player1.publicKey,
+        player2: player2.publicKey,
+        platformWallet: provider.publicKey,
+        referrer: provider.publicKey,
+        referrerProfile: userProfilePda(bystander.publicKey),
+        mint,
+        allowedMint: allowedMintPda(mint),
+        escrow: escrowPda(gameId),
+        winnerTokenAccount,
+        platformTokenAccount,
+        tokenProgram,
+        pointsMint: null,
+        ...(await seasonAccountsFor(player1.publicKey, player2.publicKey)),
+        ratingConfig: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  };
+
   describe("Week 1: User Profiles and Game Creation", () => {
     let user1: Keypair;
     let user2: Keypair;
@@ -112,7 +497,7 @@ describe("rps_game", () => {
       );
 
       await program.methods
-        .createGame(gameId, new anchor.BN(50), { points: {} }, 3)
+        .createGame(gameId, new anchor.BN(50), { points: {} }, 3, casualRules)
         .accounts({
           game: gamePda,
           userProfile: user1ProfilePda,
@@ -190,7 +575,7 @@ describe("rps_game", () => {
 
       // Create a game
       await program.methods
-        .createGame(gameId, new anchor.BN(100), { points: {} }, 2)
+        .createGame(gameId, new anchor.BN(100), { points: {} }, 2, casualRules)
         .accounts({
           game: gamePda,
           userProfile: user1ProfilePda,
@@ -444,7 +829,7 @@ describe("rps_game", () => {
       const initialUser1Balance = await getBalance(user1.publicKey);
       
       await program.methods
-        .createGame(gameId, new anchor.BN(stakeAmount), { sol: {} }, 2)
+        .createGame(gameId, new anchor.BN(stakeAmount), { sol: {} }, 2, casualRules)
         .accounts({
           game: gamePda,
           userProfile: user1ProfilePda,
@@ -478,7 +863,7 @@ describe("rps_game", () => {
       );
       
       await program.methods
-        .createGame(joinGameId, new anchor.BN(stakeAmount), { sol: {} }, 2)
+        .createGame(joinGameId, new anchor.BN(stakeAmount), { sol: {} }, 2, casualRules)
         .accounts({
           game: joinGamePda,
           userProfile: user1ProfilePda,
@@ -531,7 +916,7 @@ describe("rps_game", () => {
       );
       
       await program.methods
-        .createGame(completeGameId, new anchor.BN(stakeAmount), { sol: {} }, 2)
+        .createGame(completeGameId, new anchor.BN(stakeAmount), { sol: {} }, 2, casualRules)
         .accounts({
           game: completeGamePda,
           userProfile: user1ProfilePda,
@@ -692,7 +1077,7 @@ describe("rps_game", () => {
       );
       
       await program.methods
-        .createGame(abandonGameId, new anchor.BN(stakeAmount), { sol: {} }, 2)
+        .createGame(abandonGameId, new anchor.BN(stakeAmount), { sol: {} }, 2, casualRules)
         .accounts({
           game: abandonGamePda,
           userProfile: user1ProfilePda,
@@ -756,4 +1141,2727 @@ describe("rps_game", () => {
       expect(user2Profile.totalGames.toString()).to.equal("1");
     });
   });
+
+  describe("USD Stakes", () => {
+    // Crafted Pyth-style price accounts loaded by the local validator (see Anchor.toml):
+    // SOL at $150.00 with a $0.05 confidence interval, and the same price with $15
+    const solUsdPrice = new PublicKey("DJMmebTvMBU5uiTD7vhtQJFxbvP8tgGuEfcZ8gnVRRX4");
+    const solUsdPriceWide = new PublicKey("6pWjo32vAq2n4EF4Z5VBWJFsfZxbceSKAGj6JUt6Hrbk");
+    // Feed ids carried by the two price update fixtures
+    const solUsdFeedId = Array.from(Buffer.from("ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d", "hex"));
+    const wideFeedId = Array.from(Buffer.from("ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace", "hex"));
+    // The fixtures carry a fixed publish time, so freshness is only enforced where a test asks for it
+    const lenientStaleness = new anchor.BN(1_000_000_000);
+
+    let user1: Keypair;
+    let user1ProfilePda: PublicKey;
+    let configPda: PublicKey;
+    let solPriceFeedPda: PublicKey;
+
+    const setSolFeed = async (feedId: number[], maxStaleness: anchor.BN, maxConfidenceBps: number) => {
+      await program.methods
+        .updatePriceFeed(feedId, maxStaleness, maxConfidenceBps)
+        .accounts({
+          priceFeed: solPriceFeedPda,
+          config: configPda,
+          admin: provider.publicKey,
+        })
+        .rpc();
+    };
+
+    const createUsdGame = async (gameId: string, usdStake: number, feed: PublicKey) => {
+      const [gamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("game"), Buffer.from(gameId)],
+        program.programId
+      );
+      await program.methods
+        .createUsdGame(gameId, new anchor.BN(usdStake), { sol: {} }, 1, casualRules)
+        .accountsPartial({
+          game: gamePda,
+          userProfile: user1ProfilePda,
+          user: user1.publicKey,
+          mint: null,
+          allowedMint: null,
+          escrow: null,
+          userTokenAccount: null,
+          tokenProgram: null,
+          priceFeed: solPriceFeedPda,
+          priceAccount: feed,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+      return gamePda;
+    };
+
+    before(async () => {
+      [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+      [solPriceFeedPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("price_feed"), PublicKey.default.toBuffer()],
+        program.programId
+      );
+
+      // Config and the SOL feed are global, so they may already exist from an earlier run
+      if (!(await provider.connection.getAccountInfo(configPda))) {
+        await program.methods
+          .initializeConfig(provider.publicKey, 0, 0, 8)
+          .accounts({
+            config: configPda,
+            admin: provider.publicKey,
+            programData: programDataPda,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+      if (!(await provider.connection.getAccountInfo(solPriceFeedPda))) {
+        await program.methods
+          .registerPriceFeed(PublicKey.default, solUsdFeedId, lenientStaleness, 100)
+          .accounts({
+            priceFeed: solPriceFeedPda,
+            config: configPda,
+            admin: provider.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+    });
+
+    beforeEach(async () => {
+      user1 = Keypair.generate();
+      await fundWallet(user1);
+
+      [user1ProfilePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("user_profile"), user1.publicKey.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .initializeUserProfile()
+        .accounts({
+          userProfile: user1ProfilePda,
+          user: user1.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+
+      await setSolFeed(solUsdFeedId, lenientStaleness, 100);
+    });
+
+    it("Should convert a USD stake to lamports at the oracle price", async () => {
+      const gameId = "usd_game_" + Math.floor(Math.random() * 1000000);
+      const gamePda = await createUsdGame(gameId, 1_500_000, solUsdPrice); // $1.50
+
+      // $1.50 at $150/SOL is 0.01 SOL
+      const game = await program.account.game.fetch(gamePda);
+      expect(game.stakeAmount.toString()).to.equal((0.01 * LAMPORTS_PER_SOL).toString());
+      expect(game.usdStake.toString()).to.equal("1500000");
+
+      const gameBalance = await getBalance(gamePda);
+      expect(gameBalance).to.be.at.least(0.01 * LAMPORTS_PER_SOL);
+    });
+
+    it("Should reject a stale oracle price", async () => {
+      await setSolFeed(solUsdFeedId, new anchor.BN(60), 100);
+
+      try {
+        await createUsdGame("usd_stale_" + Math.floor(Math.random() * 1000000), 1_500_000, solUsdPrice);
+        expect.fail("Should have rejected the stale price");
+      } catch (error) {
+        expect(error.toString()).to.include("StalePrice");
+      }
+    });
+
+    it("Should reject a price with a wide confidence interval", async () => {
+      await setSolFeed(wideFeedId, lenientStaleness, 100);
+
+      try {
+        await createUsdGame("usd_wide_" + Math.floor(Math.random() * 1000000), 1_500_000, solUsdPriceWide);
+        expect.fail("Should have rejected the wide confidence interval");
+      } catch (error) {
+        expect(error.toString()).to.include("PriceConfidenceTooWide");
+      }
+    });
+
+    it("Should reject a price update for a feed other than the registered one", async () => {
+      try {
+        await createUsdGame("usd_wrong_" + Math.floor(Math.random() * 1000000), 1_500_000, solUsdPriceWide);
+        expect.fail("Should have rejected the unregistered price account");
+      } catch (error) {
+        expect(error.toString()).to.include("PriceFeedMismatch");
+      }
+    });
+  });
+
+  describe("Ratings", () => {
+    const defaultRating = 1200;
+    const ratingParams = {
+      defaultRating,
+      defaultDeviation: 350,
+      minDeviation: 50,
+      kFactor: 32,
+      minRating: 100,
+      maxRating: 3000,
+    };
+    const stakeAmount = new anchor.BN(0.01 * LAMPORTS_PER_SOL);
+    const rankedRules = { ...casualRules, ranked: true };
+
+    let configPda: PublicKey;
+    let ratingConfigPda: PublicKey;
+    let referrerProfilePda: PublicKey;
+
+    const profilePda = (user: Keypair) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("user_profile"), user.publicKey.toBuffer()],
+        program.programId
+      )[0];
+
+    const newPlayer = async () => {
+      const user = Keypair.generate();
+      await fundWallet(user);
+      await program.methods
+        .initializeUserProfile()
+        .accounts({
+          userProfile: profilePda(user),
+          user: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc();
+      return user;
+    };
+
+    const setRatingParams = async (params: typeof ratingParams) => {
+      await program.methods
+        .updateRatingConfig(params)
+        .accounts({
+          ratingConfig: ratingConfigPda,
+          config: configPda,
+          admin: provider.publicKey,
+        })
+        .rpc();
+    };
+
+    // Plays a one-round SOL game that `winner` wins with rock over scissors, up to finalization
+    const setUpRankedGame = async (winner: Keypair, loser: Keypair, rules = rankedRules) => {
+      const gameId = "ranked_" + Math.floor(Math.random() * 1000000);
+      const [gamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("game"), Buffer.from(gameId)],
+        program.programId
+      );
+
+      await program.methods
+        .createGame(gameId, stakeAmount, { sol: {} }, 1, rules)
+        .accountsPartial({
+          game: gamePda,
+          userProfile: profilePda(winner),
+          user: winner.publicKey,
+          mint: null,
+          allowedMint: null,
+          escrow: null,
+          userTokenAccount: null,
+          tokenProgram: null,
+          priceFeed: null,
+          priceAccount: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([winner])
+        .rpc();
+      await program.methods
+        .joinGame(gameId)
+        .accountsPartial({
+          game: gamePda,
+          userProfile: profilePda(loser),
+          user: loser.publicKey,
+          mint: null,
+          allowedMint: null,
+          escrow: null,
+          userTokenAccount: null,
+          tokenProgram: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([loser])
+        .rpc();
+
+      const winnerNonce = BigInt(111);
+      const loserNonce = BigInt(222);
+      for (const [user, commitment] of [
+        [winner, createMoveCommitment(0, winnerNonce)],
+        [loser, createMoveCommitment(2, loserNonce)],
+      ] as [Keypair, Buffer][]) {
+        await program.methods
+          .commitMove(gameId, Array.from(commitment))
+          .accounts({ game: gamePda, user: user.publicKey })
+          .signers([user])
+          .rpc();
+      }
+      await program.methods
+        .processRound(
+          gameId,
+          { rock: {} },
+          new anchor.BN(winnerNonce.toString()),
+          { scissors: {} },
+          new anchor.BN(loserNonce.toString())
+        )
+        .accounts({ game: gamePda, user: winner.publicKey })
+        .signers([winner])
+        .rpc();
+      return { gameId, gamePda };
+    };
+
+    const playRankedGame = async (winner: Keypair, loser: Keypair) => {
+      const { gameId, gamePda } = await setUpRankedGame(winner, loser);
+      await finalizeRankedGame(gameId, gamePda, winner, loser);
+      return {
+        gameId,
+        gamePda,
+        winner: await program.account.userProfile.fetch(profilePda(winner)),
+        loser: await program.account.userProfile.fetch(profilePda(loser)),
+      };
+    };
+
+    const [seasonPda] = PublicKey.findProgramAddressSync([Buffer.from("season")], program.programId);
+    const seasonStatsPda = (seasonId: number, player: PublicKey) => {
+      const seasonIdBytes = Buffer.alloc(4);
+      seasonIdBytes.writeUInt32LE(seasonId);
+      return PublicKey.findProgramAddressSync(
+        [Buffer.from("season_stats"), seasonIdBytes, player.toBuffer()],
+        program.programId
+      )[0];
+    };
+    const leaderboardPda = (seasonId: number) => {
+      const seasonIdBytes = Buffer.alloc(4);
+      seasonIdBytes.writeUInt32LE(seasonId);
+      return PublicKey.findProgramAddressSync([Buffer.from("leaderboard"), seasonIdBytes], program.programId)[0];
+    };
+    const chainTime = async () =>
+      provider.connection.getBlockTime(await provider.connection.getSlot("confirmed"));
+
+    // Season a game finalized now counts toward, or null before seasons have started
+    const activeSeasonId = async (): Promise<number | null> => {
+      const season = await program.account.season.fetchNullable(seasonPda);
+      if (!season) {
+        return null;
+      }
+      return (await chainTime()) >= season.endsAt.toNumber() ? season.seasonId + 1 : season.seasonId;
+    };
+
+    const finalizeRankedGame = async (
+      gameId: string,
+      gamePda: PublicKey,
+      winner: Keypair,
+      loser: Keypair,
+      seasonAccounts?: { player1SeasonStats: PublicKey | null; player2SeasonStats: PublicKey | null; payer: PublicKey | null }
+    ) => {
+      // Once seasons have started both players' stats are required, and created on first use
+      if (!seasonAccounts) {
+        const seasonId = await activeSeasonId();
+        seasonAccounts =
+          seasonId === null
+            ? { player1SeasonStats: null, player2SeasonStats: null, payer: null }
+            : {
+                player1SeasonStats: seasonStatsPda(seasonId, winner.publicKey),
+                player2SeasonStats: seasonStatsPda(seasonId, loser.publicKey),
+                payer: provider.publicKey,
+              };
+      }
+      await program.methods
+        .finalizeGame(gameId)
+        .accountsPartial({
+          game: gamePda,
+          player1Profile: profilePda(winner),
+          player2Profile: profilePda(loser),
+          player1: winner.publicKey,
+          player2: loser.publicKey,
+          platformWallet: provider.publicKey,
+          referrer: provider.publicKey,
+          referrerProfile: referrerProfilePda,
+          mint: null,
+          allowedMint: null,
+          escrow: null,
+          winnerTokenAccount: null,
+          platformTokenAccount: null,
+          tokenProgram: null,
+          pointsMint: null,
+          ...seasonAccounts,
+          // Ignored until seasons have started
+          seasonLeaderboard: leaderboardPda((await activeSeasonId()) ?? 1),
+          ratingConfig: ratingConfigPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    };
+
+    before(async () => {
+      [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+      [ratingConfigPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("rating_config")],
+        program.programId
+      );
+
+      // Config and the rating config are global, so they may already exist from an earlier run
+      if (!(await provider.connection.getAccountInfo(configPda))) {
+        await program.methods
+          .initializeConfig(provider.publicKey, 0, 0, 8)
+          .accounts({
+            config: configPda,
+            admin: provider.publicKey,
+            programData: programDataPda,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+      if (!(await provider.connection.getAccountInfo(ratingConfigPda))) {
+        await program.methods
+          .initializeRatingConfig(ratingParams)
+          .accounts({
+            ratingConfig: ratingConfigPda,
+            config: configPda,
+            admin: provider.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      } else {
+        await setRatingParams(ratingParams);
+      }
+
+      // finalize_game needs a referrer profile even when neither player was referred
+      referrerProfilePda = profilePda(await newPlayer());
+    });
+
+    it("Should move the same amount of rating from loser to winner", async () => {
+      const player1 = await newPlayer();
+      const player2 = await newPlayer();
+
+      const { winner, loser } = await playRankedGame(player1, player2);
+
+      // Evenly rated players at the default deviation trade half the k-factor
+      expect(winner.rating).to.equal(defaultRating + ratingParams.kFactor / 2);
+      expect(loser.rating).to.equal(defaultRating - ratingParams.kFactor / 2);
+      expect(winner.rating + loser.rating).to.equal(2 * defaultRating);
+      expect(winner.ratingDeviation).to.be.lessThan(ratingParams.defaultDeviation);
+      expect(loser.ratingDeviation).to.be.lessThan(ratingParams.defaultDeviation);
+    });
+
+    it("Should settle a finalized game so it can't be paid out twice", async () => {
+      const player1 = await newPlayer();
+      const player2 = await newPlayer();
+
+      const { gameId, gamePda, winner } = await playRankedGame(player1, player2);
+      const game = await program.account.game.fetch(gamePda);
+      expect(game.gameStatus).to.deep.equal({ settled: {} });
+
+      try {
+        await finalizeRankedGame(gameId, gamePda, player1, player2);
+        expect.fail("Should have rejected the second finalize");
+      } catch (error) {
+        expect(error.toString()).to.include("GameNotFinished");
+      }
+      const replayed = await program.account.userProfile.fetch(profilePda(player1));
+      expect(replayed.wins.toString()).to.equal(winner.wins.toString());
+    });
+
+    it("Should conserve the rating sum and reward upsets more", async () => {
+      // Two identical matchups, so both rematches start from the same ratings and deviations
+      const [favourite1, underdog1, favourite2, underdog2] = [
+        await newPlayer(),
+        await newPlayer(),
+        await newPlayer(),
+        await newPlayer(),
+      ];
+      await playRankedGame(favourite1, underdog1);
+      await playRankedGame(favourite2, underdog2);
+
+      const expected = await playRankedGame(favourite1, underdog1);
+      const upset = await playRankedGame(underdog2, favourite2);
+
+      const favouriteGain = expected.winner.rating - (defaultRating + ratingParams.kFactor / 2);
+      const underdogGain = upset.winner.rating - (defaultRating - ratingParams.kFactor / 2);
+      expect(underdogGain).to.be.greaterThan(favouriteGain);
+      expect(favouriteGain).to.be.greaterThan(0);
+      expect(expected.winner.rating + expected.loser.rating).to.equal(2 * defaultRating);
+      expect(upset.winner.rating + upset.loser.rating).to.equal(2 * defaultRating);
+    });
+
+    it("Should keep ratings within the configured bounds", async () => {
+      await setRatingParams({ ...ratingParams, minRating: defaultRating - 5, maxRating: defaultRating + 5 });
+
+      try {
+        const player1 = await newPlayer();
+        const player2 = await newPlayer();
+
+        const { winner, loser } = await playRankedGame(player1, player2);
+        expect(winner.rating).to.equal(defaultRating + 5);
+        expect(loser.rating).to.equal(defaultRating - 5);
+
+        // Already at the bounds, another win changes nothing
+        const again = await playRankedGame(player1, player2);
+        expect(again.winner.rating).to.equal(defaultRating + 5);
+        expect(again.loser.rating).to.equal(defaultRating - 5);
+      } finally {
+        await setRatingParams(ratingParams);
+      }
+    });
+
+    it("Should keep the all-time leaderboard sorted with one entry per player", async () => {
+      const [leaderboardPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("leaderboard"), Buffer.alloc(4)],
+        program.programId
+      );
+      if (!(await provider.connection.getAccountInfo(leaderboardPda))) {
+        await program.methods
+          .initializeLeaderboard(0)
+          .accounts({
+            leaderboard: leaderboardPda,
+            config: configPda,
+            admin: provider.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+
+      const [first, second, third] = [await newPlayer(), await newPlayer(), await newPlayer()];
+      await playRankedGame(first, second);
+      await playRankedGame(first, third);
+      await playRankedGame(second, third);
+
+      // Every ranked finalize updates the board now that it exists
+      const leaderboard = await program.account.leaderboard.fetch(leaderboardPda);
+      for (const ranking of [leaderboard.byWins, leaderboard.byNetSol, leaderboard.byRating]) {
+        const scores = ranking.map((entry) => entry.score.toNumber());
+        expect(scores).to.deep.equal([...scores].sort((a, b) => b - a));
+        const players = ranking.map((entry) => entry.player.toString());
+        expect(new Set(players).size).to.equal(players.length);
+      }
+
+      const winsOf = (player: Keypair) =>
+        leaderboard.byWins.find((entry) => entry.player.equals(player.publicKey))?.score.toNumber();
+      expect(winsOf(first)).to.equal(2);
+      expect(winsOf(second)).to.equal(1);
+      expect(winsOf(third)).to.equal(0);
+    });
+
+    it("Should roll the season over and record both players' season stats", async () => {
+      // Start a one-second season, or cut the current one short
+      if (!(await provider.connection.getAccountInfo(seasonPda))) {
+        await program.methods
+          .initializeSeason(new anchor.BN(1))
+          .accounts({
+            season: seasonPda,
+            config: configPda,
+            admin: provider.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      } else {
+        await program.methods
+          .startNewSeason(new anchor.BN(1))
+          .accounts({ season: seasonPda, config: configPda, admin: provider.publicKey })
+          .rpc();
+      }
+      const endedSeason = await program.account.season.fetch(seasonPda);
+      while ((await chainTime()) < endedSeason.endsAt.toNumber() + 1) {
+        await new Promise((resolve) => setTimeout(resolve, 500));
+      }
+
+      const player1 = await newPlayer();
+      const player2 = await newPlayer();
+      try {
+        await playRankedGame(player1, player2);
+
+        // The finalize rolled the season over and opened both players' stats for the new one
+        const season = await program.account.season.fetch(seasonPda);
+        expect(season.seasonId).to.equal(endedSeason.seasonId + 1);
+        const winnerStats = await program.account.seasonStats.fetch(seasonStatsPda(season.seasonId, player1.publicKey));
+        const loserStats = await program.account.seasonStats.fetch(seasonStatsPda(season.seasonId, player2.publicKey));
+        expect(winnerStats.seasonId).to.equal(season.seasonId);
+        expect(winnerStats.wins).to.equal(1);
+        expect(winnerStats.losses).to.equal(0);
+        expect(winnerStats.totalGames).to.equal(1);
+        expect(loserStats.wins).to.equal(0);
+        expect(loserStats.losses).to.equal(1);
+      } finally {
+        // Keep the games below inside one season
+        await program.methods
+          .startNewSeason(new anchor.BN(3600))
+          .accounts({ season: seasonPda, config: configPda, admin: provider.publicKey })
+          .rpc();
+      }
+    });
+
+    it("Should require both players' season stats once seasons have started", async () => {
+      const player1 = await newPlayer();
+      const player2 = await newPlayer();
+      const { gameId, gamePda } = await setUpRankedGame(player1, player2);
+
+      try {
+        await finalizeRankedGame(gameId, gamePda, player1, player2, {
+          player1SeasonStats: null,
+          player2SeasonStats: null,
+          payer: null,
+        });
+        expect.fail("Should have required the season stats");
+      } catch (error) {
+        expect(error.toString()).to.include("MissingSeasonStats");
+      }
+    });
+
+    it("Should record season stats for casual games without touching ratings", async () => {
+      const player1 = await newPlayer();
+      const player2 = await newPlayer();
+      const { gameId, gamePda } = await setUpRankedGame(player1, player2, casualRules);
+      const seasonId = await activeSeasonId();
+      await finalizeRankedGame(gameId, gamePda, player1, player2);
+
+      const winnerStats = await program.account.seasonStats.fetch(seasonStatsPda(seasonId, player1.publicKey));
+      const loserStats = await program.account.seasonStats.fetch(seasonStatsPda(seasonId, player2.publicKey));
+      expect(winnerStats.wins).to.equal(1);
+      expect(winnerStats.totalGames).to.equal(1);
+      expect(loserStats.losses).to.equal(1);
+      const winner = await program.account.userProfile.fetch(profilePda(player1));
+      expect(winner.rating).to.equal(0);
+      expect(winner.rankedWins).to.equal(0);
+    });
+  });
+
+  describe("Points Migration", () => {
+    const tokenProgramId = new PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    // Owner and profile of the pre-upgrade fixture in tests/fixtures/legacy_user_profile.json
+    const legacyOwner = new PublicKey("FAL3SMU71KUTwJNKTTPPS8nD7t6EGFr6j3ndUkkRCyvT");
+    const legacyProfile = new PublicKey("FYGP9qm1sTbteA49ZB6RjhnaqAU4Ac49nyS7fRt5o5oi");
+    const legacyProfileSpace = 97;
+    const userProfileSpace = 162;
+    let pointsMintPda: PublicKey;
+
+    // Token account for `owner` without pulling in @solana/spl-token
+    const createTokenAccount = async (mint: PublicKey, owner: PublicKey): Promise<PublicKey> => {
+      const account = Keypair.generate();
+      const space = 165;
+      const lamports = await provider.connection.getMinimumBalanceForRentExemption(space);
+      const initializeAccount3 = new anchor.web3.TransactionInstruction({
+        programId: tokenProgramId,
+        keys: [
+          { pubkey: account.publicKey, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+        ],
+        data: Buffer.concat([Buffer.from([18]), owner.toBuffer()]),
+      });
+      const tx = new anchor.web3.Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: provider.publicKey,
+          newAccountPubkey: account.publicKey,
+          lamports,
+          space,
+          programId: tokenProgramId,
+        }),
+        initializeAccount3
+      );
+      await provider.sendAndConfirm(tx, [account]);
+      return account.publicKey;
+    };
+
+    before(async () => {
+      const [configPda] = PublicKey.findProgramAddressSync([Buffer.from("config")], program.programId);
+      [pointsMintPda] = PublicKey.findProgramAddressSync([Buffer.from("points_mint")], program.programId);
+
+      // Config and the points mint are global, so they may already exist from an earlier run
+      if (!(await provider.connection.getAccountInfo(configPda))) {
+        await program.methods
+          .initializeConfig(provider.publicKey, 0, 0, 8)
+          .accounts({
+            config: configPda,
+            admin: provider.publicKey,
+            programData: programDataPda,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+      if (!(await provider.connection.getAccountInfo(pointsMintPda))) {
+        await program.methods
+          .initializePointsMint()
+          .accounts({
+            pointsMint: pointsMintPda,
+            config: configPda,
+            admin: provider.publicKey,
+            tokenProgram: tokenProgramId,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+    });
+
+    it("Should migrate a profile still in the pre-upgrade layout", async () => {
+      const before = await provider.connection.getAccountInfo(legacyProfile);
+      expect(before.data.length).to.equal(legacyProfileSpace);
+
+      const ownerTokenAccount = await createTokenAccount(pointsMintPda, legacyOwner);
+      await program.methods
+        .migratePointsToTokens()
+        .accountsPartial({
+          userProfile: legacyProfile,
+          owner: legacyOwner,
+          pointsMint: pointsMintPda,
+          ownerTokenAccount,
+          payer: provider.publicKey,
+          tokenProgram: tokenProgramId,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      // The profile was grown to the current layout with its old fields intact
+      const after = await provider.connection.getAccountInfo(legacyProfile);
+      expect(after.data.length).to.equal(userProfileSpace);
+      const profile = await program.account.userProfile.fetch(legacyProfile);
+      expect(profile.pointsMigrated).to.equal(true);
+      expect(profile.pointsBalance.toNumber()).to.equal(0);
+      expect(profile.wins).to.equal(3);
+      expect(profile.losses).to.equal(2);
+      expect(profile.createdAt.toNumber()).to.equal(1700000000);
+
+      const balance = await provider.connection.getTokenAccountBalance(ownerTokenAccount);
+      expect(balance.value.amount).to.equal("500");
+    });
+  });
+
+  describe("Draws and Cancels", () => {
+    const pointsStake = new anchor.BN(50);
+    const solStake = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+
+    // Accounts shared by accept_draw and accept_cancel for SOL and ledger points games
+    const settleAccounts = (gameId: string, player1: Keypair, player2: Keypair, user: Keypair) => ({
+      game: gamePdaOf(gameId),
+      config: globalConfigPda,
+      player1Profile: userProfilePda(player1.publicKey),
+      player2Profile: userProfilePda(player2.publicKey),
+      player1: player1.publicKey,
+      player2: player2.publicKey,
+      platformWallet: provider.publicKey,
+      mint: null,
+      escrow: null,
+      player1TokenAccount: null,
+      player2TokenAccount: null,
+      platformTokenAccount: null,
+      tokenProgram: null,
+      user: user.publicKey,
+    });
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should refund both points stakes on an agreed draw", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const gameId = uniqueId("draw");
+      await createGame(gameId, player1, pointsStake, { points: {} });
+      await joinGame(gameId, player2);
+      expect(await pointsOf(player1)).to.equal(250);
+
+      await program.methods
+        .offerDraw(gameId)
+        .accounts({ game: gamePdaOf(gameId), user: player1.publicKey })
+        .signers([player1])
+        .rpc();
+      await program.methods
+        .acceptDraw(gameId)
+        .accountsPartial(settleAccounts(gameId, player1, player2, player2))
+        .signers([player2])
+        .rpc();
+
+      const game = await program.account.game.fetch(gamePdaOf(gameId));
+      expect(game.gameStatus).to.deep.equal({ drawn: {} });
+      for (const player of [player1, player2]) {
+        const profile = await program.account.userProfile.fetch(userProfilePda(player.publicKey));
+        expect(profile.pointsBalance.toNumber()).to.equal(300);
+        expect(profile.activePointsGames).to.equal(0);
+        expect(profile.draws).to.equal(1);
+      }
+    });
+
+    it("Should refund both SOL stakes on an agreed cancel", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const gameId = uniqueId("cancel");
+      await createGame(gameId, player1, solStake, { sol: {} });
+      await joinGame(gameId, player2);
+
+      await program.methods
+        .proposeCancel(gameId)
+        .accounts({ game: gamePdaOf(gameId), user: player2.publicKey })
+        .signers([player2])
+        .rpc();
+
+      const before = {
+        player1: await getBalance(player1.publicKey),
+        player2: await getBalance(player2.publicKey),
+        game: await getBalance(gamePdaOf(gameId)),
+      };
+      await program.methods
+        .acceptCancel(gameId)
+        .accountsPartial(settleAccounts(gameId, player1, player2, player1))
+        .signers([player1])
+        .rpc();
+
+      // The provider pays the transaction fee, so both players get exactly their stake back
+      expect(await getBalance(player1.publicKey)).to.equal(before.player1 + solStake.toNumber());
+      expect(await getBalance(player2.publicKey)).to.equal(before.player2 + solStake.toNumber());
+      expect(await getBalance(gamePdaOf(gameId))).to.equal(before.game - 2 * solStake.toNumber());
+      const game = await program.account.game.fetch(gamePdaOf(gameId));
+      expect(game.gameStatus).to.deep.equal({ cancelled: {} });
+    });
+
+    it("Should keep a series' points locked when a child game is drawn", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const seriesId = uniqueId("draw_series");
+      const [seriesPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("series"), Buffer.from(seriesId)],
+        program.programId
+      );
+      await program.methods
+        .createSeries(seriesId, pointsStake, { points: {} }, 1, 1, new anchor.BN(600))
+        .accounts({
+          series: seriesPda,
+          userProfile: userProfilePda(player1.publicKey),
+          user: player1.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player1])
+        .rpc();
+      await program.methods
+        .joinSeries(seriesId)
+        .accounts({
+          series: seriesPda,
+          userProfile: userProfilePda(player2.publicKey),
+          user: player2.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player2])
+        .rpc();
+
+      const startGame = async (gameId: string) => {
+        await program.methods
+          .startSeriesGame(seriesId, gameId)
+          .accounts({
+            series: seriesPda,
+            game: gamePdaOf(gameId),
+            user: player1.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([player1])
+          .rpc();
+      };
+      const recordGame = async (gameId: string) => {
+        await program.methods
+          .recordSeriesGame(seriesId, gameId)
+          .accounts({ series: seriesPda, game: gamePdaOf(gameId), player1: player1.publicKey })
+          .rpc();
+      };
+
+      const drawnId = uniqueId("drawn_child");
+      await startGame(drawnId);
+      await program.methods
+        .offerDraw(drawnId)
+        .accounts({ game: gamePdaOf(drawnId), user: player1.publicKey })
+        .signers([player1])
+        .rpc();
+      await program.methods
+        .acceptDraw(drawnId)
+        .accountsPartial(settleAccounts(drawnId, player1, player2, player2))
+        .signers([player2])
+        .rpc();
+
+      // The series still holds both stakes, so neither lock is released
+      for (const player of [player1, player2]) {
+        const profile = await program.account.userProfile.fetch(userProfilePda(player.publicKey));
+        expect(profile.pointsBalance.toNumber()).to.equal(250);
+        expect(profile.activePointsGames).to.equal(1);
+      }
+      await recordGame(drawnId);
+
+      const decidingId = uniqueId("deciding_child");
+      await startGame(decidingId);
+      await playWinningRound(decidingId, player1, player2, player1);
+      await recordGame(decidingId);
+      await program.methods
+        .finalizeSeries(seriesId)
+        .accounts({
+          series: seriesPda,
+          config: globalConfigPda,
+          player1Profile: userProfilePda(player1.publicKey),
+          player2Profile: userProfilePda(player2.publicKey),
+          player1: player1.publicKey,
+          player2: player2.publicKey,
+          platformWallet: provider.publicKey,
+          user: provider.publicKey,
+        })
+        .rpc();
+
+      expect(await pointsOf(player1)).to.equal(350);
+      expect(await pointsOf(player2)).to.equal(250);
+      for (const player of [player1, player2]) {
+        const profile = await program.account.userProfile.fetch(userProfilePda(player.publicKey));
+        expect(profile.activePointsGames).to.equal(0);
+      }
+    });
+
+    it("Should not let a player accept their own draw offer", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const gameId = uniqueId("own_draw");
+      await createGame(gameId, player1, pointsStake, { points: {} });
+      await joinGame(gameId, player2);
+      await program.methods
+        .offerDraw(gameId)
+        .accounts({ game: gamePdaOf(gameId), user: player1.publicKey })
+        .signers([player1])
+        .rpc();
+
+      try {
+        await program.methods
+          .acceptDraw(gameId)
+          .accountsPartial(settleAccounts(gameId, player1, player2, player1))
+          .signers([player1])
+          .rpc();
+        expect.fail("Should have rejected accepting an own offer");
+      } catch (error) {
+        expect(error.toString()).to.include("CannotAcceptOwnOffer");
+      }
+    });
+  });
+
+  describe("Rematches", () => {
+    const stake = new anchor.BN(50);
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should start a linked rematch and collect both stakes again", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const gameId = uniqueId("first");
+      await createGame(gameId, player1, stake, { points: {} });
+      await joinGame(gameId, player2);
+      await playWinningRound(gameId, player1, player2, player1);
+
+      await program.methods
+        .requestRematch(gameId)
+        .accounts({ game: gamePdaOf(gameId), user: player2.publicKey })
+        .signers([player2])
+        .rpc();
+
+      const before = { player1: await pointsOf(player1), player2: await pointsOf(player2) };
+      const rematchId = uniqueId("rematch");
+      await program.methods
+        .acceptRematch(gameId, rematchId)
+        .accounts({
+          game: gamePdaOf(gameId),
+          newGame: gamePdaOf(rematchId),
+          userProfile: userProfilePda(player1.publicKey),
+          user: player1.publicKey,
+          requesterProfile: userProfilePda(player2.publicKey),
+          requester: player2.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player1, player2])
+        .rpc();
+
+      const previous = await program.account.game.fetch(gamePdaOf(gameId));
+      const rematch = await program.account.game.fetch(gamePdaOf(rematchId));
+      expect(previous.nextGame.toString()).to.equal(gamePdaOf(rematchId).toString());
+      expect(previous.rematchRequestedBy).to.be.null;
+      expect(rematch.previousGame.toString()).to.equal(gamePdaOf(gameId).toString());
+      expect(rematch.gameStatus).to.deep.equal({ inProgress: {} });
+      expect(rematch.player1.toString()).to.equal(player1.publicKey.toString());
+      expect(rematch.player2.toString()).to.equal(player2.publicKey.toString());
+      expect(rematch.stakeAmount.toNumber()).to.equal(stake.toNumber());
+      expect(await pointsOf(player1)).to.equal(before.player1 - stake.toNumber());
+      expect(await pointsOf(player2)).to.equal(before.player2 - stake.toNumber());
+
+      // A game is only rematched once
+      try {
+        await program.methods
+          .requestRematch(gameId)
+          .accounts({ game: gamePdaOf(gameId), user: player2.publicKey })
+          .signers([player2])
+          .rpc();
+        expect.fail("Should have rejected a second rematch");
+      } catch (error) {
+        expect(error.toString()).to.include("RematchAlreadyPlayed");
+      }
+    });
+  });
+
+  describe("Series", () => {
+    const stake = new anchor.BN(50);
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should pay a points series to its winner", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const seriesId = uniqueId("series");
+      const [seriesPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("series"), Buffer.from(seriesId)],
+        program.programId
+      );
+
+      await program.methods
+        .createSeries(seriesId, stake, { points: {} }, 1, 1, new anchor.BN(600))
+        .accounts({
+          series: seriesPda,
+          userProfile: userProfilePda(player1.publicKey),
+          user: player1.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player1])
+        .rpc();
+      await program.methods
+        .joinSeries(seriesId)
+        .accounts({
+          series: seriesPda,
+          userProfile: userProfilePda(player2.publicKey),
+          user: player2.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player2])
+        .rpc();
+
+      const gameId = uniqueId("series_game");
+      await program.methods
+        .startSeriesGame(seriesId, gameId)
+        .accounts({
+          series: seriesPda,
+          game: gamePdaOf(gameId),
+          user: player1.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player1])
+        .rpc();
+      await playWinningRound(gameId, player1, player2, player2);
+      await program.methods
+        .recordSeriesGame(seriesId, gameId)
+        .accounts({ series: seriesPda, game: gamePdaOf(gameId), player1: player1.publicKey })
+        .rpc();
+
+      const series = await program.account.series.fetch(seriesPda);
+      expect(series.status).to.deep.equal({ finished: {} });
+      expect(series.winner.toString()).to.equal(player2.publicKey.toString());
+
+      await program.methods
+        .finalizeSeries(seriesId)
+        .accounts({
+          series: seriesPda,
+          config: globalConfigPda,
+          player1Profile: userProfilePda(player1.publicKey),
+          player2Profile: userProfilePda(player2.publicKey),
+          player1: player1.publicKey,
+          player2: player2.publicKey,
+          platformWallet: provider.publicKey,
+          user: provider.publicKey,
+        })
+        .rpc();
+
+      // The winner takes both stakes; points series carry no platform fee
+      const winner = await program.account.userProfile.fetch(userProfilePda(player2.publicKey));
+      const loser = await program.account.userProfile.fetch(userProfilePda(player1.publicKey));
+      expect(winner.pointsBalance.toNumber()).to.equal(350);
+      expect(loser.pointsBalance.toNumber()).to.equal(250);
+      expect(winner.wins).to.equal(1);
+      expect(loser.losses).to.equal(1);
+      expect(winner.activePointsGames).to.equal(0);
+      expect(loser.activePointsGames).to.equal(0);
+      expect(await provider.connection.getAccountInfo(seriesPda)).to.be.null;
+    });
+
+    it("Should draw a series after three games without a winner and refund both stakes", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const seriesId = uniqueId("drawn_series");
+      const [seriesPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("series"), Buffer.from(seriesId)],
+        program.programId
+      );
+
+      await program.methods
+        .createSeries(seriesId, stake, { points: {} }, 2, 1, new anchor.BN(600))
+        .accounts({
+          series: seriesPda,
+          userProfile: userProfilePda(player1.publicKey),
+          user: player1.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player1])
+        .rpc();
+      await program.methods
+        .joinSeries(seriesId)
+        .accounts({
+          series: seriesPda,
+          userProfile: userProfilePda(player2.publicKey),
+          user: player2.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player2])
+        .rpc();
+
+      for (let i = 0; i < 3; i++) {
+        const gameId = uniqueId("undecided");
+        await program.methods
+          .startSeriesGame(seriesId, gameId)
+          .accounts({
+            series: seriesPda,
+            game: gamePdaOf(gameId),
+            user: player1.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([player1])
+          .rpc();
+        await program.methods
+          .proposeCancel(gameId)
+          .accounts({ game: gamePdaOf(gameId), user: player1.publicKey })
+          .signers([player1])
+          .rpc();
+        await program.methods
+          .acceptCancel(gameId)
+          .accountsPartial({
+            game: gamePdaOf(gameId),
+            config: globalConfigPda,
+            player1Profile: userProfilePda(player1.publicKey),
+            player2Profile: userProfilePda(player2.publicKey),
+            player1: player1.publicKey,
+            player2: player2.publicKey,
+            platformWallet: provider.publicKey,
+            mint: null,
+            escrow: null,
+            player1TokenAccount: null,
+            player2TokenAccount: null,
+            platformTokenAccount: null,
+            tokenProgram: null,
+            user: player2.publicKey,
+          })
+          .signers([player2])
+          .rpc();
+        await program.methods
+          .recordSeriesGame(seriesId, gameId)
+          .accounts({ series: seriesPda, game: gamePdaOf(gameId), player1: player1.publicKey })
+          .rpc();
+      }
+
+      const series = await program.account.series.fetch(seriesPda);
+      expect(series.status).to.deep.equal({ drawn: {} });
+      expect(series.gamesPlayed).to.equal(3);
+
+      await program.methods
+        .finalizeSeries(seriesId)
+        .accounts({
+          series: seriesPda,
+          config: globalConfigPda,
+          player1Profile: userProfilePda(player1.publicKey),
+          player2Profile: userProfilePda(player2.publicKey),
+          player1: player1.publicKey,
+          player2: player2.publicKey,
+          platformWallet: provider.publicKey,
+          user: provider.publicKey,
+        })
+        .rpc();
+
+      for (const player of [player1, player2]) {
+        const profile = await program.account.userProfile.fetch(userProfilePda(player.publicKey));
+        expect(profile.pointsBalance.toNumber()).to.equal(300);
+        expect(profile.activePointsGames).to.equal(0);
+        expect(profile.draws).to.equal(1);
+      }
+      expect(await provider.connection.getAccountInfo(seriesPda)).to.be.null;
+    });
+  });
+
+  describe("Tournaments", () => {
+    const stake = new anchor.BN(50);
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should split a tournament prize pool and pay each prize once", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const tournamentId = uniqueId("cup");
+      const [tournamentPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("tournament"), Buffer.from(tournamentId)],
+        program.programId
+      );
+
+      await program.methods
+        .createTournament(
+          tournamentId,
+          { points: {} },
+          stake,
+          2,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+          new anchor.BN(600),
+          [1, 1, 1, 1],
+          [7000, 3000, 0]
+        )
+        .accounts({
+          tournament: tournamentPda,
+          config: globalConfigPda,
+          admin: provider.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      for (const player of [player1, player2]) {
+        await program.methods
+          .registerTournament(tournamentId)
+          .accounts({
+            tournament: tournamentPda,
+            userProfile: userProfilePda(player.publicKey),
+            user: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([player])
+          .rpc();
+      }
+
+      // Full, so it can start before registration ends; two players meet in the final
+      await program.methods
+        .startTournament(tournamentId)
+        .accounts({ tournament: tournamentPda, user: provider.publicKey })
+        .rpc();
+      const gameId = uniqueId("final");
+      await program.methods
+        .startTournamentMatch(tournamentId, 0, gameId)
+        .accounts({
+          tournament: tournamentPda,
+          game: gamePdaOf(gameId),
+          user: provider.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      await playWinningRound(gameId, player1, player2, player1);
+      await program.methods
+        .recordTournamentMatch(tournamentId, 0, gameId)
+        .accounts({
+          tournament: tournamentPda,
+          game: gamePdaOf(gameId),
+          player1: player1.publicKey,
+          player1Profile: userProfilePda(player1.publicKey),
+          player2Profile: userProfilePda(player2.publicKey),
+        })
+        .rpc();
+
+      const tournament = await program.account.tournament.fetch(tournamentPda);
+      expect(tournament.status).to.deep.equal({ finished: {} });
+      expect(tournament.bracket[0].toString()).to.equal(player1.publicKey.toString());
+
+      const claim = (player: Keypair) =>
+        program.methods
+          .claimTournamentPayout(tournamentId)
+          .accounts({
+            tournament: tournamentPda,
+            userProfile: userProfilePda(player.publicKey),
+            user: player.publicKey,
+          })
+          .signers([player])
+          .rpc();
+      await claim(player1);
+      await claim(player2);
+
+      // 70% of the 100 point pool to the champion, 30% to the runner-up
+      expect(await pointsOf(player1)).to.equal(250 + 70);
+      expect(await pointsOf(player2)).to.equal(250 + 30);
+
+      try {
+        await claim(player1);
+        expect.fail("Should have rejected a second claim");
+      } catch (error) {
+        expect(error.toString()).to.include("AlreadyClaimed");
+      }
+    });
+  });
+
+  describe("Battle Royale", () => {
+    const stake = new anchor.BN(50);
+    const moves = [{ rock: {} }, { paper: {} }, { scissors: {} }];
+    const [rock, paper, scissors] = [0, 1, 2];
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    const royalePdaOf = (gameId: string) =>
+      PublicKey.findProgramAddressSync([Buffer.from("battle_royale"), Buffer.from(gameId)], program.programId)[0];
+
+    // Started points royale with the first player as creator
+    const startRoyale = async (players: Keypair[], moveTimeout = 600) => {
+      const gameId = uniqueId("royale");
+      const [creator, ...joiners] = players;
+      await program.methods
+        .createBattleRoyale(gameId, stake, { points: {} }, players.length, new anchor.BN(moveTimeout))
+        .accounts({
+          battleRoyale: royalePdaOf(gameId),
+          userProfile: userProfilePda(creator.publicKey),
+          user: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([creator])
+        .rpc();
+      for (const player of joiners) {
+        await program.methods
+          .joinBattleRoyale(gameId)
+          .accounts({
+            battleRoyale: royalePdaOf(gameId),
+            userProfile: userProfilePda(player.publicKey),
+            user: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([player])
+          .rpc();
+      }
+      await program.methods
+        .startBattleRoyale(gameId)
+        .accounts({ battleRoyale: royalePdaOf(gameId), user: creator.publicKey })
+        .signers([creator])
+        .rpc();
+      return gameId;
+    };
+
+    const commitRoyaleMove = async (gameId: string, player: Keypair, move: number) => {
+      await program.methods
+        .commitBattleRoyaleMove(gameId, Array.from(createMoveCommitment(move, BigInt(777))))
+        .accounts({ battleRoyale: royalePdaOf(gameId), user: player.publicKey })
+        .signers([player])
+        .rpc();
+    };
+
+    // Every surviving player commits, then reveals, the given move
+    const playRoyaleRound = async (gameId: string, round: [Keypair, number][]) => {
+      for (const [player, move] of round) {
+        await commitRoyaleMove(gameId, player, move);
+      }
+      for (const [player, move] of round) {
+        await program.methods
+          .revealBattleRoyaleMove(gameId, moves[move], new anchor.BN(777))
+          .accounts({ battleRoyale: royalePdaOf(gameId), user: player.publicKey })
+          .signers([player])
+          .rpc();
+      }
+    };
+
+    const finalizeRoyale = async (gameId: string, players: Keypair[]) => {
+      await program.methods
+        .finalizeBattleRoyale(gameId)
+        .accounts({
+          battleRoyale: royalePdaOf(gameId),
+          config: globalConfigPda,
+          creator: players[0].publicKey,
+          platformWallet: provider.publicKey,
+          user: provider.publicKey,
+        })
+        .remainingAccounts(
+          players.flatMap((player) => [
+            { pubkey: userProfilePda(player.publicKey), isSigner: false, isWritable: true },
+            { pubkey: player.publicKey, isSigner: false, isWritable: true },
+          ])
+        )
+        .rpc();
+    };
+
+    it("Should eliminate the losing move each round and pay the last player standing", async () => {
+      const players = [await createPlayer(), await createPlayer(), await createPlayer()];
+      const [alice, bob, carol] = players;
+      const gameId = await startRoyale(players);
+
+      await playRoyaleRound(gameId, [
+        [alice, rock],
+        [bob, rock],
+        [carol, scissors],
+      ]);
+      let royale = await program.account.battleRoyale.fetch(royalePdaOf(gameId));
+      expect(royale.round).to.equal(2);
+      expect(royale.alive).to.equal(0b011);
+
+      await playRoyaleRound(gameId, [
+        [alice, paper],
+        [bob, rock],
+      ]);
+      royale = await program.account.battleRoyale.fetch(royalePdaOf(gameId));
+      expect(royale.status).to.deep.equal({ finished: {} });
+      expect(royale.winner.toString()).to.equal(alice.publicKey.toString());
+
+      await finalizeRoyale(gameId, players);
+      expect(await pointsOf(alice)).to.equal(300 - 50 + 150);
+      expect(await pointsOf(bob)).to.equal(250);
+      expect(await pointsOf(carol)).to.equal(250);
+      const aliceProfile = await program.account.userProfile.fetch(userProfilePda(alice.publicKey));
+      expect(aliceProfile.wins).to.equal(1);
+      expect(aliceProfile.activePointsGames).to.equal(0);
+      const carolProfile = await program.account.userProfile.fetch(userProfilePda(carol.publicKey));
+      expect(carolProfile.losses).to.equal(1);
+      expect(carolProfile.activePointsGames).to.equal(0);
+      expect(await provider.connection.getAccountInfo(royalePdaOf(gameId))).to.be.null;
+    });
+
+    it("Should eliminate nobody when all three moves or only one move are played", async () => {
+      const players = [await createPlayer(), await createPlayer(), await createPlayer()];
+      const [alice, bob, carol] = players;
+      const gameId = await startRoyale(players);
+
+      await playRoyaleRound(gameId, [
+        [alice, rock],
+        [bob, paper],
+        [carol, scissors],
+      ]);
+      let royale = await program.account.battleRoyale.fetch(royalePdaOf(gameId));
+      expect(royale.round).to.equal(2);
+      expect(royale.alive).to.equal(0b111);
+
+      await playRoyaleRound(gameId, [
+        [alice, paper],
+        [bob, paper],
+        [carol, paper],
+      ]);
+      royale = await program.account.battleRoyale.fetch(royalePdaOf(gameId));
+      expect(royale.round).to.equal(3);
+      expect(royale.alive).to.equal(0b111);
+      expect(royale.status).to.deep.equal({ inProgress: {} });
+    });
+
+    it("Should drop players who miss the deadline and split the pot when nobody acts", async () => {
+      const players = [await createPlayer(), await createPlayer(), await createPlayer()];
+      const [alice] = players;
+      const resolveTimeout = (gameId: string) =>
+        program.methods
+          .resolveBattleRoyaleTimeout(gameId)
+          .accounts({ battleRoyale: royalePdaOf(gameId), user: provider.publicKey })
+          .rpc();
+
+      // Only alice commits before the deadline, so she is the last player standing
+      const gameId = await startRoyale(players, 5);
+      await commitRoyaleMove(gameId, alice, rock);
+      try {
+        await resolveTimeout(gameId);
+        expect.fail("Should have waited for the deadline");
+      } catch (error) {
+        expect(error.toString()).to.include("TimeoutNotReached");
+      }
+      await new Promise((resolve) => setTimeout(resolve, 6000));
+      await resolveTimeout(gameId);
+      let royale = await program.account.battleRoyale.fetch(royalePdaOf(gameId));
+      expect(royale.status).to.deep.equal({ finished: {} });
+      expect(royale.winner.toString()).to.equal(alice.publicKey.toString());
+      await finalizeRoyale(gameId, players);
+      expect(await pointsOf(alice)).to.equal(400);
+
+      // Nobody commits, so the game is drawn and every survivor gets the stake back
+      const drawnId = await startRoyale(players, 5);
+      await new Promise((resolve) => setTimeout(resolve, 6000));
+      await resolveTimeout(drawnId);
+      royale = await program.account.battleRoyale.fetch(royalePdaOf(drawnId));
+      expect(royale.status).to.deep.equal({ drawn: {} });
+      await finalizeRoyale(drawnId, players);
+      expect(await pointsOf(alice)).to.equal(400);
+      expect(await pointsOf(players[1])).to.equal(250);
+      const profile = await program.account.userProfile.fetch(userProfilePda(players[2].publicKey));
+      expect(profile.draws).to.equal(1);
+      expect(profile.losses).to.equal(1);
+    });
+  });
+
+  describe("Team Games", () => {
+    const stake = new anchor.BN(50);
+    const moves = [{ rock: {} }, { paper: {} }, { scissors: {} }];
+    const [rock, paper, scissors] = [0, 1, 2];
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    const teamGamePdaOf = (gameId: string) =>
+      PublicKey.findProgramAddressSync([Buffer.from("team_game"), Buffer.from(gameId)], program.programId)[0];
+
+    // Full points game: the first two players on team 0, the last two on team 1
+    const startTeamGame = async (players: Keypair[], decision: { captain: {} } | { majorityVote: {} }) => {
+      const gameId = uniqueId("team");
+      await program.methods
+        .createTeamGame(gameId, stake, { points: {} }, 1, decision, new anchor.BN(600))
+        .accounts({
+          teamGame: teamGamePdaOf(gameId),
+          userProfile: userProfilePda(players[0].publicKey),
+          user: players[0].publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([players[0]])
+        .rpc();
+      for (const [index, player] of players.entries()) {
+        if (index === 0) continue;
+        await program.methods
+          .joinTeamGame(gameId, index < 2 ? 0 : 1)
+          .accounts({
+            teamGame: teamGamePdaOf(gameId),
+            userProfile: userProfilePda(player.publicKey),
+            user: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([player])
+          .rpc();
+      }
+      return gameId;
+    };
+
+    const voteCaptain = async (gameId: string, voter: Keypair, candidate: Keypair) => {
+      await program.methods
+        .voteCaptain(gameId, candidate.publicKey)
+        .accounts({ teamGame: teamGamePdaOf(gameId), user: voter.publicKey })
+        .signers([voter])
+        .rpc();
+    };
+
+    const commitTeamMove = async (gameId: string, player: Keypair, move: number, nonce: number) => {
+      await program.methods
+        .commitTeamMove(gameId, Array.from(createMoveCommitment(move, BigInt(nonce))))
+        .accounts({ teamGame: teamGamePdaOf(gameId), user: player.publicKey })
+        .signers([player])
+        .rpc();
+    };
+
+    // Every voter commits, then reveals, the given move with the given nonce
+    const playTeamRound = async (gameId: string, votes: [Keypair, number, number][]) => {
+      for (const [player, move, nonce] of votes) {
+        await commitTeamMove(gameId, player, move, nonce);
+      }
+      for (const [player, move, nonce] of votes) {
+        await program.methods
+          .revealTeamMove(gameId, moves[move], new anchor.BN(nonce))
+          .accounts({ teamGame: teamGamePdaOf(gameId), user: player.publicKey })
+          .signers([player])
+          .rpc();
+      }
+    };
+
+    const finalizeTeamGame = async (gameId: string, players: Keypair[]) => {
+      await program.methods
+        .finalizeTeamGame(gameId)
+        .accounts({
+          teamGame: teamGamePdaOf(gameId),
+          config: globalConfigPda,
+          team0Player1Profile: userProfilePda(players[0].publicKey),
+          team0Player2Profile: userProfilePda(players[1].publicKey),
+          team1Player1Profile: userProfilePda(players[2].publicKey),
+          team1Player2Profile: userProfilePda(players[3].publicKey),
+          team0Player1: players[0].publicKey,
+          team0Player2: players[1].publicKey,
+          team1Player1: players[2].publicKey,
+          team1Player2: players[3].publicKey,
+          creator: players[0].publicKey,
+          platformWallet: provider.publicKey,
+          user: provider.publicKey,
+        })
+        .rpc();
+    };
+
+    it("Should only let a unanimously elected captain play and split the pot across the winning team", async () => {
+      const players = [await createPlayer(), await createPlayer(), await createPlayer(), await createPlayer()];
+      const [alice, bob, carol, dave] = players;
+      const gameId = await startTeamGame(players, { captain: {} });
+
+      try {
+        await commitTeamMove(gameId, alice, rock, 1);
+        expect.fail("Should have required a captain");
+      } catch (error) {
+        expect(error.toString()).to.include("CaptainNotElected");
+      }
+
+      // A split vote elects nobody until the team agrees
+      await voteCaptain(gameId, alice, bob);
+      await voteCaptain(gameId, bob, alice);
+      let teamGame = await program.account.teamGame.fetch(teamGamePdaOf(gameId));
+      expect(teamGame.captains[0]).to.be.null;
+      await voteCaptain(gameId, bob, bob);
+      await voteCaptain(gameId, carol, carol);
+      await voteCaptain(gameId, dave, carol);
+      teamGame = await program.account.teamGame.fetch(teamGamePdaOf(gameId));
+      expect(teamGame.captains[0].toString()).to.equal(bob.publicKey.toString());
+      expect(teamGame.captains[1].toString()).to.equal(carol.publicKey.toString());
+
+      try {
+        await commitTeamMove(gameId, alice, rock, 1);
+        expect.fail("Should have refused a move from a non-captain");
+      } catch (error) {
+        expect(error.toString()).to.include("NotTeamCaptain");
+      }
+
+      await playTeamRound(gameId, [
+        [bob, rock, 11],
+        [carol, scissors, 12],
+      ]);
+      teamGame = await program.account.teamGame.fetch(teamGamePdaOf(gameId));
+      expect(teamGame.status).to.deep.equal({ finished: {} });
+      expect(teamGame.winningTeam).to.equal(0);
+
+      // Four stakes of 50 split evenly between the two winners
+      await finalizeTeamGame(gameId, players);
+      expect(await pointsOf(alice)).to.equal(300 - 50 + 100);
+      expect(await pointsOf(bob)).to.equal(300 - 50 + 100);
+      expect(await pointsOf(carol)).to.equal(250);
+      expect(await pointsOf(dave)).to.equal(250);
+      for (const player of players) {
+        const profile = await program.account.userProfile.fetch(userProfilePda(player.publicKey));
+        expect(profile.activePointsGames).to.equal(0);
+        expect(profile.totalGames).to.equal(1);
+      }
+      expect(await provider.connection.getAccountInfo(teamGamePdaOf(gameId))).to.be.null;
+    });
+
+    it("Should take the majority vote and break a split vote with the voters' nonces", async () => {
+      const players = [await createPlayer(), await createPlayer(), await createPlayer(), await createPlayer()];
+      const [alice, bob, carol, dave] = players;
+      const gameId = await startTeamGame(players, { majorityVote: {} });
+
+      // Team 0 agrees on rock; team 1 splits between paper and scissors
+      const [carolNonce, daveNonce] = [21, 22];
+      await playTeamRound(gameId, [
+        [alice, rock, 1],
+        [bob, rock, 2],
+        [carol, paper, carolNonce],
+        [dave, scissors, daveNonce],
+      ]);
+
+      // The program hashes the voters' nonces in seat order and picks among the tied moves
+      const seed = Buffer.alloc(16);
+      seed.writeBigUInt64LE(BigInt(carolNonce), 0);
+      seed.writeBigUInt64LE(BigInt(daveNonce), 8);
+      const tiebreak = crypto.createHash("sha256").update(seed).digest()[0] % 2;
+      const team1Move = [paper, scissors][tiebreak];
+      const expectedWinner = team1Move === paper ? 1 : 0;
+
+      const teamGame = await program.account.teamGame.fetch(teamGamePdaOf(gameId));
+      expect(teamGame.status).to.deep.equal({ finished: {} });
+      expect(teamGame.winningTeam).to.equal(expectedWinner);
+
+      await finalizeTeamGame(gameId, players);
+      const winners = expectedWinner === 0 ? [alice, bob] : [carol, dave];
+      for (const player of players) {
+        expect(await pointsOf(player)).to.equal(winners.includes(player) ? 350 : 250);
+      }
+    });
+  });
+
+  describe("House Games", () => {
+    const [houseVaultPda] = PublicKey.findProgramAddressSync([Buffer.from("house_vault")], program.programId);
+    const houseGamePda = (gameId: string) =>
+      PublicKey.findProgramAddressSync([Buffer.from("house_game"), Buffer.from(gameId)], program.programId)[0];
+    // A one-second move timeout lets the timeout test claim without a long wait;
+    // reveals themselves are not bound by the deadline
+    const houseParams = {
+      edgeBps: 200,
+      maxExposurePerGame: new anchor.BN(LAMPORTS_PER_SOL),
+      maxExposurePerSlot: new anchor.BN(5 * LAMPORTS_PER_SOL),
+      moveTimeout: new anchor.BN(1),
+      withdrawalCooldown: new anchor.BN(0),
+    };
+    const stake = new anchor.BN(0.1 * LAMPORTS_PER_SOL);
+    const moveArg = (move: number) => [{ rock: {} }, { paper: {} }, { scissors: {} }][move];
+    const sha256 = (...parts: Buffer[]) => crypto.createHash("sha256").update(Buffer.concat(parts)).digest();
+
+    // The house move the program derives from a revealed seed and the player's nonce
+    const houseMoveFor = (seed: Buffer, nonce: bigint) => {
+      const nonceBytes = Buffer.alloc(8);
+      nonceBytes.writeBigUInt64LE(nonce);
+      return Number(sha256(seed, nonceBytes).readBigUInt64LE(0) % BigInt(3));
+    };
+
+    const createHouseGame = async (gameId: string, player: Keypair, amount: anchor.BN, commitment: Buffer) => {
+      await program.methods
+        .createHouseGame(gameId, amount, Array.from(commitment))
+        .accounts({
+          houseGame: houseGamePda(gameId),
+          houseVault: houseVaultPda,
+          user: player.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+    };
+
+    const commitSeed = async (gameId: string, seed: Buffer) => {
+      await program.methods
+        .commitHouseSeed(gameId, Array.from(sha256(seed)))
+        .accounts({ houseGame: houseGamePda(gameId), houseVault: houseVaultPda, houseAuthority: provider.publicKey })
+        .rpc();
+    };
+
+    const revealMove = async (gameId: string, player: Keypair, move: number, nonce: bigint) => {
+      await program.methods
+        .revealHouseMove(gameId, moveArg(move), new anchor.BN(nonce.toString()))
+        .accounts({
+          houseGame: houseGamePda(gameId),
+          houseVault: houseVaultPda,
+          userProfile: userProfilePda(player.publicKey),
+          user: player.publicKey,
+        })
+        .signers([player])
+        .rpc();
+    };
+
+    before(async () => {
+      await ensureConfigs();
+      if (!(await provider.connection.getAccountInfo(houseVaultPda))) {
+        await program.methods
+          .initializeHouseVault(provider.publicKey, houseParams)
+          .accounts({
+            houseVault: houseVaultPda,
+            config: globalConfigPda,
+            admin: provider.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      } else {
+        await program.methods
+          .configureHouse(provider.publicKey, houseParams)
+          .accounts({ houseVault: houseVaultPda, config: globalConfigPda, admin: provider.publicKey })
+          .rpc();
+      }
+      // Bankroll for the payouts below
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({ fromPubkey: provider.publicKey, toPubkey: houseVaultPda, lamports: 5 * LAMPORTS_PER_SOL })
+        )
+      );
+    });
+
+    it("Should pay a player who beats the house's revealed seed", async () => {
+      const player = await createPlayer();
+      const gameId = uniqueId("house");
+      const seed = crypto.randomBytes(32);
+      const nonce = BigInt(555);
+      // Pick the move that beats whatever the seed will produce
+      const playerMove = (houseMoveFor(seed, nonce) + 1) % 3;
+
+      const vaultBefore = await program.account.houseVault.fetch(houseVaultPda);
+      await createHouseGame(gameId, player, stake, createMoveCommitment(playerMove, nonce));
+      const created = await program.account.houseGame.fetch(houseGamePda(gameId));
+      expect(created.phase).to.deep.equal({ awaitingHouseSeed: {} });
+      const vaultWhileOpen = await program.account.houseVault.fetch(houseVaultPda);
+      expect(vaultWhileOpen.reserved.toString()).to.equal(vaultBefore.reserved.add(created.exposure).toString());
+
+      await commitSeed(gameId, seed);
+      await revealMove(gameId, player, playerMove, nonce);
+      expect((await program.account.houseGame.fetch(houseGamePda(gameId))).phase).to.deep.equal({
+        awaitingHouseReveal: {},
+      });
+
+      const playerBefore = await getBalance(player.publicKey);
+      const vaultLamportsBefore = await getBalance(houseVaultPda);
+      await program.methods
+        .revealHouseSeed(gameId, Array.from(seed))
+        .accounts({
+          houseGame: houseGamePda(gameId),
+          houseVault: houseVaultPda,
+          playerProfile: userProfilePda(player.publicKey),
+          player: player.publicKey,
+          houseAuthority: provider.publicKey,
+        })
+        .rpc();
+
+      // The winner gets both stakes less the 2% edge; the vault covers the house's half
+      const game = await program.account.houseGame.fetch(houseGamePda(gameId));
+      expect(game.phase).to.deep.equal({ settled: {} });
+      expect(game.result).to.deep.equal({ player1Win: {} });
+      expect(game.maxPayout.toNumber()).to.equal(stake.muln(2).muln(98).divn(100).toNumber());
+      expect(await getBalance(player.publicKey)).to.equal(playerBefore + game.maxPayout.toNumber());
+      expect(await getBalance(houseVaultPda)).to.equal(
+        vaultLamportsBefore - game.maxPayout.sub(stake).toNumber()
+      );
+      const vaultAfter = await program.account.houseVault.fetch(houseVaultPda);
+      expect(vaultAfter.reserved.toString()).to.equal(vaultBefore.reserved.toString());
+      expect((await program.account.userProfile.fetch(userProfilePda(player.publicKey))).wins).to.equal(1);
+
+      await program.methods
+        .closeHouseGame(gameId)
+        .accounts({ houseGame: houseGamePda(gameId), user: player.publicKey })
+        .signers([player])
+        .rpc();
+      expect(await provider.connection.getAccountInfo(houseGamePda(gameId))).to.be.null;
+    });
+
+    it("Should reject a game whose payout exceeds the per-game exposure", async () => {
+      const player = await createPlayer();
+      // A 1.1 SOL stake puts 1.056 SOL of house money at risk, over the 1 SOL limit
+      try {
+        await createHouseGame(
+          uniqueId("house_big"),
+          player,
+          new anchor.BN(1.1 * LAMPORTS_PER_SOL),
+          createMoveCommitment(0, BigInt(1))
+        );
+        expect.fail("Should have rejected the exposure");
+      } catch (error) {
+        expect(error.toString()).to.include("HouseExposureExceeded");
+      }
+    });
+
+    it("Should pay the player when the house never reveals its seed", async () => {
+      const player = await createPlayer();
+      const gameId = uniqueId("house_timeout");
+      const nonce = BigInt(777);
+      await createHouseGame(gameId, player, stake, createMoveCommitment(0, nonce));
+      await commitSeed(gameId, crypto.randomBytes(32));
+      await revealMove(gameId, player, 0, nonce);
+
+      // Not claimable until the deadline has passed on chain
+      const { deadline } = await program.account.houseGame.fetch(houseGamePda(gameId));
+      while (
+        (await provider.connection.getBlockTime(await provider.connection.getSlot("confirmed"))) <=
+        deadline.toNumber()
+      ) {
+        await new Promise((resolve) => setTimeout(resolve, 500));
+      }
+
+      const playerBefore = await getBalance(player.publicKey);
+      await program.methods
+        .claimHouseTimeout(gameId)
+        .accounts({
+          houseGame: houseGamePda(gameId),
+          houseVault: houseVaultPda,
+          playerProfile: userProfilePda(player.publicKey),
+          player: player.publicKey,
+          user: provider.publicKey,
+        })
+        .rpc();
+
+      const game = await program.account.houseGame.fetch(houseGamePda(gameId));
+      expect(game.phase).to.deep.equal({ settled: {} });
+      expect(game.result).to.deep.equal({ player1Win: {} });
+      expect(await getBalance(player.publicKey)).to.equal(playerBefore + game.maxPayout.toNumber());
+    });
+  });
+
+  describe("House Liquidity", () => {
+    const [houseVaultPda] = PublicKey.findProgramAddressSync([Buffer.from("house_vault")], program.programId);
+    const lpPositionPda = (user: PublicKey) =>
+      PublicKey.findProgramAddressSync([Buffer.from("lp_position"), user.toBuffer()], program.programId)[0];
+    const houseParams = {
+      edgeBps: 200,
+      maxExposurePerGame: new anchor.BN(LAMPORTS_PER_SOL),
+      maxExposurePerSlot: new anchor.BN(5 * LAMPORTS_PER_SOL),
+      moveTimeout: new anchor.BN(600),
+      withdrawalCooldown: new anchor.BN(0),
+    };
+
+    // Vault lamports above rent, which is what the LP shares are priced against
+    const houseEquity = async () => {
+      const info = await provider.connection.getAccountInfo(houseVaultPda);
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(info.data.length);
+      return new anchor.BN(info.lamports - rent);
+    };
+
+    const openPosition = async (user: Keypair) => {
+      await program.methods
+        .openLpPosition()
+        .accounts({ lpPosition: lpPositionPda(user.publicKey), user: user.publicKey, systemProgram: SystemProgram.programId })
+        .signers([user])
+        .rpc();
+    };
+
+    const deposit = async (user: Keypair, amount: anchor.BN) => {
+      await program.methods
+        .depositLiquidity(amount)
+        .accounts({
+          houseVault: houseVaultPda,
+          lpPosition: lpPositionPda(user.publicKey),
+          user: user.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([user])
+        .rpc();
+    };
+
+    before(async () => {
+      await ensureConfigs();
+      // No withdrawal cooldown, so withdrawals complete in the same test
+      if (!(await provider.connection.getAccountInfo(houseVaultPda))) {
+        await program.methods
+          .initializeHouseVault(provider.publicKey, houseParams)
+          .accounts({
+            houseVault: houseVaultPda,
+            config: globalConfigPda,
+            admin: provider.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      } else {
+        await program.methods
+          .configureHouse(provider.publicKey, houseParams)
+          .accounts({ houseVault: houseVaultPda, config: globalConfigPda, admin: provider.publicKey })
+          .rpc();
+      }
+    });
+
+    it("Should price LP shares at the vault's equity", async () => {
+      const [early, late] = [Keypair.generate(), Keypair.generate()];
+      await fundWallet(early);
+      await fundWallet(late);
+      await openPosition(early);
+      await openPosition(late);
+
+      const amount = new anchor.BN(2 * LAMPORTS_PER_SOL);
+      const expectedShares = async (amount: anchor.BN) => {
+        const vault = await program.account.houseVault.fetch(houseVaultPda);
+        // The first deposit mints one share per lamport, less the unowned dead shares
+        return vault.totalShares.isZero()
+          ? amount.subn(1000)
+          : amount.mul(vault.totalShares).div(await houseEquity());
+      };
+
+      const earlyShares = await expectedShares(amount);
+      await deposit(early, amount);
+      expect((await program.account.lpPosition.fetch(lpPositionPda(early.publicKey))).shares.toString()).to.equal(
+        earlyShares.toString()
+      );
+
+      // House profit raises the share price, so the same deposit buys fewer shares
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({ fromPubkey: provider.publicKey, toPubkey: houseVaultPda, lamports: LAMPORTS_PER_SOL })
+        )
+      );
+      const lateShares = await expectedShares(amount);
+      await deposit(late, amount);
+      const latePosition = await program.account.lpPosition.fetch(lpPositionPda(late.publicKey));
+      expect(latePosition.shares.toString()).to.equal(lateShares.toString());
+      expect(latePosition.shares.lt(earlyShares)).to.be.true;
+
+      // Withdrawing redeems the shares at the current price, profit included
+      await program.methods
+        .requestLpWithdrawal(earlyShares)
+        .accounts({ lpPosition: lpPositionPda(early.publicKey), user: early.publicKey })
+        .signers([early])
+        .rpc();
+      const vault = await program.account.houseVault.fetch(houseVaultPda);
+      const payout = earlyShares.mul(await houseEquity()).div(vault.totalShares);
+      const balanceBefore = await getBalance(early.publicKey);
+      await program.methods
+        .completeLpWithdrawal()
+        .accounts({ houseVault: houseVaultPda, lpPosition: lpPositionPda(early.publicKey), user: early.publicKey })
+        .signers([early])
+        .rpc();
+
+      expect(payout.gt(amount)).to.be.true;
+      expect(await getBalance(early.publicKey)).to.equal(balanceBefore + payout.toNumber());
+      const position = await program.account.lpPosition.fetch(lpPositionPda(early.publicKey));
+      expect(position.shares.toNumber()).to.equal(0);
+      expect(position.totalWithdrawn.toString()).to.equal(payout.toString());
+      const vaultAfter = await program.account.houseVault.fetch(houseVaultPda);
+      expect(vaultAfter.totalShares.toString()).to.equal(vault.totalShares.sub(earlyShares).toString());
+    });
+  });
+
+
+  describe("Side Bets", () => {
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should pay the winning side pari-mutuel and nothing to the losing side", async () => {
+      const [player1, player2, backer1, backer2] = [
+        await createPlayer(),
+        await createPlayer(),
+        await createPlayer(),
+        await createPlayer(),
+      ];
+      const gameId = uniqueId("bets");
+      const gamePda = gamePdaOf(gameId);
+      const [poolPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("side_bet_pool"), gamePda.toBuffer()],
+        program.programId
+      );
+      const betPda = (bettor: Keypair) =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("side_bet"), poolPda.toBuffer(), bettor.publicKey.toBuffer()],
+          program.programId
+        )[0];
+
+      await createGame(gameId, player1, new anchor.BN(50), { points: {} });
+      await program.methods
+        .openSideBetPool(gameId)
+        .accounts({
+          pool: poolPda,
+          game: gamePda,
+          config: globalConfigPda,
+          creator: provider.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+      const bets: [Keypair, { player1: {} } | { player2: {} }, number][] = [
+        [backer1, { player1: {} }, LAMPORTS_PER_SOL],
+        [backer2, { player2: {} }, LAMPORTS_PER_SOL / 2],
+      ];
+      for (const [bettor, side, amount] of bets) {
+        await program.methods
+          .placeSideBet(gameId, side, new anchor.BN(amount))
+          .accounts({
+            bet: betPda(bettor),
+            pool: poolPda,
+            game: gamePda,
+            bettor: bettor.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([bettor])
+          .rpc();
+      }
+
+      await joinGame(gameId, player2);
+      await playWinningRound(gameId, player1, player2, player1);
+      await program.methods
+        .settleSideBets(gameId)
+        .accounts({ pool: poolPda, game: gamePda, config: globalConfigPda, platformWallet: provider.publicKey })
+        .rpc();
+      const pool = await program.account.sideBetPool.fetch(poolPda);
+      expect(pool.outcome).to.deep.equal({ player1: {} });
+
+      const claim = async (bettor: Keypair) => {
+        const before = await getBalance(poolPda);
+        await program.methods
+          .claimSideBet(gameId)
+          .accounts({ bet: betPda(bettor), pool: poolPda, game: gamePda, bettor: bettor.publicKey })
+          .signers([bettor])
+          .rpc();
+        return before - (await getBalance(poolPda));
+      };
+
+      // With no platform cut the only winning bet takes the whole pool
+      expect(await claim(backer1)).to.equal(1.5 * LAMPORTS_PER_SOL);
+      expect(await claim(backer2)).to.equal(0);
+      expect((await program.account.sideBetPool.fetch(poolPda)).openBets).to.equal(0);
+    });
+  });
+
+
+  describe("Doubling Cube", () => {
+    const stake = new anchor.BN(50);
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    const doubleAccounts = (gameId: string, user: Keypair) => ({
+      game: gamePdaOf(gameId),
+      config: globalConfigPda,
+      userProfile: userProfilePda(user.publicKey),
+      user: user.publicKey,
+      systemProgram: SystemProgram.programId,
+    });
+
+    const offerDouble = async (gameId: string, user: Keypair) => {
+      await program.methods.offerDouble(gameId).accounts(doubleAccounts(gameId, user)).signers([user]).rpc();
+    };
+
+    const finalizePointsGame = async (gameId: string, player1: Keypair, player2: Keypair) => {
+      const bystander = await createPlayer();
+      await program.methods
+        .finalizeGame(gameId)
+        .accountsPartial({
+          game: gamePdaOf(gameId),
+          player1Profile: userProfilePda(player1.publicKey),
+          player2Profile: userProfilePda(player2.publicKey),
+          player1: player1.publicKey,
+          player2: player2.publicKey,
+          platformWallet: provider.publicKey,
+          referrer: provider.publicKey,
+          referrerProfile: userProfilePda(bystander.publicKey),
+          mint: null,
+          allowedMint: null,
+          escrow: null,
+          winnerTokenAccount: null,
+          platformTokenAccount: null,
+          tokenProgram: null,
+          pointsMint: null,
+          ...(await seasonAccountsFor(player1.publicKey, player2.publicKey)),
+          ratingConfig: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    };
+
+    it("Should double the stake on accept and hand the cube to the accepter", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const gameId = uniqueId("double");
+      await createGame(gameId, player1, stake, { points: {} });
+      await joinGame(gameId, player2);
+
+      // The offer escrows the offerer's top-up straight away
+      await offerDouble(gameId, player1);
+      expect(await pointsOf(player1)).to.equal(200);
+      await program.methods
+        .acceptDouble(gameId)
+        .accounts(doubleAccounts(gameId, player2))
+        .signers([player2])
+        .rpc();
+      expect(await pointsOf(player2)).to.equal(200);
+
+      const game = await program.account.game.fetch(gamePdaOf(gameId));
+      expect(game.stakeAmount.toNumber()).to.equal(100);
+      expect(game.stakeMultiplier).to.equal(2);
+      expect(game.cubeOwner.toString()).to.equal(player2.publicKey.toString());
+      expect(game.doubleOfferedBy).to.be.null;
+
+      // Only the cube owner may redouble
+      try {
+        await offerDouble(gameId, player1);
+        expect.fail("Should have refused a double from the player without the cube");
+      } catch (error) {
+        expect(error.toString()).to.include("NotCubeOwner");
+      }
+
+      await playWinningRound(gameId, player1, player2, player1);
+      await finalizePointsGame(gameId, player1, player2);
+      expect(await pointsOf(player1)).to.equal(200 + 200);
+      expect(await pointsOf(player2)).to.equal(200);
+    });
+
+    it("Should forfeit the game at the current stake and return the top-up on decline", async () => {
+      const [player1, player2] = [await createPlayer(), await createPlayer()];
+      const gameId = uniqueId("double");
+      await createGame(gameId, player1, stake, { points: {} });
+      await joinGame(gameId, player2);
+
+      await offerDouble(gameId, player1);
+      await program.methods
+        .declineDouble(gameId)
+        .accounts({
+          game: gamePdaOf(gameId),
+          offererProfile: userProfilePda(player1.publicKey),
+          offerer: player1.publicKey,
+          user: player2.publicKey,
+        })
+        .signers([player2])
+        .rpc();
+      expect(await pointsOf(player1)).to.equal(250);
+
+      const game = await program.account.game.fetch(gamePdaOf(gameId));
+      expect(game.gameStatus).to.deep.equal({ finished: {} });
+      expect(game.winner.toString()).to.equal(player1.publicKey.toString());
+      expect(game.stakeAmount.toNumber()).to.equal(50);
+
+      await finalizePointsGame(gameId, player1, player2);
+      expect(await pointsOf(player1)).to.equal(250 + 100);
+      expect(await pointsOf(player2)).to.equal(250);
+    });
+  });
+
+  describe("SPL Token Stakes", () => {
+    const stake = 1_000_000;
+    let mint: PublicKey;
+
+    before(async () => {
+      await ensureConfigs();
+      mint = await createMint(splTokenProgramId);
+      await allowMint(mint);
+    });
+
+    // Player with a funded token account for `mint`
+    const createTokenPlayer = async () => {
+      const player = await createPlayer();
+      const tokenAccount = await createTokenAccountFor(splTokenProgramId, mint, player.publicKey);
+      await mintTokens(splTokenProgramId, mint, tokenAccount, 10 * stake);
+      return { player, tokenAccount };
+    };
+
+    it("Should escrow both token stakes and pay the winner the pot less the mint's fee", async () => {
+      const creator = await createTokenPlayer();
+      const joiner = await createTokenPlayer();
+      const platformTokenAccount = await createTokenAccountFor(splTokenProgramId, mint, provider.publicKey);
+      const gameId = uniqueId("token");
+
+      await createTokenGame(gameId, creator.player, stake, mint, splTokenProgramId, creator.tokenAccount);
+      await joinTokenGame(gameId, joiner.player, mint, splTokenProgramId, joiner.tokenAccount);
+      expect(await tokenBalance(escrowPda(gameId))).to.equal(2 * stake);
+      expect(await tokenBalance(joiner.tokenAccount)).to.equal(9 * stake);
+      const game = await program.account.game.fetch(gamePdaOf(gameId));
+      expect(game.mint.toString()).to.equal(mint.toString());
+
+      await playWinningRound(gameId, creator.player, joiner.player, joiner.player);
+      await finalizeTokenGame(
+        gameId,
+        creator.player,
+        joiner.player,
+        mint,
+        splTokenProgramId,
+        joiner.tokenAccount,
+        platformTokenAccount
+      );
+
+      const fee = (2 * stake * 500) / 10000;
+      expect(await tokenBalance(joiner.tokenAccount)).to.equal(9 * stake + 2 * stake - fee);
+      expect(await tokenBalance(platformTokenAccount)).to.equal(fee);
+      expect(await tokenBalance(creator.tokenAccount)).to.equal(9 * stake);
+      // The emptied escrow is closed
+      expect(await provider.connection.getAccountInfo(escrowPda(gameId))).to.be.null;
+    });
+
+    it("Should refund the token stake when an unjoined game is cancelled", async () => {
+      const creator = await createTokenPlayer();
+      const gameId = uniqueId("token_cancel");
+      await createTokenGame(gameId, creator.player, stake, mint, splTokenProgramId, creator.tokenAccount);
+      expect(await tokenBalance(creator.tokenAccount)).to.equal(9 * stake);
+
+      await program.methods
+        .cancelGame(gameId)
+        .accountsPartial({
+          game: gamePdaOf(gameId),
+          user: creator.player.publicKey,
+          userProfile: userProfilePda(creator.player.publicKey),
+          mint,
+          escrow: escrowPda(gameId),
+          userTokenAccount: creator.tokenAccount,
+          tokenProgram: splTokenProgramId,
+          lobby: null,
+          sideBetPool: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([creator.player])
+        .rpc();
+
+      expect(await tokenBalance(creator.tokenAccount)).to.equal(10 * stake);
+      expect(await provider.connection.getAccountInfo(escrowPda(gameId))).to.be.null;
+      expect(await provider.connection.getAccountInfo(gamePdaOf(gameId))).to.be.null;
+    });
+
+    it("Should refuse a mint whose freeze authority could lock the escrow", async () => {
+      const freezable = await createMint(splTokenProgramId, { freezeAuthority: provider.publicKey });
+      try {
+        await allowMint(freezable);
+        expect.fail("Should have refused the mint");
+      } catch (error) {
+        expect(error.toString()).to.include("HostileMint");
+      }
+    });
+  });
+
+  describe("Token-2022 Stakes", () => {
+    const stake = 1_000_000;
+    const transferFeeBps = 100;
+    // Token-2022 charges min(ceil(amount * bps / 10000), max fee) on every transfer
+    const transferFee = (amount: number) => Math.ceil((amount * transferFeeBps) / 10000);
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    const initializeTransferFeeConfig = (mint: PublicKey) => {
+      const bps = Buffer.alloc(2);
+      bps.writeUInt16LE(transferFeeBps);
+      const maxFee = Buffer.alloc(8);
+      maxFee.writeBigUInt64LE(BigInt(stake));
+      return [
+        new anchor.web3.TransactionInstruction({
+          programId: token2022ProgramId,
+          keys: [{ pubkey: mint, isSigner: false, isWritable: true }],
+          data: Buffer.concat([
+            Buffer.from([26, 0, 1]),
+            provider.publicKey.toBuffer(),
+            Buffer.from([1]),
+            provider.publicKey.toBuffer(),
+            bps,
+            maxFee,
+          ]),
+        }),
+      ];
+    };
+
+    it("Should escrow stakes net of the transfer fee and pay out what the escrow holds", async () => {
+      const mint = await createMint(token2022ProgramId, { space: 278, extensions: initializeTransferFeeConfig });
+      await allowMint(mint);
+
+      const createTokenPlayer = async () => {
+        const player = await createPlayer();
+        const tokenAccount = await createTokenAccountFor(token2022ProgramId, mint, player.publicKey, 178);
+        await mintTokens(token2022ProgramId, mint, tokenAccount, 10 * stake);
+        return { player, tokenAccount };
+      };
+      const creator = await createTokenPlayer();
+      const joiner = await createTokenPlayer();
+      const platformTokenAccount = await createTokenAccountFor(token2022ProgramId, mint, provider.publicKey, 178);
+      const gameId = uniqueId("token22");
+
+      await createTokenGame(gameId, creator.player, stake, mint, token2022ProgramId, creator.tokenAccount);
+      await joinTokenGame(gameId, joiner.player, mint, token2022ProgramId, joiner.tokenAccount);
+      const pot = 2 * (stake - transferFee(stake));
+      expect(await tokenBalance(escrowPda(gameId))).to.equal(pot);
+
+      await playWinningRound(gameId, creator.player, joiner.player, creator.player);
+      await finalizeTokenGame(
+        gameId,
+        creator.player,
+        joiner.player,
+        mint,
+        token2022ProgramId,
+        creator.tokenAccount,
+        platformTokenAccount
+      );
+
+      // The platform fee comes out of the escrowed pot, not twice the stake, and both
+      // withdrawals pay the transfer fee again
+      const platformFee = Math.floor((pot * 500) / 10000);
+      const winnerAmount = pot - platformFee;
+      expect(await tokenBalance(creator.tokenAccount)).to.equal(
+        9 * stake + winnerAmount - transferFee(winnerAmount)
+      );
+      expect(await tokenBalance(platformTokenAccount)).to.equal(platformFee - transferFee(platformFee));
+      expect(await provider.connection.getAccountInfo(escrowPda(gameId))).to.be.null;
+    });
+
+    it("Should refuse a mint with a permanent delegate", async () => {
+      const mint = await createMint(token2022ProgramId, {
+        space: 202,
+        extensions: (mint) => [
+          new anchor.web3.TransactionInstruction({
+            programId: token2022ProgramId,
+            keys: [{ pubkey: mint, isSigner: false, isWritable: true }],
+            data: Buffer.concat([Buffer.from([35]), provider.publicKey.toBuffer()]),
+          }),
+        ],
+      });
+      try {
+        await allowMint(mint);
+        expect.fail("Should have refused the mint");
+      } catch (error) {
+        expect(error.toString()).to.include("HostileMint");
+      }
+    });
+
+    it("Should refuse a Token-2022 mint with a freeze authority", async () => {
+      const mint = await createMint(token2022ProgramId, { freezeAuthority: provider.publicKey });
+      try {
+        await allowMint(mint);
+        expect.fail("Should have refused the mint");
+      } catch (error) {
+        expect(error.toString()).to.include("HostileMint");
+      }
+    });
+  });
+
+  describe("Points Faucet", () => {
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should pay the daily claim once per cooldown", async () => {
+      const user = await createPlayer();
+      const claim = () =>
+        program.methods
+          .claimDailyPoints()
+          .accountsPartial({
+            userProfile: userProfilePda(user.publicKey),
+            pointsConfig: pointsConfigPda,
+            user: user.publicKey,
+            pointsMint: null,
+            userTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([user])
+          .rpc();
+
+      await claim();
+      const profile = await program.account.userProfile.fetch(userProfilePda(user.publicKey));
+      expect(profile.pointsBalance.toNumber()).to.equal(300 + pointsParams.dailyAmount.toNumber());
+      expect(profile.claimStreak).to.equal(1);
+
+      try {
+        await claim();
+        expect.fail("Should have rejected a claim inside the cooldown");
+      } catch (error) {
+        expect(error.toString()).to.include("ClaimCooldown");
+      }
+      expect(await pointsOf(user)).to.equal(300 + pointsParams.dailyAmount.toNumber());
+    });
+  });
+
+  describe("Points Transfers", () => {
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should burn the transfer fee and enforce the daily transfer limit", async () => {
+      const [sender, recipient] = [await createPlayer(), await createPlayer()];
+      const transfer = (amount: number) =>
+        program.methods
+          .transferPoints(new anchor.BN(amount))
+          .accounts({
+            senderProfile: userProfilePda(sender.publicKey),
+            recipientProfile: userProfilePda(recipient.publicKey),
+            sender: sender.publicKey,
+            recipient: recipient.publicKey,
+            pointsConfig: pointsConfigPda,
+          })
+          .signers([sender])
+          .rpc();
+
+      // 10% of every transfer is burned
+      await transfer(60);
+      expect(await pointsOf(sender)).to.equal(240);
+      expect(await pointsOf(recipient)).to.equal(354);
+
+      try {
+        await transfer(pointsParams.transferDailyLimit.toNumber() - 60 + 1);
+        expect.fail("Should have rejected a transfer over the daily limit");
+      } catch (error) {
+        expect(error.toString()).to.include("TransferLimitExceeded");
+      }
+
+      // The rest of the allowance can still be sent
+      await transfer(pointsParams.transferDailyLimit.toNumber() - 60);
+      expect(await pointsOf(sender)).to.equal(200);
+      expect(await pointsOf(recipient)).to.equal(390);
+    });
+  });
+
+  describe("Promo Codes", () => {
+    const sha256 = (...parts: Buffer[]) => crypto.createHash("sha256").update(Buffer.concat(parts)).digest();
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should let each user redeem a promo code once", async () => {
+      const user = await createPlayer();
+      const code = uniqueId("WELCOME");
+      const [promoPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("promo"), sha256(Buffer.from(code))],
+        program.programId
+      );
+      const [redemptionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("promo_redemption"), promoPda.toBuffer(), user.publicKey.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .createPromo(
+          Array.from(sha256(Buffer.from(code))),
+          new anchor.BN(25),
+          10,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600)
+        )
+        .accounts({
+          promo: promoPda,
+          config: globalConfigPda,
+          admin: provider.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const redeem = () =>
+        program.methods
+          .redeemPromo(code)
+          .accountsPartial({
+            promo: promoPda,
+            redemption: redemptionPda,
+            userProfile: userProfilePda(user.publicKey),
+            user: user.publicKey,
+            pointsMint: null,
+            userTokenAccount: null,
+            tokenProgram: null,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([user])
+          .rpc();
+
+      await redeem();
+      expect(await pointsOf(user)).to.equal(325);
+
+      // The redemption marker already exists, so the second redemption can't be created
+      let redeemedTwice = false;
+      try {
+        await redeem();
+        redeemedTwice = true;
+      } catch (error) {
+        expect(error).to.exist;
+      }
+      expect(redeemedTwice).to.be.false;
+      expect(await pointsOf(user)).to.equal(325);
+      expect((await program.account.promo.fetch(promoPda)).redemptions).to.equal(1);
+    });
+  });
+
+  describe("Reward Distributor", () => {
+    const sha256 = (...parts: Buffer[]) => crypto.createHash("sha256").update(Buffer.concat(parts)).digest();
+
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should pay merkle rewards once and reject forged amounts", async () => {
+      const [winner, runnerUp] = [await createPlayer(), await createPlayer()];
+      const distributorId = uniqueId("weekly");
+      const [distributorPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("distributor"), Buffer.from(distributorId)],
+        program.programId
+      );
+
+      // Leaves and inner nodes carry different prefixes; sibling pairs are hashed in sorted order
+      const leaf = (index: number, player: PublicKey, amount: number) => {
+        const indexBytes = Buffer.alloc(4);
+        indexBytes.writeUInt32LE(index);
+        const amountBytes = Buffer.alloc(8);
+        amountBytes.writeBigUInt64LE(BigInt(amount));
+        return sha256(Buffer.from([0]), indexBytes, player.toBuffer(), amountBytes);
+      };
+      const node = (a: Buffer, b: Buffer) =>
+        Buffer.compare(a, b) <= 0 ? sha256(Buffer.from([1]), a, b) : sha256(Buffer.from([1]), b, a);
+
+      const rewards: [PublicKey, number][] = [
+        [winner.publicKey, 40],
+        [runnerUp.publicKey, 20],
+        [Keypair.generate().publicKey, 10],
+        [Keypair.generate().publicKey, 5],
+      ];
+      const leaves = rewards.map(([player, amount], index) => leaf(index, player, amount));
+      const left = node(leaves[0], leaves[1]);
+      const right = node(leaves[2], leaves[3]);
+      const root = node(left, right);
+      const proofs = [
+        [leaves[1], right],
+        [leaves[0], right],
+      ];
+
+      await program.methods
+        .createDistributor(
+          distributorId,
+          Array.from(root),
+          { points: {} },
+          new anchor.BN(75),
+          rewards.length,
+          new anchor.BN(Math.floor(Date.now() / 1000) + 3600)
+        )
+        .accounts({
+          distributor: distributorPda,
+          config: globalConfigPda,
+          admin: provider.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      const claim = (player: Keypair, index: number, amount: number) =>
+        program.methods
+          .claimReward(distributorId, index, new anchor.BN(amount), proofs[index].map((hash) => Array.from(hash)))
+          .accountsPartial({
+            distributor: distributorPda,
+            player: player.publicKey,
+            userProfile: userProfilePda(player.publicKey),
+            pointsMint: null,
+            userTokenAccount: null,
+            tokenProgram: null,
+          })
+          .signers([player])
+          .rpc();
+
+      await claim(winner, 0, 40);
+      expect(await pointsOf(winner)).to.equal(340);
+
+      try {
+        await claim(winner, 0, 40);
+        expect.fail("Should have rejected a second claim");
+      } catch (error) {
+        expect(error.toString()).to.include("RewardAlreadyClaimed");
+      }
+
+      try {
+        await claim(runnerUp, 1, 40);
+        expect.fail("Should have rejected a forged amount");
+      } catch (error) {
+        expect(error.toString()).to.include("InvalidProof");
+      }
+
+      await claim(runnerUp, 1, 20);
+      expect(await pointsOf(runnerUp)).to.equal(320);
+      const distributor = await program.account.distributor.fetch(distributorPda);
+      expect(distributor.totalClaimed.toNumber()).to.equal(60);
+    });
+  });
+
+  describe("Match Queue", () => {
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should match the two longest-waiting players and refund the cranker on close", async () => {
+      // Queues are global per stake, so each run uses a fresh one
+      const stake = new anchor.BN(1 + Math.floor(Math.random() * 99));
+      const stakeBytes = Buffer.alloc(8);
+      stakeBytes.writeBigUInt64LE(BigInt(stake.toString()));
+      const [queuePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("match_queue"), Buffer.from([0]), stakeBytes],
+        program.programId
+      );
+      if (!(await provider.connection.getAccountInfo(queuePda))) {
+        await program.methods
+          .createMatchQueue({ points: {} }, stake, 1, false, 0)
+          .accounts({
+            queue: queuePda,
+            config: globalConfigPda,
+            admin: provider.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .rpc();
+      }
+
+      const queueAccounts = (user: Keypair) => ({
+        queue: queuePda,
+        userProfile: userProfilePda(user.publicKey),
+        ratingConfig: null,
+        user: user.publicKey,
+        systemProgram: SystemProgram.programId,
+      });
+      const [first, leaver, second] = [await createPlayer(), await createPlayer(), await createPlayer()];
+      for (const player of [first, leaver, second]) {
+        await program.methods.enqueue().accountsPartial(queueAccounts(player)).signers([player]).rpc();
+      }
+      expect(await pointsOf(leaver)).to.equal(300 - stake.toNumber());
+
+      try {
+        await program.methods.enqueue().accountsPartial(queueAccounts(first)).signers([first]).rpc();
+        expect.fail("Should have rejected queueing twice");
+      } catch (error) {
+        expect(error.toString()).to.include("AlreadyQueued");
+      }
+
+      await program.methods.dequeue().accountsPartial(queueAccounts(leaver)).signers([leaver]).rpc();
+      expect(await pointsOf(leaver)).to.equal(300);
+      expect((await program.account.userProfile.fetch(userProfilePda(leaver.publicKey))).activePointsGames).to.equal(0);
+
+      const cranker = Keypair.generate();
+      await fundWallet(cranker);
+      const gameId = uniqueId("matched");
+      await program.methods
+        .matchPlayers(gameId)
+        .accounts({
+          queue: queuePda,
+          game: gamePdaOf(gameId),
+          cranker: cranker.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([cranker])
+        .rpc();
+
+      const queue = await program.account.matchQueue.fetch(queuePda);
+      expect(queue.entries.map((entry) => entry.player.toString())).to.not.include(first.publicKey.toString());
+      expect(queue.entries.map((entry) => entry.player.toString())).to.not.include(second.publicKey.toString());
+      const game = await program.account.game.fetch(gamePdaOf(gameId));
+      expect(game.player1.toString()).to.equal(first.publicKey.toString());
+      expect(game.player2.toString()).to.equal(second.publicKey.toString());
+      expect(game.stakeAmount.toString()).to.equal(stake.toString());
+      expect(game.gameStatus).to.deep.equal({ inProgress: {} });
+      expect(game.rentPayer.toString()).to.equal(cranker.publicKey.toString());
+
+      // Settle the game by agreement, then its rent goes back to the cranker
+      await program.methods
+        .proposeCancel(gameId)
+        .accounts({ game: gamePdaOf(gameId), user: first.publicKey })
+        .signers([first])
+        .rpc();
+      await program.methods
+        .acceptCancel(gameId)
+        .accountsPartial({
+          game: gamePdaOf(gameId),
+          config: globalConfigPda,
+          player1Profile: userProfilePda(first.publicKey),
+          player2Profile: userProfilePda(second.publicKey),
+          player1: first.publicKey,
+          player2: second.publicKey,
+          platformWallet: provider.publicKey,
+          mint: null,
+          escrow: null,
+          player1TokenAccount: null,
+          player2TokenAccount: null,
+          platformTokenAccount: null,
+          tokenProgram: null,
+          user: second.publicKey,
+        })
+        .signers([second])
+        .rpc();
+      expect(await pointsOf(first)).to.equal(300);
+      expect(await pointsOf(second)).to.equal(300);
+
+      try {
+        await program.methods
+          .closeGame(gameId)
+          .accounts({ game: gamePdaOf(gameId), rentReceiver: first.publicKey, user: provider.publicKey })
+          .rpc();
+        expect.fail("Should have refused to refund the rent to a player");
+      } catch (error) {
+        expect(error.toString()).to.include("ConstraintAddress");
+      }
+
+      const rent = await getBalance(gamePdaOf(gameId));
+      const crankerBefore = await getBalance(cranker.publicKey);
+      await program.methods
+        .closeGame(gameId)
+        .accounts({ game: gamePdaOf(gameId), rentReceiver: cranker.publicKey, user: provider.publicKey })
+        .rpc();
+      expect(await getBalance(cranker.publicKey)).to.equal(crankerBefore + rent);
+    });
+  });
+
+  describe("Lobby", () => {
+    before(async () => {
+      await ensureConfigs();
+    });
+
+    it("Should list waiting games and unlist them on join and cancel", async () => {
+      // Pages are global, so each run opens its own
+      const page = Math.floor(Math.random() * 1000000);
+      const pageBytes = Buffer.alloc(4);
+      pageBytes.writeUInt32LE(page);
+      const [lobbyPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lobby"), Buffer.from([0]), pageBytes],
+        program.programId
+      );
+      await program.methods
+        .createLobbyPage({ points: {} }, page)
+        .accounts({ lobby: lobbyPda, payer: provider.publicKey, systemProgram: SystemProgram.programId })
+        .rpc();
+      const listedGames = async () =>
+        (await program.account.lobby.fetch(lobbyPda)).entries.map((entry) => entry.game.toString());
+
+      const [creator, joiner, canceller] = [await createPlayer(), await createPlayer(), await createPlayer()];
+      const [joinedId, cancelledId] = [uniqueId("listed"), uniqueId("listed")];
+      await createGame(joinedId, creator, new anchor.BN(20), { points: {} }, lobbyPda);
+      await createGame(cancelledId, canceller, new anchor.BN(30), { points: {} }, lobbyPda);
+
+      const lobby = await program.account.lobby.fetch(lobbyPda);
+      expect(lobby.entries.length).to.equal(2);
+      expect(lobby.entries[0].game.toString()).to.equal(gamePdaOf(joinedId).toString());
+      expect(lobby.entries[0].currencyType).to.deep.equal({ points: {} });
+      expect(lobby.entries[0].stakeAmount.toNumber()).to.equal(20);
+      expect(lobby.entries[0].roundsToWin).to.equal(1);
+
+      // Listed games can't expire before the lobby TTL
+      try {
+        await program.methods
+          .expireLobbyEntry(joinedId)
+          .accounts({ game: gamePdaOf(joinedId), lobby: lobbyPda })
+          .rpc();
+        expect.fail("Should have kept a fresh game listed");
+      } catch (error) {
+        expect(error.toString()).to.include("TimeoutNotReached");
+      }
+
+      await joinGame(joinedId, joiner, lobbyPda);
+      expect(await listedGames()).to.deep.equal([gamePdaOf(cancelledId).toString()]);
+      expect((await program.account.game.fetch(gamePdaOf(joinedId))).lobby).to.be.null;
+
+      await program.methods
+        .cancelGame(cancelledId)
+        .accountsPartial({
+          game: gamePdaOf(cancelledId),
+          user: canceller.publicKey,
+          userProfile: userProfilePda(canceller.publicKey),
+          mint: null,
+          escrow: null,
+          userTokenAccount: null,
+          tokenProgram: null,
+          lobby: lobbyPda,
+          sideBetPool: null,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([canceller])
+        .rpc();
+      expect(await listedGames()).to.be.empty;
+      expect(await pointsOf(canceller)).to.equal(300);
+    });
+  });
 });